@@ -9,8 +9,10 @@ use branchless::core::repo_ext::RepoExt;
 use branchless::core::rewrite::{
     BuildRebasePlanOptions, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
 };
-use branchless::git::{CherryPickFastOptions, Commit, Diff, Repo};
+use branchless::git::{CherryPickFastOptions, Commit, Diff, DiffOptions, Repo};
+use branchless::testing::{make_git, GitInitOptions, GitWrapper};
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use eden_dag::DagAlgorithm;
 use rayon::ThreadPoolBuilder;
 
 fn get_repo() -> Repo {
@@ -19,6 +21,19 @@ fn get_repo() -> Repo {
     Repo::from_dir(&PathBuf::from(repo_dir)).unwrap()
 }
 
+/// Make a test repository with many references. Requires the same environment
+/// variables as the tests (`TEST_GIT` and `TEST_GIT_EXEC_PATH`).
+fn make_git_with_refs(num_refs: usize) -> GitWrapper {
+    let git = make_git().unwrap();
+    git.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: true,
+        run_branchless_init: false,
+    })
+    .unwrap();
+    git.populate_refs(num_refs).unwrap();
+    git
+}
+
 fn nth_parent(commit: Commit, n: usize) -> Commit {
     let mut commit = commit.clone();
     for _i in 0..n {
@@ -116,6 +131,27 @@ fn bench_cherry_pick_fast(c: &mut Criterion) {
                 &target_commit,
                 &CherryPickFastOptions {
                     reuse_parent_tree_if_possible: false,
+                    mainline: None,
+                    max_dehydrated_paths: None,
+                },
+            )
+            .unwrap();
+        });
+    });
+    group.bench_function("Repo::cherry_pick_fast (full index)", |b| {
+        let repo = get_repo();
+        let head_oid = repo.get_head_info().unwrap().oid.unwrap();
+        let head_commit = repo.find_commit_or_fail(head_oid).unwrap();
+        let target_commit = nth_parent(head_commit.clone(), 1);
+
+        b.iter(|| {
+            repo.cherry_pick_fast(
+                &head_commit,
+                &target_commit,
+                &CherryPickFastOptions {
+                    reuse_parent_tree_if_possible: false,
+                    mainline: None,
+                    max_dehydrated_paths: Some(0),
                 },
             )
             .unwrap();
@@ -147,7 +183,12 @@ fn bench_diff_fast(c: &mut Criterion) {
         let commit = repo.find_commit_or_fail(oid).unwrap();
         let effects = Effects::new_suppress_for_test(Glyphs::text());
 
-        b.iter(|| -> Option<Diff> { repo.get_patch_for_commit(&effects, &commit).unwrap() });
+        let diff_options = DiffOptions::default();
+
+        b.iter(|| -> Option<Diff> {
+            repo.get_patch_for_commit(&effects, &commit, &diff_options)
+                .unwrap()
+        });
     });
 }
 
@@ -163,6 +204,67 @@ fn bench_get_paths_touched_by_commits(c: &mut Criterion) {
     });
 }
 
+fn bench_reachability_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("is_ancestor");
+    group.sample_size(10);
+
+    let repo = get_repo();
+    let references_snapshot = repo.get_references_snapshot().unwrap();
+    let head_oid = repo.get_head_info().unwrap().oid.unwrap();
+    let head_commit = repo.find_commit_or_fail(head_oid).unwrap();
+    let earlier_commit = nth_parent(head_commit.clone(), 10000);
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let conn = repo.get_db_conn().unwrap();
+    let event_log_db = EventLogDb::new(&conn).unwrap();
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db).unwrap();
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        &effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )
+    .unwrap();
+    let commits: CommitSet = [earlier_commit.get_oid(), head_oid].into_iter().collect();
+
+    group.bench_function("Dag::make_reachability_index", |b| {
+        b.iter(|| dag.make_reachability_index(&effects, &commits).unwrap());
+    });
+    group.bench_function("DagAlgorithm::is_ancestor", |b| {
+        b.iter(|| {
+            dag.query()
+                .is_ancestor(earlier_commit.get_oid().into(), head_oid.into())
+                .unwrap()
+        });
+    });
+    group.bench_function("ReachabilityIndex::is_ancestor", |b| {
+        let reachability_index = dag.make_reachability_index(&effects, &commits).unwrap();
+        b.iter(|| {
+            reachability_index
+                .is_ancestor(earlier_commit.get_oid(), head_oid)
+                .unwrap()
+        });
+    });
+}
+
+fn bench_references(c: &mut Criterion) {
+    let mut group = c.benchmark_group("references");
+    let git = make_git_with_refs(10_000);
+    let repo = git.get_repo().unwrap();
+
+    group.bench_function("Repo::get_head_info", |b| {
+        b.iter(|| repo.get_head_info().unwrap());
+    });
+    group.bench_function("Repo::get_all_references", |b| {
+        b.iter(|| repo.get_all_references().unwrap().len());
+    });
+    group.bench_function("Repo::get_reference_targets", |b| {
+        b.iter(|| repo.get_reference_targets().unwrap());
+    });
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default().sample_size(10);
@@ -170,6 +272,8 @@ criterion_group!(
         bench_cherry_pick_fast,
         bench_diff_fast,
         bench_get_paths_touched_by_commits,
+        bench_reachability_index,
         bench_rebase_plan,
+        bench_references,
 );
 criterion_main!(benches);
@@ -3,7 +3,7 @@
 
 use std::path::PathBuf;
 
-use branchless::git::{CherryPickFastOptions, Repo};
+use branchless::git::{CherryPickFastOptions, CherryPickFastResult, Repo};
 use eyre::Context;
 
 fn main() -> eyre::Result<()> {
@@ -34,11 +34,16 @@ fn main() -> eyre::Result<()> {
             }
         };
 
-        let tree = repo.cherry_pick_fast(
+        let CherryPickFastResult {
+            tree,
+            became_empty: _,
+        } = repo.cherry_pick_fast(
             &current_commit,
             &parent_commit,
             &CherryPickFastOptions {
                 reuse_parent_tree_if_possible: false,
+                mainline: None,
+                max_dehydrated_paths: None,
             },
         )?;
 
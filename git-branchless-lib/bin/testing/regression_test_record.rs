@@ -6,7 +6,7 @@ use std::path::PathBuf;
 
 use branchless::core::effects::Effects;
 use branchless::core::formatting::Glyphs;
-use branchless::git::{hydrate_tree, process_diff_for_record, FileMode, Repo};
+use branchless::git::{hydrate_tree, process_diff_for_record, DiffOptions, FileMode, Repo};
 use bstr::ByteSlice;
 use eyre::Context;
 use git_record::{FileState, Section};
@@ -43,7 +43,15 @@ fn main() -> eyre::Result<()> {
 
         let old_tree = parent_commit.get_tree()?;
         let new_tree = current_commit.get_tree()?;
-        let diff = repo.get_diff_between_trees(&effects, Some(&old_tree), &new_tree, 0)?;
+        let diff = repo.get_diff_between_trees(
+            &effects,
+            Some(&old_tree),
+            &new_tree,
+            &DiffOptions {
+                num_context_lines: 0,
+                ..Default::default()
+            },
+        )?;
 
         let entries = {
             let mut entries = process_diff_for_record(&repo, &diff)?;
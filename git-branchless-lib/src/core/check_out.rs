@@ -2,20 +2,21 @@
 
 use std::ffi::{OsStr, OsString};
 use std::fmt::Write;
+use std::panic::AssertUnwindSafe;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use cursive::theme::BaseColor;
 use cursive::utils::markup::StyledString;
 use eyre::Context;
-use tracing::instrument;
+use tracing::{instrument, warn};
 
 use crate::git::{
     update_index, CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName,
-    Repo, Stage, UpdateIndexCommand, WorkingCopySnapshot,
+    Repo, Stage, StatusOptions, UpdateIndexCommand, WorkingCopySnapshot,
 };
 use crate::util::ExitCode;
 
-use super::config::get_undo_create_snapshots;
+use super::config::{get_ignore_submodule_dirty, get_undo_create_snapshots};
 use super::effects::Effects;
 use super::eventlog::{Event, EventLogDb, EventTransactionId};
 use super::formatting::printable_styled_string;
@@ -138,6 +139,118 @@ pub fn check_out_commit(
     }
 }
 
+/// Like `check_out_commit`, but if the checkout would overwrite uncommitted
+/// changes to tracked files, stash them first, then re-apply them after the
+/// checkout. This allows checking out commits which modify the same files as
+/// the local changes, which a plain `git checkout` would refuse to do. If the
+/// checkout wouldn't overwrite any changes, then it's performed as usual.
+///
+/// If the checkout fails, the stash is re-applied on top of the original
+/// commit. If re-applying the stash after a successful checkout produces
+/// conflicts, an error message is printed and the stash is kept, so that the
+/// user's changes aren't lost.
+#[instrument]
+pub fn check_out_commit_with_autostash(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    target: Option<CheckoutTarget>,
+    options: &CheckOutCommitOptions,
+) -> eyre::Result<ExitCode> {
+    let target_commit = match &target {
+        None => None,
+        Some(CheckoutTarget::Oid(oid)) => repo.find_commit(*oid)?,
+        Some(CheckoutTarget::Reference(reference_name)) => {
+            match repo.find_reference(reference_name)? {
+                Some(reference) => reference.peel_to_commit()?,
+                None => None,
+            }
+        }
+        // If the target can't be parsed, let `git checkout` report the error.
+        Some(CheckoutTarget::Unknown(target)) => repo.revparse_single_commit(target).ok().flatten(),
+    };
+    let would_overwrite_changes = match &target_commit {
+        Some(target_commit) => repo.would_check_out_overwrite_changes(target_commit)?,
+        None => false,
+    };
+    if !would_overwrite_changes {
+        return check_out_commit(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            event_tx_id,
+            target,
+            options,
+        );
+    }
+
+    let num_stashes_before = repo.get_stashes()?.len();
+    let exit_code = git_run_info.run(
+        effects,
+        Some(event_tx_id),
+        &[
+            "stash",
+            "push",
+            "--message",
+            "branchless: automatic stash for checkout",
+        ],
+    )?;
+    if !exit_code.is_success() {
+        return Ok(exit_code);
+    }
+    let did_stash = repo.get_stashes()?.len() > num_stashes_before;
+    if !did_stash {
+        return check_out_commit(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            event_tx_id,
+            target,
+            options,
+        );
+    }
+
+    let exit_code = check_out_commit(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        event_tx_id,
+        target,
+        &CheckOutCommitOptions {
+            render_smartlog: false,
+            ..options.clone()
+        },
+    )?;
+
+    let pop_exit_code = git_run_info.run(effects, Some(event_tx_id), &["stash", "pop"])?;
+    if !pop_exit_code.is_success() {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            printable_styled_string(
+                effects.get_glyphs(),
+                StyledString::styled(
+                    "Your uncommitted changes could not be re-applied cleanly, \
+                    so they have been kept in the stash. \
+                    Resolve the conflicts, then run: git stash drop",
+                    BaseColor::Red.light()
+                )
+            )?
+        )?;
+        return Ok(pop_exit_code);
+    }
+    if exit_code.is_success() && options.render_smartlog {
+        git_run_info.run_direct_no_wrapping(Some(event_tx_id), &["branchless", "smartlog"])
+    } else {
+        Ok(exit_code)
+    }
+}
+
 /// Create a working copy snapshot containing the working copy's current contents.
 ///
 /// The working copy contents are not changed by this operation. That is, the
@@ -157,8 +270,17 @@ pub fn create_snapshot<'repo>(
 
     let head_info = repo.get_head_info()?;
     let index = repo.get_index()?;
-    let (snapshot, _status) =
-        repo.get_status(effects, git_run_info, &index, &head_info, Some(event_tx_id))?;
+    let (snapshot, _status) = repo.get_status_with_options(
+        effects,
+        git_run_info,
+        &index,
+        &head_info,
+        Some(event_tx_id),
+        &StatusOptions {
+            ignore_submodule_dirty: get_ignore_submodule_dirty(repo)?,
+            ..Default::default()
+        },
+    )?;
     event_log_db.add_events(vec![Event::WorkingCopySnapshot {
         timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64(),
         event_tx_id,
@@ -311,3 +433,50 @@ pub fn restore_snapshot(
 
     Ok(ExitCode(0))
 }
+
+/// Snapshot the working copy and `HEAD`, then run `f`. If `f` returns an error
+/// or panics, the snapshot is restored before the error is returned or the
+/// panic is resumed. Restoring the snapshot discards any changes that `f`
+/// made to the working copy.
+///
+/// If the snapshot could not be restored, a warning is printed, and the
+/// original error from `f` is still returned.
+pub fn with_working_copy_snapshot<T>(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    f: impl FnOnce(&Repo) -> eyre::Result<T>,
+) -> eyre::Result<T> {
+    let snapshot = create_snapshot(effects, git_run_info, repo, event_log_db, event_tx_id)?;
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| f(repo)));
+    if let Ok(Ok(value)) = result {
+        return Ok(value);
+    }
+
+    match restore_snapshot(effects, git_run_info, repo, event_tx_id, &snapshot) {
+        Ok(exit_code) if exit_code.is_success() => {}
+        Ok(exit_code) => {
+            warn!(?exit_code, "Failed to restore working copy snapshot");
+            writeln!(
+                effects.get_error_stream(),
+                "branchless: failed to restore working copy snapshot {}",
+                snapshot.base_commit.get_oid()
+            )?;
+        }
+        Err(err) => {
+            warn!(?err, "Failed to restore working copy snapshot");
+            writeln!(
+                effects.get_error_stream(),
+                "branchless: failed to restore working copy snapshot {}: {err}",
+                snapshot.base_commit.get_oid()
+            )?;
+        }
+    }
+
+    match result {
+        Ok(result) => result,
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
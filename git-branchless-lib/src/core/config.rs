@@ -8,7 +8,9 @@ use console::style;
 use eyre::Context;
 use tracing::{instrument, warn};
 
-use crate::git::{ConfigRead, GitRunInfo, GitRunOpts, Repo};
+use crate::git::{
+    select_auto_comment_char, ConfigRead, GitRunInfo, GitRunOpts, Repo, DEFAULT_CHANGE_ID_TRAILER,
+};
 
 use super::effects::Effects;
 
@@ -28,8 +30,10 @@ pub fn get_core_hooks_path(repo: &Repo) -> eyre::Result<PathBuf> {
 /// - finally, default to "master"
 #[instrument]
 pub fn get_main_branch_name(repo: &Repo) -> eyre::Result<String> {
-    let config = repo.get_readonly_config()?;
+    read_main_branch_name(&repo.get_readonly_config()?)
+}
 
+fn read_main_branch_name(config: &impl ConfigRead) -> eyre::Result<String> {
     if let Some(branch_name) = config.get("branchless.core.mainBranch")? {
         return Ok(branch_name);
     }
@@ -38,25 +42,366 @@ pub fn get_main_branch_name(repo: &Repo) -> eyre::Result<String> {
         return Ok(branch_name);
     }
 
-    if let Some(branch_name) = get_default_branch_name(repo)? {
+    if let Some(branch_name) = config.get("init.defaultBranch")? {
         return Ok(branch_name);
     }
 
     Ok("master".to_string())
 }
 
-/// Get the default comment character.
+/// The `git-branchless` configuration for a repository, read all at once.
+///
+/// Commands which need several of these values should load this once and pass
+/// it around, rather than calling the individual getters in this module, each
+/// of which reads its one key from the config. The defaults for each value are
+/// the same as those of the corresponding getter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BranchlessConfig {
+    /// See `get_main_branch_name`.
+    pub main_branch_name: String,
+
+    /// See `get_undo_create_snapshots`.
+    pub undo_create_snapshots: bool,
+
+    /// See `get_restack_preserve_timestamps`.
+    pub restack_preserve_timestamps: bool,
+
+    /// See `get_commit_stamp_change_id`.
+    pub commit_stamp_change_id: bool,
+
+    /// See `get_commit_change_id_trailer`.
+    pub commit_change_id_trailer: String,
+
+    /// See `get_next_interactive`.
+    pub next_interactive: bool,
+
+    /// See `get_smartlog_reachability_index`.
+    pub smartlog_reachability_index: bool,
+
+    /// See `get_smartlog_show_remote`.
+    pub smartlog_show_remote: bool,
+
+    /// See `get_commit_descriptors_branches`.
+    pub commit_descriptors_branches: bool,
+
+    /// See `get_commit_descriptors_differential_revision`.
+    pub commit_descriptors_differential_revision: bool,
+
+    /// See `get_commit_descriptors_pull_request_trailer`.
+    pub commit_descriptors_pull_request_trailer: Option<String>,
+
+    /// See `get_commit_descriptors_relative_time`.
+    pub commit_descriptors_relative_time: bool,
+
+    /// See `get_diff_max_file_size`.
+    pub diff_max_file_size: Option<u64>,
+
+    /// See `get_diff_normalize_line_endings`.
+    pub diff_normalize_line_endings: bool,
+
+    /// See `get_ignore_submodule_dirty`.
+    pub ignore_submodule_dirty: bool,
+
+    /// See `get_cherry_pick_max_dehydrated_paths`.
+    pub cherry_pick_max_dehydrated_paths: Option<usize>,
+
+    /// Whether the `Hint::RestackWarnAbandoned` hint is enabled.
+    pub hint_restack_warn_abandoned: bool,
+
+    /// Whether the `Hint::SmartlogFixAbandoned` hint is enabled.
+    pub hint_smartlog_fix_abandoned: bool,
+
+    /// Whether the `Hint::MoveImplicitHeadArgument` hint is enabled.
+    pub hint_move_implicit_head_argument: bool,
+}
+
+impl BranchlessConfig {
+    /// Read all of the known `git-branchless` configuration values for the
+    /// repository, using a single view of its configuration.
+    #[instrument]
+    pub fn load(repo: &Repo) -> eyre::Result<Self> {
+        let config = repo.get_readonly_config()?;
+        Ok(BranchlessConfig {
+            main_branch_name: read_main_branch_name(&config)?,
+            undo_create_snapshots: read_undo_create_snapshots(&config)?,
+            restack_preserve_timestamps: read_restack_preserve_timestamps(&config)?,
+            commit_stamp_change_id: read_commit_stamp_change_id(&config)?,
+            commit_change_id_trailer: read_commit_change_id_trailer(&config)?,
+            next_interactive: read_next_interactive(&config)?,
+            smartlog_reachability_index: read_smartlog_reachability_index(&config)?,
+            smartlog_show_remote: read_smartlog_show_remote(&config)?,
+            commit_descriptors_branches: read_commit_descriptors_branches(&config)?,
+            commit_descriptors_differential_revision:
+                read_commit_descriptors_differential_revision(&config)?,
+            commit_descriptors_pull_request_trailer: read_commit_descriptors_pull_request_trailer(
+                &config,
+            )?,
+            commit_descriptors_relative_time: read_commit_descriptors_relative_time(&config)?,
+            diff_max_file_size: read_diff_max_file_size(&config)?,
+            diff_normalize_line_endings: read_diff_normalize_line_endings(&config)?,
+            ignore_submodule_dirty: read_ignore_submodule_dirty(&config)?,
+            cherry_pick_max_dehydrated_paths: read_cherry_pick_max_dehydrated_paths(&config)?,
+            hint_restack_warn_abandoned: read_hint_enabled(&config, Hint::RestackWarnAbandoned)?,
+            hint_smartlog_fix_abandoned: read_hint_enabled(&config, Hint::SmartlogFixAbandoned)?,
+            hint_move_implicit_head_argument: read_hint_enabled(
+                &config,
+                Hint::MoveImplicitHeadArgument,
+            )?,
+        })
+    }
+
+    /// Determine if a given hint is enabled.
+    pub fn is_hint_enabled(&self, hint: Hint) -> bool {
+        match hint {
+            Hint::RestackWarnAbandoned => self.hint_restack_warn_abandoned,
+            Hint::SmartlogFixAbandoned => self.hint_smartlog_fix_abandoned,
+            Hint::MoveImplicitHeadArgument => self.hint_move_implicit_head_argument,
+        }
+    }
+}
+
+/// Config keys which have been renamed, as pairs of the deprecated name and
+/// the current name. See `RepoExt::migrate_config`.
+pub const DEPRECATED_CONFIG_KEYS: &[(&str, &str)] =
+    &[("branchless.mainBranch", "branchless.core.mainBranch")];
+
+/// A change made to the `git-branchless` config file by
+/// `RepoExt::migrate_config`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigMigration {
+    /// The deprecated key was renamed to its current name.
+    Renamed {
+        /// The deprecated name of the key.
+        old_key: String,
+        /// The current name of the key.
+        new_key: String,
+        /// The value of the key.
+        value: String,
+    },
+
+    /// The deprecated key was removed, since the current key was already set
+    /// and took precedence over it.
+    Removed {
+        /// The deprecated name of the key.
+        old_key: String,
+        /// The current name of the key.
+        new_key: String,
+        /// The value of the deprecated key which was removed.
+        value: String,
+    },
+}
+
+impl std::fmt::Display for ConfigMigration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigMigration::Renamed {
+                old_key,
+                new_key,
+                value: _,
+            } => write!(f, "Renamed deprecated config key {old_key} to {new_key}"),
+            ConfigMigration::Removed {
+                old_key,
+                new_key,
+                value: _,
+            } => write!(
+                f,
+                "Removed deprecated config key {old_key} (overridden by {new_key})"
+            ),
+        }
+    }
+}
+
+/// Get the minimum length to which commit hashes should be abbreviated, as
+/// configured by `core.abbrev`. Defaults to 7, which is also used for the value
+/// `auto`. The value `no` disables abbreviation.
+#[instrument]
+pub fn get_core_abbrev(repo: &Repo) -> eyre::Result<usize> {
+    const DEFAULT_ABBREV: usize = 7;
+    const MIN_ABBREV: usize = 4;
+    const MAX_ABBREV: usize = 40;
+
+    let from_config: Option<String> = repo.get_readonly_config()?.get("core.abbrev")?;
+    let abbrev = match from_config.as_deref() {
+        None | Some("auto") => DEFAULT_ABBREV,
+        Some(value) if value.eq_ignore_ascii_case("no") => MAX_ABBREV,
+        Some(value) => match value.parse::<usize>() {
+            Ok(abbrev) => abbrev.clamp(MIN_ABBREV, MAX_ABBREV),
+            Err(_) => {
+                warn!(?value, "Invalid value for core.abbrev");
+                DEFAULT_ABBREV
+            }
+        },
+    };
+    Ok(abbrev)
+}
+
+/// Config key for `get_diff_max_file_size`.
+pub const DIFF_MAX_FILE_SIZE_CONFIG_KEY: &str = "branchless.diff.maxFileSize";
+
+fn read_diff_max_file_size(config: &impl ConfigRead) -> eyre::Result<Option<u64>> {
+    let value: Option<i64> = config.get(DIFF_MAX_FILE_SIZE_CONFIG_KEY)?;
+    match value {
+        Some(size) if size > 0 => Ok(Some(size.unsigned_abs())),
+        Some(_) | None => Ok(None),
+    }
+}
+
+/// Get the size in bytes above which files are treated as binary when
+/// computing diffs, so that their contents aren't compared. This also applies
+/// to patch ID computation. The value may use the `k`, `m`, or `g` suffixes.
+/// Defaults to `None`, which uses the `libgit2` default.
+#[instrument]
+pub fn get_diff_max_file_size(repo: &Repo) -> eyre::Result<Option<u64>> {
+    read_diff_max_file_size(&repo.get_readonly_config()?)
+}
+
+/// Config key for `get_diff_normalize_line_endings`.
+pub const DIFF_NORMALIZE_LINE_ENDINGS_CONFIG_KEY: &str = "branchless.diff.normalizeLineEndings";
+
+/// If `true`, convert CRLF line endings to LF in text files when computing the
+/// patches for commits, which are used for patch IDs. Then a commit which only
+/// converts line endings between CRLF and LF has an empty patch, rather than
+/// registering as a content change, and so has no patch ID. Other whitespace
+/// is compared as usual. Defaults to `false`, which compares contents
+/// byte-for-byte.
+///
+/// Blobs are compared as stored in the repository, so `.gitattributes` text
+/// and `eol` settings aren't applied again when diffing. If line endings were
+/// already normalized when the files were committed, this setting has no
+/// effect.
+#[instrument]
+pub fn get_diff_normalize_line_endings(repo: &Repo) -> eyre::Result<bool> {
+    read_diff_normalize_line_endings(&repo.get_readonly_config()?)
+}
+
+fn read_diff_normalize_line_endings(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or(DIFF_NORMALIZE_LINE_ENDINGS_CONFIG_KEY, false)
+}
+
+/// Config key for `get_cherry_pick_max_dehydrated_paths`.
+pub const CHERRY_PICK_MAX_DEHYDRATED_PATHS_CONFIG_KEY: &str =
+    "branchless.cherryPick.maxDehydratedPaths";
+
+fn read_cherry_pick_max_dehydrated_paths(config: &impl ConfigRead) -> eyre::Result<Option<usize>> {
+    let value: Option<i64> = config.get(CHERRY_PICK_MAX_DEHYDRATED_PATHS_CONFIG_KEY)?;
+    match value {
+        Some(num_paths) if num_paths > 0 => Ok(usize::try_from(num_paths).ok()),
+        Some(_) | None => Ok(None),
+    }
+}
+
+/// Get the maximum number of paths which a commit may touch for
+/// `Repo::cherry_pick_fast` to apply it to dehydrated trees. Commits which
+/// touch more paths are applied using the full index instead. Defaults to
+/// `None`, which means that dehydrated trees are always used.
 #[instrument]
-pub fn get_comment_char(repo: &Repo) -> eyre::Result<char> {
+pub fn get_cherry_pick_max_dehydrated_paths(repo: &Repo) -> eyre::Result<Option<usize>> {
+    read_cherry_pick_max_dehydrated_paths(&repo.get_readonly_config()?)
+}
+
+/// Config key for `get_ignore_submodule_dirty`.
+pub const IGNORE_SUBMODULE_DIRTY_CONFIG_KEY: &str = "branchless.status.ignoreSubmoduleDirty";
+
+/// If `true`, submodules whose working copies have changes, but which still
+/// point to their recorded commits, are not reported as changed when querying
+/// the working copy status. Changes to the commit which a submodule points to
+/// are always reported. Defaults to `false`.
+#[instrument]
+pub fn get_ignore_submodule_dirty(repo: &Repo) -> eyre::Result<bool> {
+    read_ignore_submodule_dirty(&repo.get_readonly_config()?)
+}
+
+fn read_ignore_submodule_dirty(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or(IGNORE_SUBMODULE_DIRTY_CONFIG_KEY, false)
+}
+
+/// Get the comment character to use when editing `message`. If
+/// `core.commentChar` is `auto`, then a character is selected which doesn't
+/// start any line of `message`, as with `select_auto_comment_char`.
+#[instrument]
+pub fn get_comment_char(repo: &Repo, message: &str) -> eyre::Result<char> {
     let from_config: Option<String> = repo.get_readonly_config()?.get("core.commentChar")?;
-    let comment_char = match from_config {
-        // Note that git also allows `core.commentChar="auto"`, which we do not currently support.
+    let comment_char = match from_config.as_deref() {
+        Some("auto") => match select_auto_comment_char(message) {
+            Some(comment_char) => comment_char,
+            None => eyre::bail!(
+                "Could not select a comment character that is not used in the current commit message"
+            ),
+        },
         Some(comment_char) => comment_char.chars().next().unwrap(),
         None => char::from(git2::DEFAULT_COMMENT_CHAR.unwrap()),
     };
     Ok(comment_char)
 }
 
+/// The kind of signature to attach to commits, as set by `gpg.format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitSigningFormat {
+    /// Sign with an OpenPGP key (`gpg.format=openpgp`, the default).
+    OpenPgp,
+
+    /// Sign with an X.509 certificate (`gpg.format=x509`).
+    X509,
+
+    /// Sign with an SSH key (`gpg.format=ssh`).
+    Ssh,
+}
+
+/// How to sign the commits that `git-branchless` creates. See
+/// `get_commit_signing`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitSigning {
+    /// The kind of signature to produce.
+    pub format: CommitSigningFormat,
+
+    /// The program to invoke to produce the signature.
+    pub program: String,
+
+    /// The key to sign with, from `user.signingKey`. If not set, OpenPGP and
+    /// X.509 signatures use the committer's identity to select a key.
+    pub signing_key: Option<String>,
+}
+
+/// Get how commits should be signed, or `None` if `commit.gpgSign` isn't set.
+///
+/// As with Git, the signing program is read from `gpg.<format>.program`
+/// (or `gpg.program` for OpenPGP signatures), and defaults to `gpg`, `gpgsm`,
+/// or `ssh-keygen`, depending on `gpg.format`.
+#[instrument]
+pub fn get_commit_signing(repo: &Repo) -> eyre::Result<Option<CommitSigning>> {
+    let config = repo.get_readonly_config()?;
+    if !config.get_or("commit.gpgSign", false)? {
+        return Ok(None);
+    }
+
+    let format: String = config.get_or("gpg.format", "openpgp".to_string())?;
+    let program: Option<String> = config.get(format!("gpg.{format}.program"))?;
+    let (format, program) = match format.as_str() {
+        "openpgp" => (
+            CommitSigningFormat::OpenPgp,
+            match program {
+                Some(program) => program,
+                None => config.get_or("gpg.program", "gpg".to_string())?,
+            },
+        ),
+        "x509" => (
+            CommitSigningFormat::X509,
+            program.unwrap_or_else(|| "gpgsm".to_string()),
+        ),
+        "ssh" => (
+            CommitSigningFormat::Ssh,
+            program.unwrap_or_else(|| "ssh-keygen".to_string()),
+        ),
+        format => eyre::bail!("Unsupported value for gpg.format: {:?}", format),
+    };
+    let signing_key: Option<String> = config.get("user.signingKey")?;
+    Ok(Some(CommitSigning {
+        format,
+        program,
+        signing_key,
+    }))
+}
+
 /// Get the commit template message, if any.
 #[instrument]
 pub fn get_commit_template(repo: &Repo) -> eyre::Result<Option<String>> {
@@ -137,45 +482,139 @@ pub fn get_editor(git_run_info: &GitRunInfo, repo: &Repo) -> eyre::Result<Option
 /// operations.
 #[instrument]
 pub fn get_undo_create_snapshots(repo: &Repo) -> eyre::Result<bool> {
-    repo.get_readonly_config()?
-        .get_or("branchless.undo.createSnapshots", true)
+    read_undo_create_snapshots(&repo.get_readonly_config()?)
+}
+
+fn read_undo_create_snapshots(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.undo.createSnapshots", true)
 }
 
 /// If `true`, when restacking a commit, do not update its timestamp to the
 /// current time.
 #[instrument]
 pub fn get_restack_preserve_timestamps(repo: &Repo) -> eyre::Result<bool> {
-    repo.get_readonly_config()?
-        .get_or("branchless.restack.preserveTimestamps", false)
+    read_restack_preserve_timestamps(&repo.get_readonly_config()?)
+}
+
+fn read_restack_preserve_timestamps(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.restack.preserveTimestamps", false)
+}
+
+/// If `true`, add a change ID trailer to commits created with `git record`, so
+/// that the commits can be identified across rewrites.
+#[instrument]
+pub fn get_commit_stamp_change_id(repo: &Repo) -> eyre::Result<bool> {
+    read_commit_stamp_change_id(&repo.get_readonly_config()?)
+}
+
+fn read_commit_stamp_change_id(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.commit.stampChangeId", false)
+}
+
+/// The name of the trailer which holds the change ID of a commit.
+#[instrument]
+pub fn get_commit_change_id_trailer(repo: &Repo) -> eyre::Result<String> {
+    read_commit_change_id_trailer(&repo.get_readonly_config()?)
+}
+
+fn read_commit_change_id_trailer(config: &impl ConfigRead) -> eyre::Result<String> {
+    config.get_or_else("branchless.commit.changeIdTrailer", || {
+        DEFAULT_CHANGE_ID_TRAILER.to_string()
+    })
 }
 
 /// If `true`, when advancing to a "next" commit, prompt interactively to
 /// if there is ambiguity in which commit to advance to.
 #[instrument]
 pub fn get_next_interactive(repo: &Repo) -> eyre::Result<bool> {
-    repo.get_readonly_config()?
-        .get_or("branchless.next.interactive", false)
+    read_next_interactive(&repo.get_readonly_config()?)
+}
+
+fn read_next_interactive(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.next.interactive", false)
+}
+
+/// If `true`, build a `ReachabilityIndex` when constructing the smartlog, to
+/// speed up ancestry queries in large commit graphs.
+#[instrument]
+pub fn get_smartlog_reachability_index(repo: &Repo) -> eyre::Result<bool> {
+    read_smartlog_reachability_index(&repo.get_readonly_config()?)
+}
+
+fn read_smartlog_reachability_index(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.smartlog.reachabilityIndex", false)
+}
+
+/// If `true`, show how far each local branch in the smartlog is ahead of or
+/// behind its upstream branch.
+#[instrument]
+pub fn get_smartlog_show_remote(repo: &Repo) -> eyre::Result<bool> {
+    read_smartlog_show_remote(&repo.get_readonly_config()?)
+}
+
+fn read_smartlog_show_remote(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.smartlog.showRemote", false)
 }
 
 /// If `true`, show branches pointing to each commit in the smartlog.
 #[instrument]
 pub fn get_commit_descriptors_branches(repo: &Repo) -> eyre::Result<bool> {
-    repo.get_readonly_config()?
-        .get_or("branchless.commitDescriptors.branches", true)
+    read_commit_descriptors_branches(&repo.get_readonly_config()?)
 }
 
-/// If `true`, show associated Phabricator commits in the smartlog.
+fn read_commit_descriptors_branches(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.commitDescriptors.branches", true)
+}
+
+/// If `true`, show associated code reviews (such as Phabricator revisions) for
+/// commits in the smartlog.
 #[instrument]
 pub fn get_commit_descriptors_differential_revision(repo: &Repo) -> eyre::Result<bool> {
-    repo.get_readonly_config()?
-        .get_or("branchless.commitDescriptors.differentialRevision", true)
+    read_commit_descriptors_differential_revision(&repo.get_readonly_config()?)
+}
+
+fn read_commit_descriptors_differential_revision(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.commitDescriptors.differentialRevision", true)
+}
+
+/// The name of the commit message trailer which holds the associated GitHub
+/// pull request for a commit, if any (for example, `Pull-Request`).
+#[instrument]
+pub fn get_commit_descriptors_pull_request_trailer(repo: &Repo) -> eyre::Result<Option<String>> {
+    read_commit_descriptors_pull_request_trailer(&repo.get_readonly_config()?)
+}
+
+fn read_commit_descriptors_pull_request_trailer(
+    config: &impl ConfigRead,
+) -> eyre::Result<Option<String>> {
+    config.get("branchless.commitDescriptors.pullRequestTrailer")
 }
 
 /// If `true`, show the age of each commit in the smartlog.
 #[instrument]
 pub fn get_commit_descriptors_relative_time(repo: &Repo) -> eyre::Result<bool> {
-    repo.get_readonly_config()?
-        .get_or("branchless.commitDescriptors.relativeTime", true)
+    read_commit_descriptors_relative_time(&repo.get_readonly_config()?)
+}
+
+fn read_commit_descriptors_relative_time(config: &impl ConfigRead) -> eyre::Result<bool> {
+    config.get_or("branchless.commitDescriptors.relativeTime", true)
+}
+
+/// If `true`, render the smartlog and other output using only ASCII
+/// characters, as with `--glyphs ascii`. Set by `branchless.glyphs = ascii`.
+/// Returns an error for values other than `ascii` and `auto`.
+#[instrument]
+pub fn get_glyphs_ascii(repo: &Repo) -> eyre::Result<bool> {
+    let glyphs: Option<String> = repo.get_readonly_config()?.get("branchless.glyphs")?;
+    match glyphs {
+        None => Ok(false),
+        Some(glyphs) if glyphs.eq_ignore_ascii_case("ascii") => Ok(true),
+        Some(glyphs) if glyphs.eq_ignore_ascii_case("auto") => Ok(false),
+        Some(glyphs) => eyre::bail!(
+            "Unsupported value for branchless.glyphs: {:?} (expected \"ascii\" or \"auto\")",
+            glyphs
+        ),
+    }
 }
 
 /// Config key for `get_restack_warn_abandoned`.
@@ -206,8 +645,11 @@ impl Hint {
 
 /// Determine if a given hint is enabled.
 pub fn get_hint_enabled(repo: &Repo, hint: Hint) -> eyre::Result<bool> {
-    repo.get_readonly_config()?
-        .get_or(hint.get_config_key(), true)
+    read_hint_enabled(&repo.get_readonly_config()?, hint)
+}
+
+fn read_hint_enabled(config: &impl ConfigRead, hint: Hint) -> eyre::Result<bool> {
+    config.get_or(hint.get_config_key(), true)
 }
 
 /// Print instructions explaining how to disable a given hint.
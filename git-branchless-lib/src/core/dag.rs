@@ -6,11 +6,15 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::iter::FromIterator;
+use std::path::PathBuf;
+use std::sync::Mutex;
 
 use eden_dag::ops::DagPersistent;
 use eden_dag::DagAlgorithm;
 use eyre::Context;
 use itertools::Itertools;
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
 use tracing::{instrument, trace, warn};
 
 use crate::core::effects::{Effects, OperationType};
@@ -89,10 +93,38 @@ pub fn union_all(commits: &[CommitSet]) -> CommitSet {
         .fold(CommitSet::empty(), |acc, elem| acc.union(elem))
 }
 
+/// A set of public commits, along with the DAG directory and main branch OID
+/// that it was computed from.
+struct PublicCommitsCacheEntry {
+    dag_dir: PathBuf,
+    main_branch_oid: NonZeroOid,
+    public_commits: CommitSet,
+}
+
+lazy_static! {
+    /// The most recently computed set of public commits, so that `Dag`s opened
+    /// by successive commands in the same process don't need to recompute it.
+    /// The ancestors of a given commit never change, so the entry is valid for
+    /// any `Dag` opened on the same DAG directory with the same main branch
+    /// OID. If the main branch moves (such as after a fetch), the key no longer
+    /// matches, and the entry is replaced. Only one entry is kept, so the cache
+    /// doesn't grow with the number of repositories or main branch updates.
+    static ref PUBLIC_COMMITS_CACHE: Mutex<Option<PublicCommitsCacheEntry>> =
+        Default::default();
+}
+
 /// Interface to access the directed acyclic graph (DAG) representing Git's
 /// commit graph. Based on the Eden SCM DAG.
 pub struct Dag {
     inner: eden_dag::Dag,
+    dag_dir: PathBuf,
+
+    /// The OID that the main branch pointed to in the references snapshot this
+    /// DAG was opened with. Used as the key for `PUBLIC_COMMITS_CACHE`.
+    main_branch_oid: NonZeroOid,
+
+    /// The memoized result of `query_public_commits`.
+    public_commits: OnceCell<CommitSet>,
 
     /// A set containing the commit which `HEAD` points to. If `HEAD` is unborn,
     /// this is an empty set.
@@ -182,6 +214,9 @@ impl Dag {
 
         Ok(Self {
             inner: dag,
+            dag_dir,
+            main_branch_oid: *main_branch_oid,
+            public_commits: Default::default(),
             head_commit,
             main_branch_commit,
             branch_commits,
@@ -362,9 +397,33 @@ impl Dag {
     }
 
     /// Return the set of commits which are public (checked into the main branch).
+    ///
+    /// The result is cached across `Dag` instances in this process, keyed by
+    /// the DAG directory and the main branch OID in the references snapshot, so
+    /// repeated calls are cheap as long as the main branch hasn't moved.
     pub fn query_public_commits(&self) -> eyre::Result<CommitSet> {
-        let public_commits = self.query().ancestors(self.main_branch_commit.clone())?;
-        Ok(public_commits)
+        let public_commits = self.public_commits.get_or_try_init(|| {
+            let mut cache = PUBLIC_COMMITS_CACHE
+                .lock()
+                .expect("Poisoned public commits cache");
+            if let Some(entry) = cache.as_ref() {
+                if entry.dag_dir == self.dag_dir && entry.main_branch_oid == self.main_branch_oid {
+                    return Ok(entry.public_commits.clone());
+                }
+            }
+
+            let public_commits = self
+                .query()
+                .ancestors(self.main_branch_commit.clone())
+                .wrap_err("Querying public commits")?;
+            *cache = Some(PublicCommitsCacheEntry {
+                dag_dir: self.dag_dir.clone(),
+                main_branch_oid: self.main_branch_oid,
+                public_commits: public_commits.clone(),
+            });
+            Ok::<_, eyre::Error>(public_commits)
+        })?;
+        Ok(public_commits.clone())
     }
 
     /// Query the set of active heads. This includes the heads of the set of
@@ -422,6 +481,39 @@ impl Dag {
         Ok(Some(path))
     }
 
+    /// Build a `ReachabilityIndex` which can answer ancestry queries between
+    /// any of the provided commits. The index also covers all commits on the
+    /// paths between them, so that ancestry which passes through commits
+    /// outside of `commits` is still detected.
+    #[instrument]
+    pub fn make_reachability_index(
+        &self,
+        effects: &Effects,
+        commits: &CommitSet,
+    ) -> eyre::Result<ReachabilityIndex> {
+        let (_effects, _progress) = effects.start_operation(OperationType::BuildReachabilityIndex);
+
+        let commits = self.query().range(commits.clone(), commits.clone())?;
+        let oids = commit_set_to_vec_unsorted(&commits)?;
+        let indexes: HashMap<NonZeroOid, usize> = oids
+            .iter()
+            .enumerate()
+            .map(|(index, oid)| (*oid, index))
+            .collect();
+        let mut parents = Vec::with_capacity(oids.len());
+        for oid in oids.iter() {
+            let mut commit_parents = Vec::new();
+            for parent in self.query().parent_names(CommitVertex::from(*oid))? {
+                let parent_oid = NonZeroOid::try_from(parent)?;
+                if let Some(parent_index) = indexes.get(&parent_oid) {
+                    commit_parents.push(*parent_index);
+                }
+            }
+            parents.push(commit_parents);
+        }
+        Ok(ReachabilityIndex::new(indexes, parents))
+    }
+
     /// Given a CommitSet, return a list of CommitSets, each representing a
     /// connected component of the set.
     ///
@@ -468,6 +560,173 @@ impl Dag {
     }
 }
 
+/// An in-memory index which answers ancestry queries between a fixed set of
+/// commits, built with `Dag::make_reachability_index`.
+///
+/// Each commit is labeled with the interval of its descendants in a spanning
+/// forest of the set (using only the first parent of each commit), so most
+/// ancestry queries are answered by comparing two intervals. If the set
+/// contains merge commits, queries not answered by the spanning forest fall
+/// back to walking the parents of the descendant commit, skipping any commits
+/// whose generation number rules them out.
+///
+/// Building the index requires a pass over all commits in the set, so it
+/// only pays off when many queries are made. See the `is_ancestor`
+/// benchmarks for a comparison with `DagAlgorithm::is_ancestor`.
+#[derive(Debug)]
+pub struct ReachabilityIndex {
+    indexes: HashMap<NonZeroOid, usize>,
+    oids: Vec<NonZeroOid>,
+    parents: Vec<Vec<usize>>,
+    generations: Vec<usize>,
+
+    /// For each commit, its pre-order position in the spanning forest and the
+    /// largest pre-order position among its descendants in the forest.
+    intervals: Vec<(usize, usize)>,
+
+    has_merges: bool,
+}
+
+impl ReachabilityIndex {
+    fn new(indexes: HashMap<NonZeroOid, usize>, parents: Vec<Vec<usize>>) -> Self {
+        let num_commits = parents.len();
+        let mut oids = vec![None; num_commits];
+        for (oid, index) in indexes.iter() {
+            oids[*index] = Some(*oid);
+        }
+        let oids: Vec<NonZeroOid> = oids.into_iter().flatten().collect();
+
+        // Visit commits in topological order (parents before children) to
+        // assign generation numbers.
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); num_commits];
+        let mut num_unvisited_parents: Vec<usize> = vec![0; num_commits];
+        for (index, commit_parents) in parents.iter().enumerate() {
+            num_unvisited_parents[index] = commit_parents.len();
+            for parent_index in commit_parents {
+                children[*parent_index].push(index);
+            }
+        }
+        let mut generations = vec![0; num_commits];
+        let mut queue: Vec<usize> = (0..num_commits)
+            .filter(|index| num_unvisited_parents[*index] == 0)
+            .collect();
+        while let Some(index) = queue.pop() {
+            generations[index] = parents[index]
+                .iter()
+                .map(|parent_index| generations[*parent_index] + 1)
+                .max()
+                .unwrap_or_default();
+            for child_index in children[index].iter() {
+                num_unvisited_parents[*child_index] -= 1;
+                if num_unvisited_parents[*child_index] == 0 {
+                    queue.push(*child_index);
+                }
+            }
+        }
+
+        // Label each commit with the interval of its descendants in the
+        // spanning forest formed by the first-parent edges.
+        let mut tree_children: Vec<Vec<usize>> = vec![Vec::new(); num_commits];
+        for (index, commit_parents) in parents.iter().enumerate() {
+            if let Some(parent_index) = commit_parents.first() {
+                tree_children[*parent_index].push(index);
+            }
+        }
+        let mut intervals = vec![(0, 0); num_commits];
+        let mut next_position = 0;
+        for root_index in (0..num_commits).filter(|index| parents[*index].is_empty()) {
+            // Each stack entry is a commit and whether its descendants have
+            // already been visited.
+            let mut stack = vec![(root_index, false)];
+            while let Some((index, is_finished)) = stack.pop() {
+                if is_finished {
+                    intervals[index].1 = next_position - 1;
+                } else {
+                    intervals[index].0 = next_position;
+                    next_position += 1;
+                    stack.push((index, true));
+                    stack.extend(
+                        tree_children[index]
+                            .iter()
+                            .map(|child_index| (*child_index, false)),
+                    );
+                }
+            }
+        }
+
+        let has_merges = parents
+            .iter()
+            .any(|commit_parents| commit_parents.len() > 1);
+        Self {
+            indexes,
+            oids,
+            parents,
+            generations,
+            intervals,
+            has_merges,
+        }
+    }
+
+    /// Determine whether the given commit is contained in the index.
+    pub fn contains(&self, oid: NonZeroOid) -> bool {
+        self.indexes.contains_key(&oid)
+    }
+
+    /// Get the parents of the given commit which are contained in the index.
+    /// Returns `None` if the commit is not contained in the index.
+    pub fn get_parent_oids(&self, oid: NonZeroOid) -> Option<Vec<NonZeroOid>> {
+        let index = self.indexes.get(&oid)?;
+        Some(
+            self.parents[*index]
+                .iter()
+                .map(|parent_index| self.oids[*parent_index])
+                .collect(),
+        )
+    }
+
+    /// Determine whether `ancestor_oid` is an ancestor of `descendant_oid`. A
+    /// commit is considered to be an ancestor of itself. Returns `None` if
+    /// either commit is not contained in the index.
+    pub fn is_ancestor(
+        &self,
+        ancestor_oid: NonZeroOid,
+        descendant_oid: NonZeroOid,
+    ) -> Option<bool> {
+        let ancestor_index = *self.indexes.get(&ancestor_oid)?;
+        let descendant_index = *self.indexes.get(&descendant_oid)?;
+        if self.is_tree_ancestor(ancestor_index, descendant_index) {
+            return Some(true);
+        }
+        if !self.has_merges {
+            // The spanning forest is the entire graph.
+            return Some(false);
+        }
+
+        let ancestor_generation = self.generations[ancestor_index];
+        let mut visited = vec![false; self.oids.len()];
+        let mut stack = vec![descendant_index];
+        while let Some(index) = stack.pop() {
+            if self.is_tree_ancestor(ancestor_index, index) {
+                return Some(true);
+            }
+            for parent_index in self.parents[index].iter() {
+                if !visited[*parent_index] && self.generations[*parent_index] >= ancestor_generation
+                {
+                    visited[*parent_index] = true;
+                    stack.push(*parent_index);
+                }
+            }
+        }
+        Some(false)
+    }
+
+    fn is_tree_ancestor(&self, ancestor_index: usize, descendant_index: usize) -> bool {
+        let (ancestor_start, ancestor_end) = self.intervals[ancestor_index];
+        let (descendant_start, _descendant_end) = self.intervals[descendant_index];
+        ancestor_start <= descendant_start && descendant_start <= ancestor_end
+    }
+}
+
 impl std::fmt::Debug for Dag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "<Dag>")
@@ -545,3 +804,162 @@ pub fn sorted_commit_set<'repo>(
 
     Ok(commits)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::eventlog::EventLogDb;
+    use crate::core::formatting::Glyphs;
+    use crate::core::repo_ext::RepoExt;
+    use crate::testing::make_git;
+
+    use super::*;
+
+    #[test]
+    fn test_reachability_index() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.detach_head()?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        let test3_oid = git.commit_file("test3", 3)?;
+        git.run(&["checkout", &test1_oid.to_string()])?;
+        let test4_oid = git.commit_file("test4", 4)?;
+        git.run(&["merge", "--no-edit", &test3_oid.to_string()])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let merge_oid = repo.get_head_info()?.oid.unwrap();
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        let event_cursor = event_replayer.make_default_cursor();
+        let references_snapshot = repo.get_references_snapshot()?;
+        let dag = Dag::open_and_sync(
+            &effects,
+            &repo,
+            &event_replayer,
+            event_cursor,
+            &references_snapshot,
+        )?;
+
+        let commits: CommitSet = [test1_oid, test3_oid, test4_oid, merge_oid]
+            .into_iter()
+            .collect();
+        let reachability_index = dag.make_reachability_index(&effects, &commits)?;
+
+        // Commits on the path between the provided commits are also indexed.
+        assert!(reachability_index.contains(test2_oid));
+        assert_eq!(
+            reachability_index.get_parent_oids(test3_oid),
+            Some(vec![test2_oid])
+        );
+
+        assert_eq!(
+            reachability_index.is_ancestor(test1_oid, test1_oid),
+            Some(true)
+        );
+        assert_eq!(
+            reachability_index.is_ancestor(test1_oid, merge_oid),
+            Some(true)
+        );
+        assert_eq!(
+            reachability_index.is_ancestor(test2_oid, merge_oid),
+            Some(true)
+        );
+        assert_eq!(
+            reachability_index.is_ancestor(test3_oid, merge_oid),
+            Some(true)
+        );
+        assert_eq!(
+            reachability_index.is_ancestor(test4_oid, merge_oid),
+            Some(true)
+        );
+        assert_eq!(
+            reachability_index.is_ancestor(test3_oid, test4_oid),
+            Some(false)
+        );
+        assert_eq!(
+            reachability_index.is_ancestor(test4_oid, test3_oid),
+            Some(false)
+        );
+        assert_eq!(
+            reachability_index.is_ancestor(merge_oid, test1_oid),
+            Some(false)
+        );
+
+        let initial_oid = repo.find_commit_or_fail(test1_oid)?.get_only_parent_oid();
+        assert_eq!(
+            reachability_index.is_ancestor(initial_oid.unwrap(), merge_oid),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_public_commits_after_main_branch_moves() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let open_dag = || -> eyre::Result<Dag> {
+            let repo = git.get_repo()?;
+            let conn = repo.get_db_conn()?;
+            let event_log_db = EventLogDb::new(&conn)?;
+            let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+            let event_cursor = event_replayer.make_default_cursor();
+            let references_snapshot = repo.get_references_snapshot()?;
+            Dag::open_and_sync(
+                &effects,
+                &repo,
+                &event_replayer,
+                event_cursor,
+                &references_snapshot,
+            )
+        };
+
+        let dag = open_dag()?;
+        let public_commits = dag.query_public_commits()?;
+        assert!(public_commits.contains(&test1_oid.into())?);
+        // A `Dag` opened against the same main branch OID may reuse the cached
+        // result.
+        let public_commits = open_dag()?.query_public_commits()?;
+        assert!(public_commits.contains(&test1_oid.into())?);
+
+        let test2_oid = git.commit_file("test2", 2)?;
+        assert!(!dag.query_public_commits()?.contains(&test2_oid.into())?);
+        let public_commits = open_dag()?.query_public_commits()?;
+        assert!(public_commits.contains(&test1_oid.into())?);
+        assert!(public_commits.contains(&test2_oid.into())?);
+
+        // A `Dag` opened before the main branch moved still sees the old set,
+        // even though the cache now holds the new one.
+        let public_commits = dag.query_public_commits()?;
+        assert!(public_commits.contains(&test1_oid.into())?);
+        assert!(!public_commits.contains(&test2_oid.into())?);
+        // Likewise for a `Dag` opened against a references snapshot in which
+        // the main branch still points to `test1`.
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        let references_snapshot = RepoReferencesSnapshot {
+            main_branch_oid: test1_oid,
+            ..repo.get_references_snapshot()?
+        };
+        let old_dag = Dag::open_without_syncing(
+            &effects,
+            &repo,
+            &event_replayer,
+            event_replayer.make_default_cursor(),
+            &references_snapshot,
+        )?;
+        let public_commits = old_dag.query_public_commits()?;
+        assert!(public_commits.contains(&test1_oid.into())?);
+        assert!(!public_commits.contains(&test2_oid.into())?);
+
+        Ok(())
+    }
+}
@@ -19,6 +19,7 @@ use crate::core::formatting::Glyphs;
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum OperationType {
     BuildRebasePlan,
+    BuildReachabilityIndex,
     CalculateDiff,
     CalculatePatchId,
     CheckForCycles,
@@ -51,6 +52,7 @@ impl ToString for OperationType {
     fn to_string(&self) -> String {
         let s = match self {
             OperationType::BuildRebasePlan => "Building rebase plan",
+            OperationType::BuildReachabilityIndex => "Indexing commit ancestry",
             OperationType::CalculateDiff => "Computing diffs",
             OperationType::CalculatePatchId => "Hashing commit contents",
             OperationType::CheckForCycles => "Checking for cycles",
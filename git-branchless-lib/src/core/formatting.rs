@@ -153,6 +153,16 @@ impl Glyphs {
     pub fn text() -> Self {
         Glyphs {
             should_write_ansi_escape_codes: false,
+            ..Glyphs::ascii()
+        }
+    }
+
+    /// Glyphs using only ASCII characters, but which still render colors. This
+    /// is useful for terminals which can't display the box-drawing characters
+    /// used by `Glyphs::pretty`.
+    pub fn ascii() -> Self {
+        Glyphs {
+            should_write_ansi_escape_codes: true,
             line: "|",
             line_with_offshoot: "|",
             vertical_ellipsis: ":",
@@ -300,6 +310,31 @@ impl StyledStringBuilder {
         }
         result.into()
     }
+
+    /// Helper function to split a `StyledString` into lines, preserving the
+    /// style of each span. The newline characters are not included in the
+    /// resulting lines.
+    pub fn split_lines(string: StyledString) -> Vec<StyledString> {
+        let mut lines = Vec::new();
+        let mut current_line = StyledString::new();
+        for span in string.spans() {
+            let Span {
+                content,
+                attr,
+                width: _,
+            } = span;
+            for (i, part) in content.split('\n').enumerate() {
+                if i > 0 {
+                    lines.push(std::mem::take(&mut current_line));
+                }
+                if !part.is_empty() {
+                    current_line.append_styled(part, *attr);
+                }
+            }
+        }
+        lines.push(current_line);
+        lines
+    }
 }
 
 /// Set the provided effect to all the internal spans of the styled string.
@@ -23,10 +23,10 @@ pub fn find_dangling_references<'repo>(
     event_cursor: EventCursor,
 ) -> eyre::Result<Vec<Reference<'repo>>> {
     let mut result = Vec::new();
-    for reference in repo.get_all_references()? {
+    repo.for_each_reference(|reference| {
         let reference_name = reference.get_name()?;
         if !is_gc_ref(&reference_name) {
-            continue;
+            return Ok(());
         }
 
         // The graph only contains commits, so we don't need to handle the
@@ -34,7 +34,7 @@ pub fn find_dangling_references<'repo>(
         // a reference to a different kind of object.)
         let commit = match reference.peel_to_commit()? {
             Some(commit) => commit,
-            None => continue,
+            None => return Ok(()),
         };
 
         match event_replayer.get_cursor_commit_activity_status(event_cursor, commit.get_oid()) {
@@ -50,7 +50,8 @@ pub fn find_dangling_references<'repo>(
                 result.push(reference)
             }
         }
-    }
+        Ok(())
+    })?;
     Ok(result)
 }
 
@@ -17,12 +17,14 @@ use tracing::instrument;
 
 use crate::core::config::{
     get_commit_descriptors_branches, get_commit_descriptors_differential_revision,
-    get_commit_descriptors_relative_time,
+    get_commit_descriptors_pull_request_trailer, get_commit_descriptors_relative_time,
 };
 use crate::git::{
-    CategorizedReferenceName, Commit, NonZeroOid, ReferenceName, Repo, ResolvedReferenceInfo,
+    BranchType, CategorizedReferenceName, Commit, DiffOptions, NonZeroOid, ReferenceName, Repo,
+    ResolvedReferenceInfo,
 };
 
+use super::effects::Effects;
 use super::eventlog::{Event, EventCursor, EventReplayer};
 use super::formatting::{Glyphs, StyledStringBuilder};
 use super::repo_ext::RepoReferencesSnapshot;
@@ -53,17 +55,42 @@ impl<'repo> NodeObject<'repo> {
         }
     }
 
-    fn get_short_oid(&self) -> eyre::Result<String> {
-        match self {
-            NodeObject::Commit { commit } => Ok(commit.get_short_oid()?),
+    fn get_short_oid(&self, abbrev_len: Option<usize>) -> eyre::Result<String> {
+        let short_oid = match self {
+            NodeObject::Commit { commit } => commit.get_short_oid()?,
             NodeObject::GarbageCollected { oid } => {
                 // `7` is the default value for config setting `core.abbrev`.
-                Ok(oid.to_string()[..7].to_string())
+                oid.to_string()[..7].to_string()
+            }
+        };
+        match abbrev_len {
+            Some(abbrev_len) if abbrev_len > short_oid.len() => {
+                let oid = self.get_oid().to_string();
+                Ok(oid[..abbrev_len.min(oid.len())].to_string())
             }
+            _ => Ok(short_oid),
         }
     }
 }
 
+/// Get the shortest length, no shorter than `min_len`, to which all of the
+/// provided OIDs can be abbreviated while still being distinct from each
+/// other.
+pub fn get_unique_abbrev_len(oids: impl IntoIterator<Item = NonZeroOid>, min_len: usize) -> usize {
+    let mut oids: Vec<String> = oids.into_iter().map(|oid| oid.to_string()).collect();
+    oids.sort_unstable();
+    oids.windows(2)
+        .map(|window| {
+            let common_prefix_len = window[0]
+                .bytes()
+                .zip(window[1].bytes())
+                .take_while(|(lhs, rhs)| lhs == rhs)
+                .count();
+            common_prefix_len + 1
+        })
+        .fold(min_len, usize::max)
+}
+
 /// Object responsible for redacting sensitive information, so that it can be
 /// included in a bug report.
 #[derive(Debug)]
@@ -176,12 +203,18 @@ pub fn render_node_descriptors(
 #[derive(Debug)]
 pub struct CommitOidDescriptor {
     use_color: bool,
+    abbrev_len: Option<usize>,
 }
 
 impl CommitOidDescriptor {
-    /// Constructor.
-    pub fn new(use_color: bool) -> eyre::Result<Self> {
-        Ok(CommitOidDescriptor { use_color })
+    /// Constructor. If `abbrev_len` is provided, commit hashes are abbreviated
+    /// to at least that many characters. Otherwise, they're abbreviated
+    /// according to `core.abbrev`.
+    pub fn new(use_color: bool, abbrev_len: Option<usize>) -> eyre::Result<Self> {
+        Ok(CommitOidDescriptor {
+            use_color,
+            abbrev_len,
+        })
     }
 }
 
@@ -192,7 +225,7 @@ impl NodeDescriptor for CommitOidDescriptor {
         _glyphs: &Glyphs,
         object: &NodeObject,
     ) -> eyre::Result<Option<StyledString>> {
-        let oid = object.get_short_oid()?;
+        let oid = object.get_short_oid(self.abbrev_len)?;
         let oid = if self.use_color {
             StyledString::styled(oid, BaseColor::Yellow.dark())
         } else {
@@ -202,16 +235,22 @@ impl NodeDescriptor for CommitOidDescriptor {
     }
 }
 
-/// Display the first line of the commit message.
+/// Display the first line of the commit message, and optionally the rest of
+/// the message on subsequent lines.
 #[derive(Debug)]
 pub struct CommitMessageDescriptor<'a> {
     redactor: &'a Redactor,
+    show_body: bool,
 }
 
 impl<'a> CommitMessageDescriptor<'a> {
-    /// Constructor.
-    pub fn new(redactor: &'a Redactor) -> eyre::Result<Self> {
-        Ok(CommitMessageDescriptor { redactor })
+    /// Constructor. If `show_body` is set, the body of the commit message is
+    /// included after the summary line.
+    pub fn new(redactor: &'a Redactor, show_body: bool) -> eyre::Result<Self> {
+        Ok(CommitMessageDescriptor {
+            redactor,
+            show_body,
+        })
     }
 }
 
@@ -225,7 +264,24 @@ impl<'a> NodeDescriptor for CommitMessageDescriptor<'a> {
         let summary = match object {
             NodeObject::Commit { commit } => {
                 let summary = commit.get_summary()?.to_vec();
-                summary.into_string_lossy()
+                let summary = summary.into_string_lossy();
+                if self.show_body {
+                    let message = commit.get_message_pretty()?.to_vec().into_string_lossy();
+                    let body: Vec<&str> = message
+                        .lines()
+                        .skip(1)
+                        .skip_while(|line| line.trim().is_empty())
+                        .collect();
+                    let body = body.join("\n");
+                    let body = body.trim_end();
+                    if body.is_empty() {
+                        summary
+                    } else {
+                        format!("{summary}\n{body}")
+                    }
+                } else {
+                    summary
+                }
             }
             NodeObject::GarbageCollected { oid: _ } => "<garbage collected>".to_string(),
         };
@@ -234,6 +290,67 @@ impl<'a> NodeDescriptor for CommitMessageDescriptor<'a> {
     }
 }
 
+/// Display the number of lines added and removed by the commit, as `+N/-M`.
+#[derive(Debug)]
+pub struct CommitStatsDescriptor<'a> {
+    effects: &'a Effects,
+    repo: &'a Repo,
+    is_enabled: bool,
+    diff_options: DiffOptions,
+}
+
+impl<'a> CommitStatsDescriptor<'a> {
+    /// Constructor. If `is_enabled` is not set, no stats are shown.
+    pub fn new(effects: &'a Effects, repo: &'a Repo, is_enabled: bool) -> eyre::Result<Self> {
+        let diff_options = if is_enabled {
+            DiffOptions::load(repo)?
+        } else {
+            Default::default()
+        };
+        Ok(CommitStatsDescriptor {
+            effects,
+            repo,
+            is_enabled,
+            diff_options,
+        })
+    }
+}
+
+impl<'a> NodeDescriptor for CommitStatsDescriptor<'a> {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        if !self.is_enabled {
+            return Ok(None);
+        }
+        let commit = match object {
+            NodeObject::Commit { commit } => commit,
+            NodeObject::GarbageCollected { oid: _ } => return Ok(None),
+        };
+
+        // This diffs dehydrated trees containing only the paths touched by
+        // the commit, so it stays fast even for large commits. Merge commits
+        // don't have stats.
+        let stats =
+            match self
+                .repo
+                .get_diff_stats_for_commit(self.effects, commit, &self.diff_options)?
+            {
+                Some(stats) => stats,
+                None => return Ok(None),
+            };
+        let result = StyledStringBuilder::new()
+            .append_styled(format!("+{}", stats.insertions), BaseColor::Green.dark())
+            .append_plain("/")
+            .append_styled(format!("-{}", stats.deletions), BaseColor::Red.dark())
+            .build();
+        Ok(Some(result))
+    }
+}
+
 /// For obsolete commits, provide the reason that it's obsolete.
 pub struct ObsolescenceExplanationDescriptor<'a> {
     event_replayer: &'a EventReplayer,
@@ -295,6 +412,8 @@ impl<'a> NodeDescriptor for ObsolescenceExplanationDescriptor<'a> {
 #[derive(Debug)]
 pub struct BranchesDescriptor<'a> {
     is_enabled: bool,
+    show_remote: bool,
+    repo: &'a Repo,
     head_info: &'a ResolvedReferenceInfo,
     references_snapshot: &'a RepoReferencesSnapshot,
     redactor: &'a Redactor,
@@ -303,7 +422,7 @@ pub struct BranchesDescriptor<'a> {
 impl<'a> BranchesDescriptor<'a> {
     /// Constructor.
     pub fn new(
-        repo: &Repo,
+        repo: &'a Repo,
         head_info: &'a ResolvedReferenceInfo,
         references_snapshot: &'a RepoReferencesSnapshot,
         redactor: &'a Redactor,
@@ -311,11 +430,73 @@ impl<'a> BranchesDescriptor<'a> {
         let is_enabled = get_commit_descriptors_branches(repo)?;
         Ok(BranchesDescriptor {
             is_enabled,
+            show_remote: false,
+            repo,
             head_info,
             references_snapshot,
             redactor,
         })
     }
+
+    /// If `show_remote` is set, follow each local branch name with how far it
+    /// is ahead of or behind its upstream branch, such as `[ahead 2, behind
+    /// 1]`, or with `[gone]` if its upstream branch no longer exists.
+    pub fn with_show_remote(mut self, show_remote: bool) -> Self {
+        self.show_remote = show_remote;
+        self
+    }
+
+    /// Describe the relationship between the given branch and its upstream
+    /// branch. Returns `None` if this isn't a local branch, if it doesn't
+    /// track an upstream branch, or if it's up-to-date with its upstream
+    /// branch.
+    fn get_remote_status(&self, branch_name: &ReferenceName) -> eyre::Result<Option<String>> {
+        if !self.show_remote {
+            return Ok(None);
+        }
+
+        let branch_name = match CategorizedReferenceName::new(branch_name) {
+            reference_name @ CategorizedReferenceName::LocalBranch { .. } => {
+                reference_name.render_suffix()
+            }
+            CategorizedReferenceName::RemoteBranch { .. }
+            | CategorizedReferenceName::OtherRef { .. } => return Ok(None),
+        };
+        let branch = match self.repo.find_branch(&branch_name, BranchType::Local)? {
+            Some(branch) => branch,
+            None => return Ok(None),
+        };
+
+        let upstream_branch = match branch.get_upstream_branch()? {
+            Some(upstream_branch) => upstream_branch,
+            None => {
+                return if branch.get_upstream_branch_name()?.is_some() {
+                    Ok(Some("[gone]".to_string()))
+                } else {
+                    Ok(None)
+                };
+            }
+        };
+        let (ahead, behind) = match (branch.get_oid()?, upstream_branch.get_oid()?) {
+            (Some(local_oid), Some(upstream_oid)) => {
+                self.repo.ahead_behind(local_oid, upstream_oid, false)?
+            }
+            _ => return Ok(None),
+        };
+
+        let mut counts = Vec::new();
+        if ahead > 0 {
+            counts.push(format!("ahead {ahead}"));
+        }
+        if behind > 0 {
+            counts.push(format!("behind {behind}"));
+        }
+        if counts.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(format!("[{}]", counts.join(", "))))
+        }
+    }
 }
 
 impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
@@ -329,15 +510,21 @@ impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
             return Ok(None);
         }
 
-        let branch_names: HashSet<ReferenceName> = match self
+        let branch_names: HashSet<(ReferenceName, Option<String>)> = match self
             .references_snapshot
             .branch_oid_to_names
             .get(&object.get_oid())
         {
             Some(branch_names) => branch_names
                 .iter()
-                .map(|branch_name| self.redactor.redact_ref_name(branch_name.to_owned()))
-                .collect(),
+                .map(|branch_name| -> eyre::Result<_> {
+                    let remote_status = self.get_remote_status(branch_name)?;
+                    Ok((
+                        self.redactor.redact_ref_name(branch_name.to_owned()),
+                        remote_status,
+                    ))
+                })
+                .collect::<eyre::Result<_>>()?,
             None => HashSet::new(),
         };
 
@@ -346,7 +533,7 @@ impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
         } else {
             let mut branch_names: Vec<String> = branch_names
                 .into_iter()
-                .map(|branch_name| {
+                .map(|(branch_name, remote_status)| {
                     let is_checked_out_branch =
                         self.head_info.reference_name.as_ref() == Some(&branch_name);
                     let icon = if is_checked_out_branch {
@@ -357,7 +544,15 @@ impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
 
                     match CategorizedReferenceName::new(&branch_name) {
                         reference_name @ CategorizedReferenceName::LocalBranch { .. } => {
-                            format!("{}{}", icon, reference_name.render_suffix())
+                            match remote_status {
+                                Some(remote_status) => format!(
+                                    "{}{} {}",
+                                    icon,
+                                    reference_name.render_suffix(),
+                                    remote_status
+                                ),
+                                None => format!("{}{}", icon, reference_name.render_suffix()),
+                            }
                         }
                         reference_name @ CategorizedReferenceName::RemoteBranch { .. } => {
                             format!("{}remote {}", icon, reference_name.render_suffix())
@@ -378,10 +573,14 @@ impl<'a> NodeDescriptor for BranchesDescriptor<'a> {
     }
 }
 
-/// Display the associated Phabricator revision for a given commit.
+/// Display the associated code review for a given commit. This can be a
+/// Phabricator revision (`Differential Revision:`), a Gerrit change
+/// (`Change-Id:`), or a GitHub pull request (stored in the trailer configured
+/// by `branchless.commitDescriptors.pullRequestTrailer`).
 #[derive(Debug)]
 pub struct DifferentialRevisionDescriptor<'a> {
     is_enabled: bool,
+    pull_request_trailer: Option<String>,
     redactor: &'a Redactor,
 }
 
@@ -389,8 +588,10 @@ impl<'a> DifferentialRevisionDescriptor<'a> {
     /// Constructor.
     pub fn new(repo: &Repo, redactor: &'a Redactor) -> eyre::Result<Self> {
         let is_enabled = get_commit_descriptors_differential_revision(repo)?;
+        let pull_request_trailer = get_commit_descriptors_pull_request_trailer(repo)?;
         Ok(DifferentialRevisionDescriptor {
             is_enabled,
+            pull_request_trailer,
             redactor,
         })
     }
@@ -413,6 +614,38 @@ $",
     Some(diff_number.to_owned())
 }
 
+/// Extract the Gerrit change ID from the `Change-Id` trailer, abbreviated in
+/// the same way as Gerrit does (e.g. `I0123456`).
+fn extract_change_id(trailers: &[(String, String)]) -> Option<String> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r"^I[0-9a-f]{40}$").expect("Failed to compile `extract_change_id` regex");
+    }
+    trailers
+        .iter()
+        .filter(|(key, _value)| key.eq_ignore_ascii_case("Change-Id"))
+        .map(|(_key, value)| value.trim())
+        .find(|value| RE.is_match(value))
+        .map(|change_id| change_id[..8].to_owned())
+}
+
+/// Extract the pull request number from the given trailer, which may contain
+/// either the URL of the pull request or just its number (e.g. `#123`).
+fn extract_pull_request_number(
+    trailers: &[(String, String)],
+    trailer_name: &str,
+) -> Option<String> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"(^|/|#)(?P<number>[0-9]+)/?$")
+            .expect("Failed to compile `extract_pull_request_number` regex");
+    }
+    trailers
+        .iter()
+        .filter(|(key, _value)| key.eq_ignore_ascii_case(trailer_name))
+        .find_map(|(_key, value)| RE.captures(value.trim()))
+        .map(|captures| format!("#{}", &captures["number"]))
+}
+
 impl<'a> NodeDescriptor for DifferentialRevisionDescriptor<'a> {
     #[instrument]
     fn describe_node(
@@ -432,11 +665,24 @@ impl<'a> NodeDescriptor for DifferentialRevisionDescriptor<'a> {
             NodeObject::GarbageCollected { oid: _ } => return Ok(None),
         };
 
-        let diff_number = match extract_diff_number(&commit.get_message_raw()?.to_str_lossy()) {
-            Some(diff_number) => diff_number,
+        let label = match extract_diff_number(&commit.get_message_raw()?.to_str_lossy()) {
+            Some(diff_number) => Some(diff_number),
+            None => {
+                // Commits with messages which aren't valid UTF-8 have no
+                // trailers as far as we're concerned.
+                let trailers = commit.get_trailers().unwrap_or_default();
+                extract_change_id(&trailers).or_else(|| {
+                    self.pull_request_trailer.as_ref().and_then(|trailer_name| {
+                        extract_pull_request_number(&trailers, trailer_name)
+                    })
+                })
+            }
+        };
+        let label = match label {
+            Some(label) => label,
             None => return Ok(None),
         };
-        let result = StyledString::styled(diff_number, BaseColor::Green.dark());
+        let result = StyledString::styled(label, BaseColor::Green.dark());
         Ok(Some(result))
     }
 }
@@ -546,6 +792,61 @@ Differential Revision: phabricator.com/D123";
         Ok(())
     }
 
+    #[test]
+    fn test_extract_change_id() -> eyre::Result<()> {
+        let trailers = vec![(
+            String::from("Change-Id"),
+            String::from("I0123456789abcdef0123456789abcdef01234567"),
+        )];
+        assert_eq!(extract_change_id(&trailers), Some(String::from("I0123456")));
+
+        let trailers = vec![(String::from("Change-Id"), String::from("not-a-change-id"))];
+        assert_eq!(extract_change_id(&trailers), None);
+
+        assert_eq!(extract_change_id(&[]), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_pull_request_number() -> eyre::Result<()> {
+        let trailers = vec![(
+            String::from("Pull-Request"),
+            String::from("https://github.com/arxanas/git-branchless/pull/123"),
+        )];
+        assert_eq!(
+            extract_pull_request_number(&trailers, "Pull-Request"),
+            Some(String::from("#123"))
+        );
+        assert_eq!(extract_pull_request_number(&trailers, "PR"), None);
+
+        let trailers = vec![(String::from("PR"), String::from("#456"))];
+        assert_eq!(
+            extract_pull_request_number(&trailers, "pr"),
+            Some(String::from("#456"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_unique_abbrev_len() -> eyre::Result<()> {
+        let oids: Vec<NonZeroOid> = [
+            "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+            "62fc2a00000000000000000000000000000000ff",
+            "96d1c37a3d4363611c49f7e52186e189a04c531f",
+        ]
+        .iter()
+        .map(|oid| oid.parse())
+        .collect::<Result<_, _>>()?;
+        assert_eq!(get_unique_abbrev_len(oids.iter().copied(), 7), 7);
+        assert_eq!(get_unique_abbrev_len(oids.iter().copied(), 4), 6);
+        assert_eq!(get_unique_abbrev_len(oids[1..].iter().copied(), 4), 4);
+        assert_eq!(get_unique_abbrev_len(Vec::new(), 7), 7);
+
+        Ok(())
+    }
+
     #[test]
     fn test_describe_time_delta() -> eyre::Result<()> {
         let test_cases: Vec<(isize, &str)> = vec![
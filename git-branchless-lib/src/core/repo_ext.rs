@@ -6,9 +6,13 @@ use color_eyre::Help;
 use eyre::Context;
 use tracing::instrument;
 
-use crate::git::{NonZeroOid, Reference, ReferenceName, Repo};
+use crate::git::{Config, ConfigRead, ConfigWrite, NonZeroOid, Reference, ReferenceName, Repo};
 
-use super::config::get_main_branch_name;
+use super::config::{
+    get_main_branch_name, BranchlessConfig, ConfigMigration, DEPRECATED_CONFIG_KEYS,
+};
+use super::dag::{commit_set_to_vec_unsorted, CommitSet};
+use super::eventlog::EventReplayer;
 
 /// A snapshot of all the positions of references we care about in the repository.
 #[derive(Debug)]
@@ -39,6 +43,31 @@ pub trait RepoExt {
 
     /// Get the positions of references in the repository.
     fn get_references_snapshot(&self) -> eyre::Result<RepoReferencesSnapshot>;
+
+    /// Rename any deprecated keys in the `git-branchless` config file to their
+    /// current names, and return the changes that were made. Running this
+    /// again afterwards makes no further changes.
+    fn migrate_config(&self) -> eyre::Result<Vec<ConfigMigration>>;
+
+    /// Count how many of the provided commits were written by each author. The
+    /// authors are keyed by their name and email, as canonicalized by the
+    /// repository's mailmap. Commits which can't be found are skipped.
+    fn get_commit_count_by_author(
+        &self,
+        commits: &CommitSet,
+    ) -> eyre::Result<HashMap<String, usize>>;
+
+    /// Read all of the `git-branchless` configuration for this repository in
+    /// one pass. See `BranchlessConfig`.
+    fn load_branchless_config(&self) -> eyre::Result<BranchlessConfig>;
+
+    /// Explain why the commit identified by `commit_hash` (a full or
+    /// abbreviated hex OID) couldn't be found, for use in error messages.
+    ///
+    /// If the event log has observed a matching commit, then the commit most
+    /// likely existed at some point and has since been garbage-collected.
+    /// Otherwise, the hash was probably mistyped.
+    fn describe_missing_commit(&self, event_replayer: &EventReplayer, commit_hash: &str) -> String;
 }
 
 impl RepoExt for Repo {
@@ -132,4 +161,102 @@ Either create it, or update the main branch setting by running:
             branch_oid_to_names,
         })
     }
+
+    #[instrument]
+    fn migrate_config(&self) -> eyre::Result<Vec<ConfigMigration>> {
+        let config_path = self.get_config_path();
+        if !config_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut config = Config::open(&config_path)?;
+        let mut migrations = Vec::new();
+        for (old_key, new_key) in DEPRECATED_CONFIG_KEYS {
+            let value: String = match config.get(old_key)? {
+                Some(value) => value,
+                None => continue,
+            };
+            let existing_value: Option<String> = config.get(new_key)?;
+            if existing_value.is_none() {
+                config.set(new_key, value.as_str())?;
+            }
+            config.remove(old_key)?;
+
+            let (old_key, new_key) = (old_key.to_string(), new_key.to_string());
+            let migration = match existing_value {
+                Some(_) => ConfigMigration::Removed {
+                    old_key,
+                    new_key,
+                    value,
+                },
+                None => ConfigMigration::Renamed {
+                    old_key,
+                    new_key,
+                    value,
+                },
+            };
+            migrations.push(migration);
+        }
+        Ok(migrations)
+    }
+
+    #[instrument]
+    fn get_commit_count_by_author(
+        &self,
+        commits: &CommitSet,
+    ) -> eyre::Result<HashMap<String, usize>> {
+        let mailmap = self.get_mailmap()?;
+        let mut result = HashMap::new();
+        for commit_oid in commit_set_to_vec_unsorted(commits)? {
+            let commit = match self.find_commit(commit_oid)? {
+                Some(commit) => commit,
+                None => continue,
+            };
+            let author = mailmap.resolve_signature(&commit.get_author())?;
+            let author = author
+                .friendly_describe()
+                .unwrap_or_else(|| "<unknown>".to_string());
+            *result.entry(author).or_insert(0) += 1;
+        }
+        Ok(result)
+    }
+
+    fn load_branchless_config(&self) -> eyre::Result<BranchlessConfig> {
+        BranchlessConfig::load(self)
+    }
+
+    #[instrument(skip(event_replayer))]
+    fn describe_missing_commit(&self, event_replayer: &EventReplayer, commit_hash: &str) -> String {
+        let prefix = commit_hash.to_ascii_lowercase();
+        if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return format!("{commit_hash:?} is not a valid commit hash.");
+        }
+
+        let cursor = event_replayer.make_default_cursor();
+        let mut known_oids: Vec<NonZeroOid> = event_replayer
+            .get_cursor_oids(cursor)
+            .into_iter()
+            .filter(|oid| oid.to_string().starts_with(&prefix))
+            .collect();
+        known_oids.sort_unstable();
+
+        match known_oids.as_slice() {
+            [] => format!(
+                "Commit {commit_hash} could not be found, and git-branchless has no record of it. Check that the hash was typed correctly."
+            ),
+            [oid] => match self.find_commit(*oid) {
+                Ok(Some(_)) => format!("Commit {commit_hash} exists as {oid}."),
+                Ok(None) | Err(_) => format!(
+                    "Commit {commit_hash} could not be found, but git-branchless previously observed it as {oid}. It was probably garbage-collected."
+                ),
+            },
+            oids => format!(
+                "Commit {commit_hash} is ambiguous; git-branchless previously observed these matching commits: {}",
+                oids.iter()
+                    .map(|oid| oid.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
 }
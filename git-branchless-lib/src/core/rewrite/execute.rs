@@ -29,75 +29,93 @@ pub fn move_branches<'a>(
     repo: &'a Repo,
     event_tx_id: EventTransactionId,
     rewritten_oids_map: &'a HashMap<NonZeroOid, MaybeZeroOid>,
+) -> eyre::Result<()> {
+    move_branches_with_targets(
+        effects,
+        git_run_info,
+        repo,
+        event_tx_id,
+        rewritten_oids_map,
+        &HashMap::new(),
+    )
+}
+
+/// Like `move_branches`, but move the branches in `branch_targets` to the
+/// provided commits, rather than to the rewritten versions of the commits they
+/// currently point to.
+pub(super) fn move_branches_with_targets<'a>(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &'a Repo,
+    event_tx_id: EventTransactionId,
+    rewritten_oids_map: &'a HashMap<NonZeroOid, MaybeZeroOid>,
+    branch_targets: &HashMap<ReferenceName, NonZeroOid>,
 ) -> eyre::Result<()> {
     let branch_oid_to_names = repo.get_branch_oid_to_names()?;
 
-    // We may experience an error in the case of a branch move. Ideally, we
-    // would use `git2::Transaction::commit`, which stops the transaction at the
-    // first error, but we don't know which references we successfully committed
-    // in that case. Instead, we just do things non-atomically and record which
-    // ones succeeded. See https://github.com/libgit2/libgit2/issues/5918
-    let mut branch_moves: Vec<(NonZeroOid, MaybeZeroOid, &ReferenceName)> = Vec::new();
+    // Determine all of the branch moves up front, so that they can be applied
+    // together in a single reference transaction. That way, an error partway
+    // through doesn't leave the repository with only some of its branches
+    // moved.
+    let mut planned_moves: Vec<(NonZeroOid, MaybeZeroOid, &ReferenceName)> = Vec::new();
     let mut branch_move_err: Option<eyre::Error> = None;
     'outer: for (old_oid, names) in branch_oid_to_names.iter() {
-        let new_oid = match rewritten_oids_map.get(old_oid) {
-            Some(new_oid) => new_oid,
-            None => continue,
-        };
         let mut names: Vec<_> = names.iter().collect();
         // Sort for determinism in tests.
         names.sort_unstable();
-        match new_oid {
-            MaybeZeroOid::NonZero(new_oid) => {
-                let new_commit = match repo.find_commit_or_fail(*new_oid).wrap_err_with(|| {
-                    format!(
-                        "Could not find newly-rewritten commit with old OID: {:?}, new OID: {:?}",
-                        old_oid, new_oid,
-                    )
-                }) {
-                    Ok(commit) => commit,
-                    Err(err) => {
-                        branch_move_err = Some(err);
-                        break 'outer;
-                    }
-                };
+        let (targeted_names, names): (Vec<_>, Vec<_>) = names
+            .into_iter()
+            .partition(|name| branch_targets.contains_key(name));
+        for reference_name in targeted_names {
+            let new_oid = branch_targets[reference_name];
+            planned_moves.push((*old_oid, MaybeZeroOid::NonZero(new_oid), reference_name));
+        }
 
-                for reference_name in names {
-                    if let Err(err) = repo.create_reference(
-                        reference_name,
-                        new_commit.get_oid(),
-                        true,
-                        "move branches",
-                    ) {
-                        branch_move_err = Some(eyre::eyre!(err));
-                        break 'outer;
-                    }
-                    branch_moves.push((*old_oid, MaybeZeroOid::NonZero(*new_oid), reference_name));
-                }
+        let new_oid = match rewritten_oids_map.get(old_oid) {
+            Some(new_oid) => new_oid,
+            None => continue,
+        };
+        if let MaybeZeroOid::NonZero(new_oid) = new_oid {
+            if let Err(err) = repo.find_commit_or_fail(*new_oid).wrap_err_with(|| {
+                format!(
+                    "Could not find newly-rewritten commit with old OID: {:?}, new OID: {:?}",
+                    old_oid, new_oid,
+                )
+            }) {
+                branch_move_err = Some(err);
+                break 'outer;
             }
+        }
+        for reference_name in names {
+            planned_moves.push((*old_oid, *new_oid, reference_name));
+        }
+    }
 
-            MaybeZeroOid::Zero => {
-                for name in names {
-                    match repo.find_reference(name) {
-                        Ok(Some(mut reference)) => {
-                            if let Err(err) = reference.delete() {
-                                branch_move_err = Some(eyre::eyre!(err));
-                                break 'outer;
-                            }
-                        }
-                        Ok(None) => {
-                            warn!(?name, "Reference not found, not deleting")
-                        }
-                        Err(err) => {
-                            branch_move_err = Some(eyre::eyre!(err));
-                            break 'outer;
-                        }
+    let branch_moves = match branch_move_err {
+        Some(_) => Vec::new(),
+        None => match apply_branch_moves(repo, &planned_moves) {
+            Ok(()) => planned_moves,
+            Err(err) => {
+                branch_move_err = Some(err);
+                // `libgit2` may have applied some of the updates before
+                // failing (see https://github.com/libgit2/libgit2/issues/5918),
+                // so check which ones actually took effect.
+                let mut applied_moves = Vec::new();
+                for (old_oid, new_oid, name) in planned_moves {
+                    let current_oid = match repo.find_reference(name)? {
+                        Some(reference) => reference
+                            .peel_to_commit()?
+                            .map(|commit| MaybeZeroOid::NonZero(commit.get_oid())),
+                        None => Some(MaybeZeroOid::Zero),
                     };
-                    branch_moves.push((*old_oid, MaybeZeroOid::Zero, name));
+                    if current_oid == Some(new_oid) && new_oid != MaybeZeroOid::NonZero(old_oid) {
+                        applied_moves.push((old_oid, new_oid, name));
+                    }
                 }
+                applied_moves
             }
-        }
-    }
+        },
+    };
 
     let branch_moves_stdin: String = branch_moves
         .into_iter()
@@ -120,6 +138,32 @@ pub fn move_branches<'a>(
     }
 }
 
+/// Apply the provided branch moves atomically using a reference transaction.
+fn apply_branch_moves(
+    repo: &Repo,
+    branch_moves: &[(NonZeroOid, MaybeZeroOid, &ReferenceName)],
+) -> eyre::Result<()> {
+    let mut transaction = repo.reference_transaction()?;
+    for (_old_oid, new_oid, name) in branch_moves {
+        match new_oid {
+            MaybeZeroOid::NonZero(new_oid) => {
+                transaction.lock_ref(name)?;
+                transaction.set_target(name, *new_oid, "move branches")?;
+            }
+            MaybeZeroOid::Zero => {
+                if repo.find_reference(name)?.is_none() {
+                    warn!(?name, "Reference not found, not deleting");
+                    continue;
+                }
+                transaction.lock_ref(name)?;
+                transaction.remove(name)?;
+            }
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
 /// After a rebase, check out the appropriate new `HEAD`. This can be difficult
 /// because the commit might have been rewritten, dropped, or have a branch
 /// pointing to it which also needs to be checked out.
@@ -346,6 +390,7 @@ mod in_memory {
     use eyre::Context;
     use tracing::{instrument, warn};
 
+    use crate::core::config::{get_cherry_pick_max_dehydrated_paths, get_commit_signing};
     use crate::core::effects::{Effects, OperationType};
     use crate::core::eventlog::EventLogDb;
     use crate::core::formatting::printable_styled_string;
@@ -354,7 +399,8 @@ mod in_memory {
     use crate::core::rewrite::move_branches;
     use crate::core::rewrite::plan::{OidOrLabel, RebaseCommand, RebasePlan};
     use crate::git::{
-        CherryPickFastError, CherryPickFastOptions, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+        CherryPickFastError, CherryPickFastOptions, CherryPickFastResult, GitRunInfo, MaybeZeroOid,
+        NonZeroOid, Repo,
     };
     use crate::util::ExitCode;
 
@@ -426,6 +472,8 @@ mod in_memory {
             check_out_commit_options: _, // Caller is responsible for checking out to new HEAD.
         } = options;
 
+        let max_dehydrated_paths = get_cherry_pick_max_dehydrated_paths(repo)?;
+        let commit_signing = get_commit_signing(repo)?;
         let mut current_oid = rebase_plan.first_dest_oid;
         let mut labels: HashMap<String, NonZeroOid> = HashMap::new();
         let mut rewritten_oids: Vec<(NonZeroOid, MaybeZeroOid)> = Vec::new();
@@ -513,14 +561,16 @@ mod in_memory {
                         "Applying patch for commit: {}",
                         commit_description
                     ));
-                    let commit_tree = match repo.cherry_pick_fast(
+                    let (commit_tree, became_empty) = match repo.cherry_pick_fast(
                         &commit_to_apply,
                         &current_commit,
                         &CherryPickFastOptions {
                             reuse_parent_tree_if_possible: true,
+                            mainline: None,
+                            max_dehydrated_paths,
                         },
                     ) {
-                        Ok(rebased_commit) => rebased_commit,
+                        Ok(CherryPickFastResult { tree, became_empty }) => (tree, became_empty),
                         Err(CherryPickFastError::MergeConflict { conflicting_paths }) => {
                             return Ok(RebaseInMemoryResult::MergeConflict(MergeConflictInfo {
                                 commit_oid: *commit_to_apply_oid,
@@ -530,6 +580,21 @@ mod in_memory {
                         Err(other) => eyre::bail!(other),
                     };
 
+                    // Don't bother creating (and possibly signing) a commit
+                    // which would be skipped anyways.
+                    if became_empty || commit_to_apply.is_empty() {
+                        rewritten_oids.push((*original_commit_oid, MaybeZeroOid::Zero));
+                        maybe_set_skipped_head_new_oid(*original_commit_oid, current_oid);
+
+                        writeln!(
+                            effects.get_output_stream(),
+                            "{} Skipped now-empty commit: {}",
+                            commit_num,
+                            commit_description
+                        )?;
+                        continue;
+                    }
+
                     let commit_message = commit_to_apply.get_message_raw()?;
                     let commit_message = commit_message.to_str().with_context(|| {
                         eyre::eyre!(
@@ -546,8 +611,8 @@ mod in_memory {
                         commit_to_apply.get_committer().update_timestamp(*now)?
                     };
                     let rebased_commit_oid = repo
-                        .create_commit(
-                            None,
+                        .create_signed_commit(
+                            commit_signing.as_ref(),
                             &commit_to_apply.get_author(),
                             &committer_signature,
                             commit_message,
@@ -556,9 +621,6 @@ mod in_memory {
                         )
                         .wrap_err("Applying rebased commit")?;
 
-                    let rebased_commit = repo
-                        .find_commit_or_fail(rebased_commit_oid)
-                        .wrap_err("Looking up just-rebased commit")?;
                     let commit_description = printable_styled_string(
                         effects.get_glyphs(),
                         repo.friendly_describe_commit_from_oid(
@@ -566,31 +628,18 @@ mod in_memory {
                             rebased_commit_oid,
                         )?,
                     )?;
-                    if rebased_commit.is_empty() {
-                        rewritten_oids.push((*original_commit_oid, MaybeZeroOid::Zero));
-                        maybe_set_skipped_head_new_oid(*original_commit_oid, current_oid);
-
-                        writeln!(
-                            effects.get_output_stream(),
-                            "[{}/{}] Skipped now-empty commit: {}",
-                            i,
-                            num_picks,
-                            commit_description
-                        )?;
-                    } else {
-                        rewritten_oids.push((
-                            *original_commit_oid,
-                            MaybeZeroOid::NonZero(rebased_commit_oid),
-                        ));
-                        current_oid = rebased_commit_oid;
+                    rewritten_oids.push((
+                        *original_commit_oid,
+                        MaybeZeroOid::NonZero(rebased_commit_oid),
+                    ));
+                    current_oid = rebased_commit_oid;
 
-                        writeln!(
-                            effects.get_output_stream(),
-                            "{} Committed as: {}",
-                            commit_num,
-                            commit_description
-                        )?;
-                    }
+                    writeln!(
+                        effects.get_output_stream(),
+                        "{} Committed as: {}",
+                        commit_num,
+                        commit_description
+                    )?;
                 }
 
                 RebaseCommand::Merge {
@@ -665,8 +714,8 @@ mod in_memory {
                         result
                     };
                     let rebased_commit_oid = repo
-                        .create_commit(
-                            None,
+                        .create_signed_commit(
+                            commit_signing.as_ref(),
                             &commit_to_apply.get_author(),
                             &committer_signature,
                             commit_message,
@@ -797,7 +846,7 @@ mod in_memory {
         }
 
         let head_info = repo.get_head_info()?;
-        if head_info.oid.is_some() {
+        if !head_info.is_unborn() {
             // Avoid moving the branch which HEAD points to, or else the index will show
             // a lot of changes in the working copy.
             repo.detach_head(&head_info)?;
@@ -848,11 +897,15 @@ mod on_disk {
     use eyre::Context;
     use tracing::instrument;
 
+    use crate::core::config::get_ignore_submodule_dirty;
     use crate::core::effects::{Effects, OperationType};
+    use crate::core::repo_ext::RepoExt;
     use crate::core::rewrite::plan::RebaseCommand;
     use crate::core::rewrite::plan::RebasePlan;
-    use crate::core::rewrite::rewrite_hooks::save_original_head_info;
-    use crate::git::{GitRunInfo, Repo};
+    use crate::core::rewrite::rewrite_hooks::{save_original_head_info, save_update_refs};
+    use crate::git::{
+        CategorizedReferenceName, GitRunInfo, NonZeroOid, ReferenceName, Repo, StatusOptions,
+    };
     use crate::util::ExitCode;
 
     use super::ExecuteRebasePlanOptions;
@@ -890,7 +943,11 @@ mod on_disk {
             }));
         }
 
-        if repo.has_changed_files(&effects, git_run_info)? {
+        let status_options = StatusOptions {
+            ignore_submodule_dirty: get_ignore_submodule_dirty(repo)?,
+            ..Default::default()
+        };
+        if repo.has_changed_files(&effects, git_run_info, &status_options)? {
             return Ok(Err(Error::ChangedFilesInRepository));
         }
 
@@ -975,13 +1032,56 @@ mod on_disk {
             eyre::bail!("Not implemented: replacing commits in an on disk rebase");
         }
 
+        // Starting with Git v2.38, Git can keep track of where the branches
+        // attached to the rebased commits should end up via `update-ref`
+        // commands (as with `git rebase --update-refs`). The originally
+        // checked-out branch is excluded, since it's handled by
+        // `check_out_updated_head` once the rebase concludes. On older
+        // versions of Git, the branches are moved to the rewritten commits
+        // recorded in the `post-rewrite` hook instead.
+        let branch_oid_to_names = if git_run_info
+            .get_version(repo)?
+            .supports_rebase_update_refs()
+        {
+            repo.get_branch_oid_to_names()?
+        } else {
+            Default::default()
+        };
+        let mut todo_lines: Vec<String> = Vec::new();
+        let mut update_refs: Vec<(ReferenceName, NonZeroOid)> = Vec::new();
+        for command in rebase_plan.commands.iter() {
+            todo_lines.push(command.to_string());
+            if let RebaseCommand::Pick {
+                original_commit_oid,
+                commit_to_apply_oid: _,
+            } = command
+            {
+                let mut names: Vec<_> = match branch_oid_to_names.get(original_commit_oid) {
+                    Some(names) => names.iter().collect(),
+                    None => continue,
+                };
+                // Sort for determinism in tests.
+                names.sort_unstable();
+                for name in names {
+                    let is_local_branch = matches!(
+                        CategorizedReferenceName::new(name),
+                        CategorizedReferenceName::LocalBranch { .. }
+                    );
+                    if is_local_branch && head_info.reference_name.as_ref() != Some(name) {
+                        todo_lines.push(format!("update-ref {}", name.as_str()));
+                        update_refs.push((name.clone(), *original_commit_oid));
+                    }
+                }
+            }
+        }
+        save_update_refs(repo, &update_refs)?;
+
         let todo_file_path = rebase_state_dir.join("git-rebase-todo");
         std::fs::write(
             &todo_file_path,
-            rebase_plan
-                .commands
+            todo_lines
                 .iter()
-                .map(|command| format!("{}\n", command.to_string()))
+                .map(|line| format!("{}\n", line))
                 .collect::<String>(),
         )
         .wrap_err_with(|| {
@@ -992,11 +1092,8 @@ mod on_disk {
         })?;
 
         let end_file_path = rebase_state_dir.join("end");
-        std::fs::write(
-            end_file_path.as_path(),
-            format!("{}\n", rebase_plan.commands.len()),
-        )
-        .wrap_err_with(|| format!("Writing `end` to: {:?}", end_file_path.as_path()))?;
+        std::fs::write(end_file_path.as_path(), format!("{}\n", todo_lines.len()))
+            .wrap_err_with(|| format!("Writing `end` to: {:?}", end_file_path.as_path()))?;
 
         // Corresponds to the `--empty=keep` flag. We'll drop the commits later once
         // we find out that they're empty.
@@ -1021,7 +1118,7 @@ mod on_disk {
         // Make sure we don't move around the current branch unintentionally. If it
         // actually needs to be moved, then it will be moved as part of the
         // post-rebase operations.
-        if head_info.oid.is_some() {
+        if !head_info.is_unborn() {
             repo.detach_head(&head_info)?;
         }
 
@@ -16,7 +16,7 @@ use crate::core::effects::{Effects, OperationType};
 use crate::core::formatting::{printable_styled_string, Pluralize};
 use crate::core::rewrite::{RepoPool, RepoResource};
 use crate::core::task::ResourcePool;
-use crate::git::{Commit, NonZeroOid, PatchId, Repo};
+use crate::git::{Commit, DiffOptions, NonZeroOid, PatchId, Repo};
 
 #[derive(Debug)]
 pub enum OidOrLabel {
@@ -485,6 +485,10 @@ struct BuildState<'repo> {
     /// (If not, then we stop this sub-traversal and wait for a later traversal
     /// to hit the same merge commit).
     merge_commit_parent_labels: HashMap<NonZeroOid, String>,
+
+    /// The options for computing the patches used for patch IDs, as read from
+    /// the config once for the whole rebase plan.
+    diff_options: DiffOptions,
 }
 
 /// Builder for a rebase plan. Unlike regular Git rebases, a `git-branchless`
@@ -696,7 +700,7 @@ impl<'a> RebasePlanBuilder<'a> {
                 // to calculate the diff for the patch ID.
                 false
             } else {
-                match repo.get_patch_id(effects, &current_commit)? {
+                match repo.get_patch_id(effects, &current_commit, &state.diff_options)? {
                     Some(current_patch_id) => upstream_patch_ids.contains(&current_patch_id),
                     None => false,
                 }
@@ -951,12 +955,14 @@ impl<'a> RebasePlanBuilder<'a> {
         pool: &ThreadPool,
         repo_pool: &ResourcePool<RepoResource>,
     ) -> eyre::Result<Result<Option<RebasePlan>, BuildRebasePlanError>> {
+        let repo = repo_pool.try_create()?;
         let mut constraints = ConstraintGraph::new(self.dag, &self.permissions);
         constraints.add_constraints(&self.initial_constraints)?;
         let mut state = BuildState {
             constraints,
             used_labels: Default::default(),
             merge_commit_parent_labels: Default::default(),
+            diff_options: DiffOptions::load(&repo)?,
         };
 
         let (effects, _progress) = effects.start_operation(OperationType::BuildRebasePlan);
@@ -992,7 +998,6 @@ impl<'a> RebasePlanBuilder<'a> {
             return Ok(Err(err));
         }
 
-        let repo = repo_pool.try_create()?;
         let roots = state.constraints.find_roots();
         let mut acc = Vec::new();
         let mut first_dest_oid = None;
@@ -1157,7 +1162,7 @@ impl<'a> RebasePlanBuilder<'a> {
                             Some(commit) => commit,
                             None => return Ok(None),
                         };
-                        let result = repo.get_patch_id(&effects, &commit)?;
+                        let result = repo.get_patch_id(&effects, &commit, &state.diff_options)?;
                         Ok(result)
                     })
                     .inspect(|_| progress.notify_progress_inc(1))
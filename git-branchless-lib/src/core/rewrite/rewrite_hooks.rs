@@ -27,8 +27,8 @@ use crate::git::{
     ResolvedReferenceInfo,
 };
 
-use super::execute::check_out_updated_head;
-use super::{find_abandoned_children, move_branches};
+use super::execute::{check_out_updated_head, move_branches_with_targets};
+use super::find_abandoned_children;
 
 #[instrument(skip(stream))]
 fn read_rewritten_list_entries(
@@ -157,7 +157,21 @@ pub fn hook_post_rewrite(
         // Make sure to resolve `ORIG_HEAD` before we potentially delete the
         // branch it points to, so that we can get the original OID of `HEAD`.
         let previous_head_info = load_original_head_info(&repo)?;
-        move_branches(effects, git_run_info, &repo, event_tx_id, &rewritten_oids)?;
+
+        // Git applies the `update-ref` commands only after this hook has run,
+        // but the branches should have been moved by the time we check out the
+        // updated `HEAD` (and render the smartlog). Move them to the commits
+        // recorded by Git ourselves, and then clear Git's state so that it
+        // doesn't try to move them again.
+        let branch_targets = take_update_refs(&repo)?;
+        move_branches_with_targets(
+            effects,
+            git_run_info,
+            &repo,
+            event_tx_id,
+            &rewritten_oids,
+            &branch_targets,
+        )?;
 
         let skipped_head_updated_oid = load_updated_head_oid(&repo)?;
         let exit_code = check_out_updated_head(
@@ -372,6 +386,89 @@ fn load_original_head_info(repo: &Repo) -> eyre::Result<ResolvedReferenceInfo> {
     })
 }
 
+/// The file where Git keeps track of the references to update at the end of
+/// the rebase via `update-ref` commands. Each reference is stored as three
+/// lines: its name, the OID it pointed to before the rebase, and the OID it
+/// should point to after the rebase (zero until the `update-ref` command has
+/// been executed).
+const UPDATE_REFS_FILE_NAME: &str = "update-refs";
+
+/// Save the branches which Git should move as part of the rebase, along with
+/// the commits which they originally pointed to. There should be a
+/// corresponding `update-ref` command in the rebase plan for each branch.
+#[instrument]
+pub fn save_update_refs(
+    repo: &Repo,
+    update_refs: &[(ReferenceName, NonZeroOid)],
+) -> eyre::Result<()> {
+    if update_refs.is_empty() {
+        return Ok(());
+    }
+
+    let contents: String = update_refs
+        .iter()
+        .map(|(name, before_oid)| {
+            format!(
+                "{}\n{}\n{}\n",
+                name.as_str(),
+                before_oid,
+                MaybeZeroOid::Zero
+            )
+        })
+        .collect();
+    let dest_file_name = repo.get_rebase_state_dir_path().join(UPDATE_REFS_FILE_NAME);
+    std::fs::write(dest_file_name, contents).wrap_err("Writing update-refs")?;
+    Ok(())
+}
+
+/// Load the commits which Git has moved each branch to via `update-ref`
+/// commands, and remove Git's record of them so that they won't be applied
+/// again when the rebase concludes.
+#[instrument]
+fn take_update_refs(repo: &Repo) -> eyre::Result<HashMap<ReferenceName, NonZeroOid>> {
+    let source_file_name = repo.get_rebase_state_dir_path().join(UPDATE_REFS_FILE_NAME);
+    let contents = match std::fs::read_to_string(&source_file_name) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut branch_targets = HashMap::new();
+    for (name, _before_oid, after_oid) in contents.lines().tuples() {
+        // The target is zero if the corresponding `update-ref` command was
+        // never executed.
+        let after_oid: MaybeZeroOid = after_oid.parse()?;
+        if let MaybeZeroOid::NonZero(after_oid) = after_oid {
+            branch_targets.insert(ReferenceName::from(name), after_oid);
+        }
+    }
+    std::fs::remove_file(&source_file_name).wrap_err("Removing update-refs")?;
+    Ok(branch_targets)
+}
+
+/// Stop Git from moving the branches which pointed to the given commit. This
+/// is used when the commit is dropped, in which case the branches should be
+/// deleted in the `post-rewrite` hook instead.
+#[instrument]
+fn remove_update_refs(repo: &Repo, old_commit_oid: NonZeroOid) -> eyre::Result<()> {
+    let source_file_name = repo.get_rebase_state_dir_path().join(UPDATE_REFS_FILE_NAME);
+    let contents = match std::fs::read_to_string(&source_file_name) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let old_commit_oid = old_commit_oid.to_string();
+    let contents: String = contents
+        .lines()
+        .tuples()
+        .filter(|(_name, before_oid, _after_oid)| *before_oid != old_commit_oid)
+        .map(|(name, before_oid, after_oid)| format!("{}\n{}\n{}\n", name, before_oid, after_oid))
+        .collect();
+    std::fs::write(source_file_name, contents).wrap_err("Writing update-refs")?;
+    Ok(())
+}
+
 const EXTRA_POST_REWRITE_FILE_NAME: &str = "branchless_do_extra_post_rewrite";
 
 /// In order to handle the case of a commit being skipped and its corresponding
@@ -475,6 +572,7 @@ pub fn hook_drop_commit_if_empty(
     if Some(old_commit_oid) == orig_head_oid {
         save_updated_head_oid(&repo, only_parent_oid)?;
     }
+    remove_update_refs(&repo, old_commit_oid)?;
     add_rewritten_list_entries(
         &repo.get_tempfile_dir(),
         &repo.get_rebase_state_dir_path().join("rewritten-list"),
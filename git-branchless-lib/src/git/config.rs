@@ -9,11 +9,19 @@ use super::repo::wrap_git_error;
 /// Wrapper around the config values stored on disk for Git.
 pub struct Config {
     inner: git2::Config,
+
+    /// The per-worktree configuration (`config.worktree`), if it's enabled
+    /// with `extensions.worktreeConfig`. Values set here take precedence over
+    /// those in `inner` when reading. It's never written to.
+    worktree_inner: Option<git2::Config>,
 }
 
 impl From<git2::Config> for Config {
     fn from(config: git2::Config) -> Self {
-        Config { inner: config }
+        Config {
+            inner: config,
+            worktree_inner: None,
+        }
     }
 }
 
@@ -27,6 +35,7 @@ impl std::fmt::Debug for Config {
 enum ConfigValueInner {
     String(String),
     Bool(bool),
+    Int(i64),
 }
 
 /// A wrapper around a possible value that can be set for a config key.
@@ -43,6 +52,14 @@ impl From<bool> for ConfigValue {
     }
 }
 
+impl From<i64> for ConfigValue {
+    fn from(value: i64) -> ConfigValue {
+        ConfigValue {
+            inner: ConfigValueInner::Int(value),
+        }
+    }
+}
+
 impl From<String> for ConfigValue {
     fn from(value: String) -> ConfigValue {
         ConfigValue {
@@ -64,6 +81,7 @@ impl Display for ConfigValue {
         match &self.inner {
             ConfigValueInner::String(value) => write!(f, "{}", value),
             ConfigValueInner::Bool(value) => write!(f, "{:?}", value),
+            ConfigValueInner::Int(value) => write!(f, "{}", value),
         }
     }
 }
@@ -78,7 +96,7 @@ impl GetConfigValue<String> for String {
     fn get_from_config(config: &Config, key: impl AsRef<str>) -> eyre::Result<Option<String>> {
         #[instrument]
         fn inner(config: &Config, key: &str) -> eyre::Result<Option<String>> {
-            let value = match config.inner.get_string(key) {
+            let value = match config.lookup(key, git2::Config::get_string) {
                 Ok(value) => Some(value),
                 Err(err) if err.code() == git2::ErrorCode::NotFound => None,
                 Err(err) => {
@@ -96,7 +114,7 @@ impl GetConfigValue<bool> for bool {
     fn get_from_config(config: &Config, key: impl AsRef<str>) -> eyre::Result<Option<bool>> {
         #[instrument]
         fn inner(config: &Config, key: &str) -> eyre::Result<Option<bool>> {
-            let value = match config.inner.get_bool(key) {
+            let value = match config.lookup(key, git2::Config::get_bool) {
                 Ok(value) => Some(value),
                 Err(err) if err.code() == git2::ErrorCode::NotFound => None,
                 Err(err) => {
@@ -110,11 +128,29 @@ impl GetConfigValue<bool> for bool {
     }
 }
 
+impl GetConfigValue<i64> for i64 {
+    fn get_from_config(config: &Config, key: impl AsRef<str>) -> eyre::Result<Option<i64>> {
+        #[instrument]
+        fn inner(config: &Config, key: &str) -> eyre::Result<Option<i64>> {
+            let value = match config.lookup(key, git2::Config::get_i64) {
+                Ok(value) => Some(value),
+                Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+                Err(err) => {
+                    return Err(wrap_git_error(err))
+                        .wrap_err("Looking up integer value for config key")
+                }
+            };
+            Ok(value)
+        }
+        inner(config, key.as_ref())
+    }
+}
+
 impl GetConfigValue<PathBuf> for PathBuf {
     fn get_from_config(config: &Config, key: impl AsRef<str>) -> eyre::Result<Option<PathBuf>> {
         #[instrument]
         fn inner(config: &Config, key: &str) -> eyre::Result<Option<PathBuf>> {
-            let value = match config.inner.get_path(key.as_ref()) {
+            let value = match config.lookup(key, git2::Config::get_path) {
                 Ok(value) => Some(value),
                 Err(err) if err.code() == git2::ErrorCode::NotFound => None,
                 Err(err) => {
@@ -201,7 +237,7 @@ impl Config {
     #[instrument]
     pub fn open(path: &Path) -> eyre::Result<Self> {
         let inner = git2::Config::open(path).map_err(wrap_git_error)?;
-        Ok(Config { inner })
+        Ok(Config::from(inner))
     }
 
     /// Open a configuration instance derived from the global, XDG and
@@ -209,7 +245,32 @@ impl Config {
     #[instrument]
     pub fn open_default() -> eyre::Result<Self> {
         let inner = git2::Config::open_default().map_err(wrap_git_error)?;
-        Ok(Config { inner })
+        Ok(Config::from(inner))
+    }
+
+    /// Layer the per-worktree configuration on top of this configuration for
+    /// reads. `libgit2` doesn't load `config.worktree` itself.
+    pub(super) fn with_worktree_config(self, worktree_config: git2::Config) -> Self {
+        Config {
+            worktree_inner: Some(worktree_config),
+            ..self
+        }
+    }
+
+    /// Look up the given key with `f`, preferring the value from the
+    /// per-worktree configuration, if any.
+    fn lookup<T>(
+        &self,
+        key: &str,
+        f: impl Fn(&git2::Config, &str) -> Result<T, git2::Error>,
+    ) -> Result<T, git2::Error> {
+        if let Some(worktree_inner) = &self.worktree_inner {
+            match f(worktree_inner, key) {
+                Err(err) if err.code() == git2::ErrorCode::NotFound => {}
+                result => return result,
+            }
+        }
+        f(&self.inner, key)
     }
 
     #[instrument]
@@ -221,6 +282,7 @@ impl Config {
             ConfigValueInner::Bool(value) => {
                 self.inner.set_bool(key, *value).map_err(wrap_git_error)
             }
+            ConfigValueInner::Int(value) => self.inner.set_i64(key, *value).map_err(wrap_git_error),
         }
     }
 
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
@@ -7,13 +8,385 @@ use eyre::Context;
 use git_record::{FileState, Section, SectionChangedLine};
 use itertools::Itertools;
 
-use super::{MaybeZeroOid, Repo};
+use crate::core::config::{get_diff_max_file_size, get_diff_normalize_line_endings};
+
+use super::{FileMode, FileStatus, MaybeZeroOid, Repo};
 
 /// A diff between two trees/commits.
 pub struct Diff<'repo> {
     pub(super) inner: git2::Diff<'repo>,
 }
 
+/// A change to the commit which a submodule points to, as reported by
+/// `Diff::get_submodule_changes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubmoduleChange {
+    /// The path of the submodule.
+    pub path: PathBuf,
+
+    /// The commit which the submodule previously pointed to, or zero if the
+    /// submodule was added.
+    pub old_oid: MaybeZeroOid,
+
+    /// The commit which the submodule now points to, or zero if the submodule
+    /// was removed.
+    pub new_oid: MaybeZeroOid,
+}
+
+/// A single changed file between two trees, as reported by
+/// `Repo::get_commit_deltas`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileDelta {
+    /// The kind of change. This is one of `Added`, `Deleted`, `Modified`,
+    /// `Renamed`, `Copied`, or `TypeChanged`.
+    pub status: FileStatus,
+
+    /// The path of the file before the change, or `None` if it was added.
+    pub old_path: Option<PathBuf>,
+
+    /// The path of the file after the change, or `None` if it was deleted.
+    /// This differs from `old_path` only for renames and copies.
+    pub new_path: Option<PathBuf>,
+
+    /// The file mode before the change, or `None` if the file was added.
+    pub old_mode: Option<FileMode>,
+
+    /// The file mode after the change, or `None` if the file was deleted.
+    pub new_mode: Option<FileMode>,
+}
+
+/// Options for computing diffs, as used by `Repo::get_diff_between_trees` and
+/// the functions which compute the patches for commits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// The number of lines of context to include around each hunk.
+    pub num_context_lines: usize,
+
+    /// If non-empty, only the paths matching these pathspecs are diffed.
+    pub pathspec: Vec<PathBuf>,
+
+    /// If provided, files larger than this many bytes are treated as binary,
+    /// so their contents aren't diffed. Otherwise, the `libgit2` default
+    /// threshold is used.
+    pub max_file_size: Option<u64>,
+
+    /// If set, CRLF line endings in the diffed text files are converted to LF
+    /// before comparing them, so changes which only convert line endings
+    /// between CRLF and LF are hidden. Other whitespace is compared as usual.
+    pub normalize_line_endings: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            num_context_lines: 3,
+            pathspec: Default::default(),
+            max_file_size: Default::default(),
+            normalize_line_endings: Default::default(),
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Get the options for computing the patches for commits, as configured
+    /// by `branchless.diff.maxFileSize` and `branchless.diff.normalizeLineEndings`.
+    /// This reads the config, so callers which compute many patches should
+    /// call it once and reuse the result.
+    pub fn load(repo: &Repo) -> eyre::Result<Self> {
+        Ok(Self {
+            max_file_size: get_diff_max_file_size(repo)?,
+            normalize_line_endings: get_diff_normalize_line_endings(repo)?,
+            ..Default::default()
+        })
+    }
+}
+
+/// Summary statistics for a diff, as would be reported by `git diff
+/// --shortstat`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    /// The number of files which were changed.
+    pub files_changed: usize,
+
+    /// The number of lines which were added.
+    pub insertions: usize,
+
+    /// The number of lines which were removed.
+    pub deletions: usize,
+}
+
+impl From<git2::DiffDelta<'_>> for FileDelta {
+    fn from(delta: git2::DiffDelta) -> Self {
+        let status = FileStatus::from(delta.status());
+        let (old_file, new_file) = (delta.old_file(), delta.new_file());
+        let (old_file, new_file) = match status {
+            FileStatus::Added => (None, Some(new_file)),
+            FileStatus::Deleted => (Some(old_file), None),
+            _ => (Some(old_file), Some(new_file)),
+        };
+        FileDelta {
+            status,
+            old_path: old_file
+                .as_ref()
+                .and_then(|file| file.path())
+                .map(|path| path.to_path_buf()),
+            new_path: new_file
+                .as_ref()
+                .and_then(|file| file.path())
+                .map(|path| path.to_path_buf()),
+            old_mode: old_file.map(|file| file.mode().into()),
+            new_mode: new_file.map(|file| file.mode().into()),
+        }
+    }
+}
+
+impl Diff<'_> {
+    /// Get the submodules whose pointed-to commit changed in this diff. (Such
+    /// changes are rendered as `Subproject commit` lines in the patch, since
+    /// there's no file content to compare.)
+    pub fn get_submodule_changes(&self) -> Vec<SubmoduleChange> {
+        self.inner
+            .deltas()
+            .filter(|delta| {
+                delta.old_file().mode() == git2::FileMode::Commit
+                    || delta.new_file().mode() == git2::FileMode::Commit
+            })
+            .filter_map(|delta| {
+                let path = delta
+                    .new_file()
+                    .path()
+                    .or_else(|| delta.old_file().path())?;
+                let oid_if_submodule = |file: git2::DiffFile| {
+                    if file.mode() == git2::FileMode::Commit {
+                        MaybeZeroOid::from(file.id())
+                    } else {
+                        MaybeZeroOid::Zero
+                    }
+                };
+                Some(SubmoduleChange {
+                    path: path.to_path_buf(),
+                    old_oid: oid_if_submodule(delta.old_file()),
+                    new_oid: oid_if_submodule(delta.new_file()),
+                })
+            })
+            .collect()
+    }
+
+    /// Determine whether this diff would produce an empty patch. This is the
+    /// case if it has no hunks, and doesn't add, remove, or rename any files,
+    /// change their modes, or change any binary files. For example, a diff
+    /// which only converts line endings from CRLF to LF is empty when computed
+    /// with `DiffOptions::normalize_line_endings`.
+    pub fn is_empty(&self) -> eyre::Result<bool> {
+        let is_empty = Cell::new(true);
+        let result = self.inner.foreach(
+            &mut |delta, _progress| {
+                if delta.status() != git2::Delta::Modified
+                    || delta.old_file().mode() != delta.new_file().mode()
+                    || delta.flags().is_binary()
+                {
+                    is_empty.set(false);
+                }
+                is_empty.get()
+            },
+            None,
+            Some(&mut |_delta, _hunk| {
+                is_empty.set(false);
+                false
+            }),
+            None,
+        );
+        match result {
+            Ok(()) => Ok(is_empty.get()),
+            // Returning `false` from a callback stops the iteration early,
+            // which is reported as an error.
+            Err(_) if !is_empty.get() => Ok(false),
+            Err(err) => Err(err).wrap_err("Checking for empty diff"),
+        }
+    }
+
+    /// Get the number of files changed and lines added and removed in this
+    /// diff.
+    pub fn get_stats(&self) -> eyre::Result<DiffStats> {
+        let stats = self.inner.stats().wrap_err("Calculating diff stats")?;
+        Ok(DiffStats {
+            files_changed: stats.files_changed(),
+            insertions: stats.insertions(),
+            deletions: stats.deletions(),
+        })
+    }
+
+    /// Serialize this diff in the unified diff format, as would be produced by
+    /// `git diff`. The result can be written to a `.patch` file and applied
+    /// with `git apply`.
+    pub fn to_unified_bytes(&self) -> eyre::Result<Vec<u8>> {
+        let mut result = Vec::new();
+        self.inner
+            .print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+                // The origin character is not included in the line content for
+                // context, addition, and deletion lines.
+                match line.origin() {
+                    ' ' => result.push(b' '),
+                    '+' => result.push(b'+'),
+                    '-' => result.push(b'-'),
+                    _ => {}
+                }
+                result.extend(line.content());
+                true
+            })
+            .wrap_err("Printing diff")?;
+        Ok(result)
+    }
+
+    /// Serialize this diff in a format similar to `git diff --word-diff`.
+    /// Within each hunk, the removed and added lines are compared
+    /// word-by-word, and changed words are marked as `[-removed-]` or
+    /// `{+added+}`. This is intended only for display: the result can't be
+    /// applied as a patch.
+    pub fn to_word_diff_bytes(&self) -> eyre::Result<Vec<u8>> {
+        let mut result = Vec::new();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        self.inner
+            .print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+                match line.origin() {
+                    '-' => removed.extend(line.content()),
+                    '+' => added.extend(line.content()),
+                    origin => {
+                        write_word_diff(&mut result, &removed, &added);
+                        removed.clear();
+                        added.clear();
+                        match origin {
+                            // End-of-file newline markers aren't meaningful
+                            // once the lines have been merged.
+                            '=' | '>' | '<' => {}
+                            _ => result.extend(line.content()),
+                        }
+                    }
+                }
+                true
+            })
+            .wrap_err("Printing diff")?;
+        write_word_diff(&mut result, &removed, &added);
+        Ok(result)
+    }
+}
+
+/// A word in a line of text, along with the whitespace preceding it.
+struct Word<'a> {
+    leading_whitespace: &'a [u8],
+    word: &'a [u8],
+}
+
+/// Split the provided text into words, returning the words and any whitespace
+/// after the last word.
+fn split_words(text: &[u8]) -> (Vec<Word<'_>>, &[u8]) {
+    let mut words = Vec::new();
+    let mut rest = text;
+    loop {
+        let word_start = rest
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        if word_start == rest.len() {
+            return (words, rest);
+        }
+        let word_end = rest[word_start..]
+            .iter()
+            .position(|c| c.is_ascii_whitespace())
+            .map_or(rest.len(), |len| word_start + len);
+        words.push(Word {
+            leading_whitespace: &rest[..word_start],
+            word: &rest[word_start..word_end],
+        });
+        rest = &rest[word_end..];
+    }
+}
+
+/// Write `text` surrounded by the `open` and `close` markers. As with `git
+/// diff --word-diff`, the markers don't span lines, so each line of `text` is
+/// marked separately.
+fn write_marked(out: &mut Vec<u8>, open: &[u8], close: &[u8], text: &[u8]) {
+    for (i, line) in text.split(|c| *c == b'\n').enumerate() {
+        if i > 0 {
+            out.push(b'\n');
+        }
+        if !line.is_empty() {
+            out.extend(open);
+            out.extend(line);
+            out.extend(close);
+        }
+    }
+}
+
+/// Write the word-by-word differences between the `removed` and `added`
+/// lines of a hunk.
+fn write_word_diff(out: &mut Vec<u8>, removed: &[u8], added: &[u8]) {
+    let (old_words, old_trailing) = split_words(removed);
+    let (new_words, new_trailing) = split_words(added);
+
+    // Compute the longest common subsequence of words, where `lcs[i][j]` is
+    // the length of the LCS of `old_words[i..]` and `new_words[j..]`.
+    let mut lcs = vec![vec![0_usize; new_words.len() + 1]; old_words.len() + 1];
+    for i in (0..old_words.len()).rev() {
+        for j in (0..new_words.len()).rev() {
+            lcs[i][j] = if old_words[i].word == new_words[j].word {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut pending_removed: Vec<&Word> = Vec::new();
+    let mut pending_added: Vec<&Word> = Vec::new();
+    let flush =
+        |out: &mut Vec<u8>, pending_removed: &mut Vec<&Word>, pending_added: &mut Vec<&Word>| {
+            let join = |words: &[&Word]| -> Vec<u8> {
+                let mut result = Vec::new();
+                for (i, word) in words.iter().enumerate() {
+                    if i > 0 {
+                        result.extend(word.leading_whitespace);
+                    }
+                    result.extend(word.word);
+                }
+                result
+            };
+            let leading_whitespace = match (pending_added.first(), pending_removed.first()) {
+                (Some(word), _) | (None, Some(word)) => word.leading_whitespace,
+                (None, None) => return,
+            };
+            out.extend(leading_whitespace);
+            write_marked(out, b"[-", b"-]", &join(pending_removed));
+            write_marked(out, b"{+", b"+}", &join(pending_added));
+            pending_removed.clear();
+            pending_added.clear();
+        };
+
+    let (mut i, mut j) = (0, 0);
+    while i < old_words.len() || j < new_words.len() {
+        if i < old_words.len() && j < new_words.len() && old_words[i].word == new_words[j].word {
+            flush(out, &mut pending_removed, &mut pending_added);
+            out.extend(new_words[j].leading_whitespace);
+            out.extend(new_words[j].word);
+            i += 1;
+            j += 1;
+        } else if j == new_words.len() || (i < old_words.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            pending_removed.push(&old_words[i]);
+            i += 1;
+        } else {
+            pending_added.push(&new_words[j]);
+            j += 1;
+        }
+    }
+    flush(out, &mut pending_removed, &mut pending_added);
+    out.extend(if added.is_empty() {
+        old_trailing
+    } else {
+        new_trailing
+    });
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct GitHunk {
     old_start: usize,
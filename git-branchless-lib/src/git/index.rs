@@ -1,10 +1,12 @@
 use std::path::{Path, PathBuf};
 
+use bstr::ByteVec;
 use eyre::Context;
 use tracing::instrument;
 
 use crate::core::eventlog::EventTransactionId;
 
+use super::repo::{Error, Result};
 use super::{FileMode, GitRunInfo, GitRunOpts, GitRunResult, MaybeZeroOid, NonZeroOid, Repo};
 
 /// The possible stages for items in the index.
@@ -69,6 +71,27 @@ impl Index {
         self.inner.has_conflicts()
     }
 
+    /// Get the paths which have unresolved merge conflicts, in index order.
+    #[instrument]
+    pub fn get_conflicted_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for conflict in self.inner.conflicts().map_err(Error::ReadIndex)? {
+            let conflict = conflict.map_err(Error::ReadIndex)?;
+            let entry = match conflict.our.or(conflict.their).or(conflict.ancestor) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let path = entry
+                .path
+                .into_path_buf()
+                .map_err(|_err| Error::DecodeUtf8 {
+                    item: "index entry path",
+                })?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
     /// Get the (stage 0) entry for the given path.
     pub fn get_entry(&self, path: &Path) -> Option<IndexEntry> {
         self.get_entry_in_stage(path, Stage::Stage0)
@@ -88,6 +111,38 @@ impl Index {
                 },
             })
     }
+
+    /// Stage the current working copy contents of the file at `path`, which is
+    /// relative to the root of the repository. The change is only made in
+    /// memory until `write` is called.
+    #[instrument]
+    pub fn add_path(&mut self, path: &Path) -> Result<()> {
+        self.inner
+            .add_path(path)
+            .map_err(|err| Error::AddIndexPath {
+                source: err,
+                path: path.to_owned(),
+            })
+    }
+
+    /// Remove the (stage 0) entry for `path`, which is relative to the root
+    /// of the repository. The change is only made in memory until `write` is
+    /// called.
+    #[instrument]
+    pub fn remove_path(&mut self, path: &Path) -> Result<()> {
+        self.inner
+            .remove_path(path)
+            .map_err(|err| Error::RemoveIndexPath {
+                source: err,
+                path: path.to_owned(),
+            })
+    }
+
+    /// Write the in-memory contents of the index back to disk.
+    #[instrument]
+    pub fn write(&mut self) -> Result<()> {
+        self.inner.write().map_err(Error::WriteIndex)
+    }
 }
 
 /// The command to update the index, as defined by `git update-index`.
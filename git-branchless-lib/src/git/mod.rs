@@ -11,16 +11,18 @@ mod status;
 mod tree;
 
 pub use config::{Config, ConfigRead, ConfigValue, ConfigWrite};
-pub use diff::{process_diff_for_record, Diff};
+pub use diff::{process_diff_for_record, Diff, DiffOptions, DiffStats, FileDelta, SubmoduleChange};
 pub use index::{update_index, Index, IndexEntry, Stage, UpdateIndexCommand};
 pub use oid::{MaybeZeroOid, NonZeroOid};
 pub use repo::{
-    message_prettify, AmendFastOptions, Branch, BranchType, CategorizedReferenceName,
-    CherryPickFastError, CherryPickFastOptions, Commit, Error as RepoError, GitVersion, PatchId,
-    Reference, ReferenceName, ReferenceTarget, Repo, ResolvedReferenceInfo, Result as RepoResult,
-    Time,
+    make_change_id, message_prettify, select_auto_comment_char, AmendFastOptions, ApplyLocation,
+    Branch, BranchInfo, BranchType, CategorizedReferenceName, CherryPickFastError,
+    CherryPickFastOptions, CherryPickFastResult, Commit, Error as RepoError, GitVersion, Mailmap,
+    PatchId, PromptInfo, QuickStatus, RefTransaction, Reference, ReferenceName, ReferenceTarget,
+    ReflogEntry, Repo, RepoStateWarnings, ResolvedReferenceInfo, Result as RepoResult, Signature,
+    StashEntry, Tag, Time, Worktree, DEFAULT_CHANGE_ID_TRAILER,
 };
 pub use run::{GitRunInfo, GitRunOpts, GitRunResult};
 pub use snapshot::{WorkingCopyChangesType, WorkingCopySnapshot};
-pub use status::{FileMode, FileStatus, StatusEntry};
+pub use status::{FileMode, FileStatus, StatusEntry, StatusHeader, StatusOptions};
 pub use tree::{dehydrate_tree, hydrate_tree, Tree};
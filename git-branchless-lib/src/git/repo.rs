@@ -10,12 +10,15 @@
 //! - To collect some different helper Git functions.
 
 use std::borrow::{Borrow, Cow};
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::ffi::OsStr;
+use std::io::{Read, Write};
 use std::num::TryFromIntError;
 use std::ops::Add;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
 use std::str::FromStr;
 use std::string::FromUtf8Error;
 use std::time::{Duration, SystemTime};
@@ -25,18 +28,20 @@ use bstr::{BString, ByteSlice, ByteVec};
 use chrono::NaiveDateTime;
 use cursive::theme::BaseColor;
 use cursive::utils::markup::StyledString;
-use git2::{message_trailers_bytes, DiffOptions};
+use git2::{message_trailers_bytes, DiffFindOptions};
 use itertools::Itertools;
+use tempfile::NamedTempFile;
 use thiserror::Error;
 use tracing::{instrument, warn};
 
+use crate::core::config::{CommitSigning, CommitSigningFormat};
 use crate::core::effects::{Effects, OperationType};
 use crate::core::eventlog::EventTransactionId;
 use crate::core::formatting::{Glyphs, StyledStringBuilder};
 use crate::core::node_descriptors::{
     render_node_descriptors, CommitMessageDescriptor, CommitOidDescriptor, NodeObject, Redactor,
 };
-use crate::git::config::{Config, ConfigRead};
+use crate::git::config::{Config, ConfigRead, ConfigWrite};
 use crate::git::oid::{make_non_zero_oid, MaybeZeroOid, NonZeroOid};
 use crate::git::run::GitRunInfo;
 use crate::git::tree::{dehydrate_tree, get_changed_paths_between_trees, hydrate_tree, Tree};
@@ -44,7 +49,9 @@ use crate::git::tree::{dehydrate_tree, get_changed_paths_between_trees, hydrate_
 use super::index::{Index, IndexEntry};
 use super::snapshot::WorkingCopySnapshot;
 use super::status::FileMode;
-use super::{tree, Diff, StatusEntry};
+use super::{
+    tree, Diff, DiffOptions, DiffStats, FileDelta, StatusEntry, StatusHeader, StatusOptions,
+};
 
 #[allow(missing_docs)]
 #[derive(Debug, Error)]
@@ -55,6 +62,15 @@ pub enum Error {
     #[error("could not read index: {0}")]
     ReadIndex(#[source] git2::Error),
 
+    #[error("could not add {path} to index: {source}")]
+    AddIndexPath { source: git2::Error, path: PathBuf },
+
+    #[error("could not remove {path} from index: {source}")]
+    RemoveIndexPath { source: git2::Error, path: PathBuf },
+
+    #[error("could not write index: {0}")]
+    WriteIndex(#[source] git2::Error),
+
     #[error("could not create .git/branchless directory at {path}: {source}")]
     CreateBranchlessDir { source: io::Error, path: PathBuf },
 
@@ -92,21 +108,68 @@ pub enum Error {
         oid: NonZeroOid,
     },
 
+    #[error("could not find tag {oid}: {source}")]
+    FindTag {
+        source: git2::Error,
+        oid: NonZeroOid,
+    },
+
+    #[error("could not create tag {name}: {source}")]
+    CreateTag { source: git2::Error, name: String },
+
+    #[error("could not read note for object {oid} from {notes_ref}: {source}")]
+    ReadNote {
+        source: git2::Error,
+        notes_ref: String,
+        oid: NonZeroOid,
+    },
+
+    #[error("could not write note for object {oid} to {notes_ref}: {source}")]
+    WriteNote {
+        source: git2::Error,
+        notes_ref: String,
+        oid: NonZeroOid,
+    },
+
     #[error("could not create blob: {0}")]
     CreateBlob(#[source] git2::Error),
 
     #[error("could not create blob from {path}: {source}")]
     CreateBlobFromPath { source: git2::Error, path: PathBuf },
 
+    #[error("could not read shallow file at {path}: {source}")]
+    ReadShallowFile { source: io::Error, path: PathBuf },
+
+    #[error("could not parse line {line:?} in shallow file: {source}")]
+    ParseShallowFile { source: eyre::Error, line: String },
+
+    #[error("could not write blob contents from stream: {0}")]
+    WriteBlobStream(#[source] io::Error),
+
+    #[error("could not read symbolic link at {path}: {source}")]
+    ReadSymlink { source: io::Error, path: PathBuf },
+
     #[error("could not find commit {oid}: {source}")]
     FindCommit {
         source: git2::Error,
         oid: NonZeroOid,
     },
 
+    #[error("could not sign commit with {program}: {message}")]
+    SignCommit { program: String, message: String },
+
     #[error("could not create commit: {0}")]
     CreateCommit(#[source] git2::Error),
 
+    #[error("a merge commit requires at least two distinct parents, but got: {parent_oids:?}")]
+    InvalidMergeParents { parent_oids: Vec<NonZeroOid> },
+
+    #[error("invalid commit header name: {0:?}")]
+    InvalidCommitHeaderName(String),
+
+    #[error("invalid commit encoding: {0:?}")]
+    InvalidCommitEncoding(String),
+
     #[error("could not cherry-pick commit {commit} onto {onto}: {0}")]
     CherryPickCommit {
         source: git2::Error,
@@ -148,6 +211,15 @@ pub enum Error {
     #[error("could not find branch with name '{name}': {source}")]
     FindBranch { source: git2::Error, name: String },
 
+    #[error("invalid branch glob pattern {pattern:?}: {reason}")]
+    InvalidBranchGlob { pattern: String, reason: String },
+
+    #[error("could not find branches matching pattern {pattern:?}: {source}")]
+    FindBranchesMatching {
+        source: git2::Error,
+        pattern: String,
+    },
+
     #[error("could not find upstream branch for branch with name '{name}': {source}")]
     FindUpstreamBranch { source: git2::Error, name: String },
 
@@ -166,9 +238,33 @@ pub enum Error {
     #[error("could not delete reference: {0}")]
     DeleteReference(#[source] git2::Error),
 
+    #[error("could not start reference transaction: {0}")]
+    CreateReferenceTransaction(#[source] git2::Error),
+
+    #[error("could not lock reference '{}': {source}", name.as_str())]
+    LockReference {
+        source: git2::Error,
+        name: ReferenceName,
+    },
+
+    #[error("could not update reference '{}' in transaction: {source}", name.as_str())]
+    UpdateReferenceInTransaction {
+        source: git2::Error,
+        name: ReferenceName,
+    },
+
+    #[error("could not commit reference transaction: {0}")]
+    CommitReferenceTransaction(#[source] git2::Error),
+
     #[error("could not resolve reference: {0}")]
     ResolveReference(#[source] git2::Error),
 
+    #[error("could not read reflog for reference '{}': {source}", name.as_str())]
+    ReadReflog {
+        source: git2::Error,
+        name: ReferenceName,
+    },
+
     #[error("could not diff trees {old_tree} and {new_tree}: {source}")]
     DiffTreeToTree {
         source: git2::Error,
@@ -194,33 +290,106 @@ pub enum Error {
     #[error("could not calculate changed paths: {0}")]
     GetChangedPaths(#[source] super::tree::Error),
 
+    #[error("could not calculate diff stats: {0}")]
+    GetDiffStats(#[source] eyre::Error),
+
+    #[error("could not detect renames: {0}")]
+    FindRenames(#[source] git2::Error),
+
     #[error("could not get paths touched by commit {commit}")]
     GetPatch { commit: NonZeroOid },
 
     #[error("compute patch ID: {0}")]
     GetPatchId(#[source] git2::Error),
 
+    #[error("could not check whether patch is empty: {0}")]
+    CheckEmptyPatch(#[source] eyre::Error),
+
+    #[error("could not apply diff to {location:?}: {source}")]
+    ApplyDiff {
+        source: git2::Error,
+        location: ApplyLocation,
+    },
+
+    #[error("could not apply hunk at {}:{line} to {location:?}: {source}", path.display())]
+    ApplyDiffHunk {
+        source: git2::Error,
+        location: ApplyLocation,
+        path: PathBuf,
+        line: u32,
+    },
+
     #[error("could not get references: {0}")]
     GetReferences(#[source] git2::Error),
 
     #[error("could not get branches: {0}")]
     GetBranches(#[source] git2::Error),
 
+    #[error("could not get worktrees: {0}")]
+    GetWorktrees(#[source] git2::Error),
+
+    #[error("could not open worktree {name}: {source}")]
+    OpenWorktree { source: git2::Error, name: String },
+
+    #[error("could not read common Git directory path from {path}: {source}")]
+    ReadCommonDir { source: io::Error, path: PathBuf },
+
     #[error("could not get remote names: {0}")]
     GetRemoteNames(#[source] git2::Error),
 
+    #[error("could not get stashes: {0}")]
+    GetStashes(#[source] git2::Error),
+
+    #[error("could not check whether checking out {oid} would overwrite changes: {source}")]
+    CheckOutDryRun {
+        source: git2::Error,
+        oid: NonZeroOid,
+    },
+
+    #[error("could not get working copy status: {0}")]
+    GetStatuses(#[source] git2::Error),
+
+    #[error("could not count commits between {local} and {upstream}: {source}")]
+    GetAheadBehind {
+        source: git2::Error,
+        local: NonZeroOid,
+        upstream: NonZeroOid,
+    },
+
+    #[error("could not walk commits in range {from}..{to}: {source}")]
+    GetRange {
+        source: git2::Error,
+        from: NonZeroOid,
+        to: NonZeroOid,
+    },
+
     #[error("HEAD is unborn (try making a commit?)")]
     UnbornHead,
 
     #[error("could not create commit signature: {0}")]
     CreateSignature(#[source] git2::Error),
 
+    #[error("could not read mailmap: {0}")]
+    ReadMailmap(#[source] git2::Error),
+
+    #[error("could not resolve signature using mailmap: {0}")]
+    ResolveMailmap(#[source] git2::Error),
+
     #[error("could not execute git: {0}")]
     ExecGit(#[source] eyre::Error),
 
     #[error("unsupported spec: {0} (ends with @, which is buggy in libgit2")]
     UnsupportedRevParseSpec(String),
 
+    #[error(
+        "ambiguous object prefix '{prefix}', candidates: {}",
+        candidates.iter().map(|oid| oid.to_string()).collect::<Vec<_>>().join(", ")
+    )]
+    AmbiguousPrefix {
+        prefix: String,
+        candidates: Vec<NonZeroOid>,
+    },
+
     #[error("could not parse git version output: {0}")]
     ParseGitVersionOutput(String),
 
@@ -236,6 +405,9 @@ pub enum Error {
     #[error("could not parse status line: {0}")]
     ParseStatusEntry(#[source] eyre::Error),
 
+    #[error("could not parse status header: {0}")]
+    ParseStatusHeader(#[source] eyre::Error),
+
     #[error("could not decode UTF-8 value for {item}")]
     DecodeUtf8 { item: &'static str },
 
@@ -294,6 +466,94 @@ pub fn message_prettify(message: &str, comment_char: Option<char>) -> Result<Str
     Ok(message)
 }
 
+/// The characters which may be selected for `core.commentChar = auto`, in
+/// order of preference. These are the same candidates that Git uses.
+const AUTO_COMMENT_CHAR_CANDIDATES: &[char] = &['#', ';', '@', '!', '$', '%', '^', '&', '|', ':'];
+
+/// Select a comment character for editing `message`, as Git does for
+/// `core.commentChar = auto`. This is `#` if it doesn't appear anywhere in
+/// `message`, and otherwise the first candidate which doesn't start any line of
+/// `message`. Returns `None` if every candidate is in use.
+#[instrument]
+pub fn select_auto_comment_char(message: &str) -> Option<char> {
+    let default_char = AUTO_COMMENT_CHAR_CANDIDATES[0];
+    if !message.contains(default_char) {
+        return Some(default_char);
+    }
+
+    let used_chars: HashSet<char> = message
+        .split(['\n', '\r'])
+        .filter_map(|line| line.chars().next())
+        .collect();
+    AUTO_COMMENT_CHAR_CANDIDATES
+        .iter()
+        .copied()
+        .find(|candidate| !used_chars.contains(candidate))
+}
+
+/// The name of the trailer which holds a commit's change ID by default.
+pub const DEFAULT_CHANGE_ID_TRAILER: &str = "Change-Id";
+
+/// Generate a new change ID in the format used by Gerrit (`I` followed by 40
+/// hex digits), derived from the provided data. The data should be unique to
+/// the change being created.
+#[instrument]
+pub fn make_change_id(seed: &[u8]) -> Result<String> {
+    let oid = git2::Oid::hash_object(git2::ObjectType::Blob, seed).map_err(Error::Git)?;
+    Ok(format!("I{}", oid))
+}
+
+/// Get the extra arguments to pass to `git status` and `git diff` according to
+/// `StatusOptions::ignore_submodule_dirty`.
+fn get_ignore_submodules_args(options: &StatusOptions) -> &'static [&'static str] {
+    if options.ignore_submodule_dirty {
+        &["--ignore-submodules=dirty"]
+    } else {
+        &[]
+    }
+}
+
+/// Whether `glob` contains a `[` character class which is never closed. Ref
+/// globs use wildmatch syntax, under which such a pattern silently matches
+/// nothing, rather than being reported as malformed.
+fn has_unterminated_bracket(glob: &str) -> bool {
+    let bytes = glob.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'[' => {
+                i += 1;
+                if matches!(bytes.get(i), Some(b'!' | b'^')) {
+                    i += 1;
+                }
+                // A `]` immediately after the opening bracket is a literal.
+                if bytes.get(i) == Some(&b']') {
+                    i += 1;
+                }
+                loop {
+                    match bytes.get(i) {
+                        None => return true,
+                        Some(b']') => break,
+                        Some(b'\\') => i += 2,
+                        Some(b'[') if bytes.get(i + 1) == Some(&b':') => {
+                            // Skip over a character class such as `[:alpha:]`.
+                            match glob[i + 2..].find(":]") {
+                                Some(end) => i += 2 + end + 2,
+                                None => i += 1,
+                            }
+                        }
+                        Some(_) => i += 1,
+                    }
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    false
+}
+
 /// A snapshot of information about a certain reference. Updates to the
 /// reference after this value is obtained are not reflected.
 ///
@@ -318,11 +578,24 @@ pub struct ResolvedReferenceInfo {
     pub oid: Option<NonZeroOid>,
 
     /// The name of the reference that `HEAD` points to symbolically. If `HEAD`
-    /// is detached, then this is `None`.
+    /// is detached, then this is `None`. If this reference is itself symbolic,
+    /// use `Repo::get_reference_chain` to see the full chain of references.
     pub reference_name: Option<ReferenceName>,
 }
 
 impl ResolvedReferenceInfo {
+    /// Whether `HEAD` points directly to a commit rather than symbolically to
+    /// a reference.
+    pub fn is_detached(&self) -> bool {
+        self.oid.is_some() && self.reference_name.is_none()
+    }
+
+    /// Whether `HEAD` is unborn, i.e. there is no commit for it to point to
+    /// yet.
+    pub fn is_unborn(&self) -> bool {
+        self.oid.is_none()
+    }
+
     /// Get the name of the branch, if any. Returns `None` if `HEAD` is
     /// detached. The `refs/heads/` prefix, if any, is stripped.
     pub fn get_branch_name(&self) -> Result<Option<&str>> {
@@ -372,12 +645,51 @@ impl FromStr for GitVersion {
     }
 }
 
+impl GitVersion {
+    /// Determine if this version of Git supports the `update-ref` rebase
+    /// command (used by `git rebase --update-refs`), which was introduced in
+    /// Git v2.38.
+    pub fn supports_rebase_update_refs(&self) -> bool {
+        *self >= GitVersion(2, 38, 0)
+    }
+
+    /// Determine if this version of Git supports the `--trailer` option to
+    /// `git commit`, which was introduced in Git v2.32.
+    pub fn supports_commit_trailer_option(&self) -> bool {
+        *self >= GitVersion(2, 32, 0)
+    }
+}
+
 /// Options for `Repo::cherry_pick_fast`.
 #[derive(Clone, Debug)]
 pub struct CherryPickFastOptions {
     /// Detect if a commit is being applied onto a parent with the same tree,
     /// and skip applying the patch in that case.
     pub reuse_parent_tree_if_possible: bool,
+
+    /// If the patch commit is a merge commit, apply its changes relative to
+    /// this parent (numbered from 1), like `git cherry-pick -m`. This
+    /// linearizes the merge. Must be `None` for non-merge commits.
+    pub mainline: Option<u32>,
+
+    /// If set, commits which touch more than this many paths are applied
+    /// using the full indexes rather than dehydrated trees. Callers applying
+    /// many commits should read this once with
+    /// `get_cherry_pick_max_dehydrated_paths`.
+    pub max_dehydrated_paths: Option<usize>,
+}
+
+/// The result of a successful `Repo::cherry_pick_fast` operation.
+#[derive(Debug)]
+pub struct CherryPickFastResult<'repo> {
+    /// The tree resulting from applying the patch.
+    pub tree: Tree<'repo>,
+
+    /// Whether the patch made changes originally, but applying it to the
+    /// target produced no changes (i.e. `tree` is the same as the target's
+    /// tree). This usually means that the patch was already applied to the
+    /// target. A patch which was empty to begin with did not "become" empty.
+    pub became_empty: bool,
 }
 
 /// An error raised when attempting the `Repo::cherry_pick_fast` operation.
@@ -394,6 +706,15 @@ pub enum CherryPickFastError {
     #[error("could not get paths touched by commit {commit}")]
     GetPatch { commit: NonZeroOid },
 
+    #[error(
+        "mainline parent {mainline} is not valid for commit {commit} with {parent_count} parents"
+    )]
+    InvalidMainline {
+        commit: NonZeroOid,
+        mainline: u32,
+        parent_count: usize,
+    },
+
     #[error("could not get conflicts generated by cherry-pick of {commit} onto {onto}: {source}")]
     GetConflicts {
         source: git2::Error,
@@ -442,9 +763,213 @@ impl AmendFastOptions {
     }
 }
 
+/// Where `Repo::apply_diff` should apply a diff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApplyLocation {
+    /// Apply the diff to the files in the working copy.
+    WorkDir,
+    /// Apply the diff to the index.
+    Index,
+    /// Apply the diff to both the working copy and the index, as with `git
+    /// apply --index`.
+    Both,
+}
+
+impl From<ApplyLocation> for git2::ApplyLocation {
+    fn from(location: ApplyLocation) -> Self {
+        match location {
+            ApplyLocation::WorkDir => git2::ApplyLocation::WorkDir,
+            ApplyLocation::Index => git2::ApplyLocation::Index,
+            ApplyLocation::Both => git2::ApplyLocation::Both,
+        }
+    }
+}
+
+/// Minimal information about the state of `HEAD` and the working copy. See
+/// `Repo::quick_status`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuickStatus {
+    /// The name of the branch that `HEAD` points to, if any. The `refs/heads/`
+    /// prefix is stripped.
+    pub branch_name: Option<String>,
+
+    /// The OID of the commit that `HEAD` points to, if `HEAD` is not unborn.
+    pub head_oid: Option<NonZeroOid>,
+
+    /// Whether there are any staged or unstaged changes to tracked files.
+    pub is_dirty: bool,
+}
+
+/// Summary information about the state of the repository, suitable for
+/// display in a shell prompt. See `Repo::render_prompt_info`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PromptInfo {
+    /// The name of the branch that `HEAD` points to, if any. The `refs/heads/`
+    /// prefix is stripped.
+    pub branch_name: Option<String>,
+
+    /// The abbreviated OID of the commit that `HEAD` points to, if `HEAD` is
+    /// not unborn.
+    pub short_oid: Option<String>,
+
+    /// The number of commits that the current branch is ahead of and behind
+    /// its upstream branch, respectively. `None` if `HEAD` is detached or the
+    /// branch has no upstream.
+    pub ahead_behind: Option<(usize, usize)>,
+
+    /// Whether there are any staged or unstaged changes to tracked files.
+    pub is_dirty: bool,
+
+    /// The operation in progress, if any, as returned by
+    /// `Repo::get_current_operation_type`.
+    pub operation_type: Option<String>,
+}
+
+impl std::fmt::Display for PromptInfo {
+    /// Render the information in the form `<branch> +<ahead> -<behind> * (<operation>)`,
+    /// where the parts after the branch name are only included if applicable.
+    /// If `HEAD` is detached, the abbreviated commit OID is used in place of
+    /// the branch name.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.branch_name, &self.short_oid) {
+            (Some(branch_name), _) => write!(f, "{branch_name}")?,
+            (None, Some(short_oid)) => write!(f, "{short_oid}")?,
+            (None, None) => write!(f, "(unborn)")?,
+        }
+        if let Some((ahead, behind)) = self.ahead_behind {
+            if ahead > 0 {
+                write!(f, " +{ahead}")?;
+            }
+            if behind > 0 {
+                write!(f, " -{behind}")?;
+            }
+        }
+        if self.is_dirty {
+            write!(f, " *")?;
+        }
+        if let Some(operation_type) = &self.operation_type {
+            write!(f, " ({operation_type})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Potential problems with the state of the repository which could interfere
+/// with operations. See `Repo::check_repo_state`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepoStateWarnings {
+    /// The operation in progress, if any, as returned by
+    /// `Repo::get_current_operation_type`.
+    pub operation_type: Option<String>,
+
+    /// The paths which have unresolved merge conflicts in the index.
+    pub unmerged_paths: Vec<PathBuf>,
+
+    /// Whether `HEAD` is detached and there are changes to tracked files,
+    /// which would be difficult to recover if `HEAD` were moved.
+    pub is_detached_and_dirty: bool,
+
+    /// Whether `HEAD` is unborn, i.e. there are no commits yet.
+    pub is_head_unborn: bool,
+}
+
+impl RepoStateWarnings {
+    /// Whether there are no warnings.
+    pub fn is_empty(&self) -> bool {
+        let RepoStateWarnings {
+            operation_type,
+            unmerged_paths,
+            is_detached_and_dirty,
+            is_head_unborn,
+        } = self;
+        operation_type.is_none()
+            && unmerged_paths.is_empty()
+            && !is_detached_and_dirty
+            && !is_head_unborn
+    }
+}
+
+impl std::fmt::Display for RepoStateWarnings {
+    /// Render each warning on its own line.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(operation_type) = &self.operation_type {
+            writeln!(f, "A {operation_type} operation is in progress.")?;
+        }
+        if !self.unmerged_paths.is_empty() {
+            writeln!(
+                f,
+                "There are unresolved merge conflicts in {} path(s): {}",
+                self.unmerged_paths.len(),
+                self.unmerged_paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .join(", ")
+            )?;
+        }
+        if self.is_detached_and_dirty {
+            writeln!(
+                f,
+                "HEAD is detached and there are uncommitted changes to tracked files."
+            )?;
+        }
+        if self.is_head_unborn {
+            writeln!(f, "HEAD is unborn (try making a commit?)")?;
+        }
+        Ok(())
+    }
+}
+
+/// An entry in the stash list, as would be shown by `git stash list`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StashEntry {
+    /// The index of the stash entry, where `0` is the most recent (i.e.
+    /// `stash@{0}`).
+    pub index: usize,
+
+    /// The message associated with the stash entry.
+    pub message: String,
+
+    /// The OID of the commit storing the stashed changes.
+    pub commit_oid: NonZeroOid,
+}
+
+/// Information about a local branch, as returned by
+/// `Repo::get_local_branch_infos`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BranchInfo {
+    /// The name of the branch, not including any `refs/heads/` prefix.
+    pub name: String,
+
+    /// The OID that the branch points to, or `None` if the branch is not a
+    /// direct reference (which is unusual).
+    pub oid: Option<NonZeroOid>,
+
+    /// The name of the upstream branch which this branch tracks, such as
+    /// `origin/master`, if any.
+    pub upstream_name: Option<String>,
+
+    /// The remote to push this branch to, if any. See
+    /// `Branch::get_push_remote_name`.
+    pub push_remote_name: Option<String>,
+
+    /// The description of the branch from `branch.<name>.description`, if
+    /// any.
+    pub description: Option<String>,
+}
+
 /// Wrapper around `git2::Repository`.
 pub struct Repo {
     pub(super) inner: git2::Repository,
+
+    /// Cache of merge-base queries, keyed by the pair of commit OIDs (with the
+    /// smaller OID first). Since commits are immutable, a cached merge-base
+    /// never becomes invalid.
+    merge_base_cache: RefCell<HashMap<(NonZeroOid, NonZeroOid), Option<NonZeroOid>>>,
+
+    /// Cache of the paths touched by each commit, keyed by commit OID. The
+    /// touched paths are a function of the commit's trees, so a cached entry
+    /// never becomes invalid.
+    touched_paths_cache: RefCell<HashMap<NonZeroOid, Option<HashSet<PathBuf>>>>,
 }
 
 impl std::fmt::Debug for Repo {
@@ -455,10 +980,39 @@ impl std::fmt::Debug for Repo {
 
 impl Repo {
     /// Get the Git repository associated with the given directory.
+    ///
+    /// As with Git itself, the search for the repository stops at any of the
+    /// directories listed in the `GIT_CEILING_DIRECTORIES` environment
+    /// variable.
     #[instrument]
     pub fn from_dir(path: &Path) -> Result<Self> {
-        let repo = git2::Repository::discover(path).map_err(Error::OpenRepo)?;
-        Ok(Repo { inner: repo })
+        let ceiling_dirs = match std::env::var_os("GIT_CEILING_DIRECTORIES") {
+            Some(value) => std::env::split_paths(&value)
+                .filter(|path| !path.as_os_str().is_empty())
+                .collect_vec(),
+            None => Vec::new(),
+        };
+        Repo::from_dir_with_ceiling(path, &ceiling_dirs)
+    }
+
+    /// Get the Git repository associated with the given directory, searching
+    /// parent directories but not crossing into any of the provided ceiling
+    /// directories. See `GIT_CEILING_DIRECTORIES` in `git help git`. The
+    /// search may cross filesystem boundaries.
+    #[instrument]
+    pub fn from_dir_with_ceiling(path: &Path, ceiling_dirs: &[PathBuf]) -> Result<Self> {
+        let repo =
+            git2::Repository::open_ext(path, git2::RepositoryOpenFlags::CROSS_FS, ceiling_dirs)
+                .map_err(Error::OpenRepo)?;
+        Ok(Repo::from_inner(repo))
+    }
+
+    fn from_inner(inner: git2::Repository) -> Self {
+        Repo {
+            inner,
+            merge_base_cache: Default::default(),
+            touched_paths_cache: Default::default(),
+        }
     }
 
     /// Get the Git repository associated with the current directory.
@@ -473,7 +1027,7 @@ impl Repo {
     pub fn try_clone(&self) -> Result<Self> {
         let path = self.get_path();
         let repo = git2::Repository::open(path).map_err(Error::OpenRepo)?;
-        Ok(Repo { inner: repo })
+        Ok(Repo::from_inner(repo))
     }
 
     /// Get the path to the `.git` directory for the repository.
@@ -487,7 +1041,9 @@ impl Repo {
     }
 
     /// Get the path to the directory inside the `.git` directory which contains
-    /// state used for the current rebase (if any).
+    /// state used for the current rebase (if any). For a linked worktree, this
+    /// is inside the worktree's own Git directory, since each worktree can
+    /// have its own rebase in progress.
     pub fn get_rebase_state_dir_path(&self) -> PathBuf {
         self.inner.path().join("rebase-merge")
     }
@@ -498,6 +1054,75 @@ impl Repo {
         self.inner.workdir()
     }
 
+    /// Whether this repository is bare, i.e. has no working copy. Operations
+    /// which read or write the working copy fail with
+    /// `Error::NoWorkingCopyPath` for bare repositories.
+    pub fn is_bare(&self) -> bool {
+        self.inner.is_bare()
+    }
+
+    /// Whether this repository is a shallow clone, i.e. some commits' parents
+    /// are missing from the repository. See `get_shallow_boundary`.
+    pub fn is_shallow(&self) -> bool {
+        self.inner.is_shallow()
+    }
+
+    /// Get the set of commits at the boundary of a shallow clone. These commits
+    /// are present in the repository, but their parents aren't, so history
+    /// traversals should stop at them. Returns an empty set if the repository
+    /// isn't shallow.
+    #[instrument]
+    pub fn get_shallow_boundary(&self) -> Result<HashSet<NonZeroOid>> {
+        // The `shallow` file is shared between all worktrees.
+        let path = self.get_common_dir_path()?.join("shallow");
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+            Err(err) => return Err(Error::ReadShallowFile { source: err, path }),
+        };
+        contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.parse().map_err(|err| Error::ParseShallowFile {
+                    source: err,
+                    line: line.to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Whether this repository is a partial clone, i.e. some objects may be
+    /// missing locally and are fetched on demand from a "promisor" remote.
+    /// This is the case for repositories cloned with `git clone --filter`.
+    #[instrument]
+    pub fn is_partial(&self) -> Result<bool> {
+        let config = self.inner.config().map_err(Error::ReadConfig)?;
+        let is_set = |key: &str| -> Result<bool> {
+            match config.get_entry(key) {
+                Ok(_) => Ok(true),
+                Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(false),
+                Err(err) => Err(Error::ReadConfig(err)),
+            }
+        };
+
+        if is_set("extensions.partialClone")? {
+            return Ok(true);
+        }
+        for remote_name in self.get_all_remote_names()? {
+            let is_promisor = match config.get_bool(&format!("remote.{remote_name}.promisor")) {
+                Ok(value) => value,
+                Err(err) if err.code() == git2::ErrorCode::NotFound => false,
+                Err(err) => return Err(Error::ReadConfig(err)),
+            };
+            if is_promisor || is_set(&format!("remote.{remote_name}.partialclonefilter"))? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     /// Get the index file for this repository.
     pub fn get_index(&self) -> Result<Index> {
         let mut index = self.inner.index().map_err(Error::ReadIndex)?;
@@ -508,12 +1133,59 @@ impl Repo {
 
     /// Get the configuration object for the repository.
     ///
+    /// Values set in files referenced by `include.path` and `includeIf.*.path`
+    /// directives are also visible through this object, as `libgit2` expands
+    /// them when loading the configuration. Note that `libgit2` only supports
+    /// the `gitdir`, `gitdir/i`, and `onbranch` conditions for `includeIf`.
+    ///
+    /// If `extensions.worktreeConfig` is enabled, then values set in the
+    /// current worktree's `config.worktree` file take precedence over the
+    /// others, as with `git config`.
+    ///
     /// **Warning**: This object should only be used for read operations. Write
     /// operations should go to the `config` file under the `.git/branchless`
-    /// directory.
+    /// directory; see `get_writable_config`.
     #[instrument]
     pub fn get_readonly_config(&self) -> Result<impl ConfigRead> {
         let config = self.inner.config().map_err(Error::ReadConfig)?;
+        let worktree_config_enabled = match config.get_bool("extensions.worktreeConfig") {
+            Ok(value) => value,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => false,
+            Err(err) => return Err(Error::ReadConfig(err)),
+        };
+        let worktree_config_path = self.get_path().join("config.worktree");
+        let config = Config::from(config);
+        if worktree_config_enabled && worktree_config_path.exists() {
+            let worktree_config =
+                git2::Config::open(&worktree_config_path).map_err(Error::ReadConfig)?;
+            Ok(config.with_worktree_config(worktree_config))
+        } else {
+            Ok(config)
+        }
+    }
+
+    /// Get the configuration object for the git-branchless-specific
+    /// configuration file (see `get_config_path`), for persisting settings.
+    /// The `.git/branchless` directory is created if it doesn't exist, and the
+    /// file itself is created on the first write.
+    ///
+    /// Reads through this object only see the values set in that file; use
+    /// `get_readonly_config` to read the effective configuration.
+    ///
+    /// Each write is atomic: `libgit2` locks the file by creating a
+    /// `config.lock` file next to it, writes the new contents there, and then
+    /// renames it into place. If another process is writing to the file at the
+    /// same time, the write fails instead of corrupting the file.
+    #[instrument]
+    pub fn get_writable_config(&self) -> Result<impl ConfigRead + ConfigWrite> {
+        let path = self.get_config_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| Error::CreateBranchlessDir {
+                source: err,
+                path: dir.to_path_buf(),
+            })?;
+        }
+        let config = git2::Config::open(&path).map_err(Error::ReadConfig)?;
         Ok(Config::from(config))
     }
 
@@ -585,53 +1257,218 @@ impl Repo {
     }
 
     /// Get the OID for the repository's `HEAD` reference.
+    ///
+    /// This also works for bare repositories, where `HEAD` usually points to
+    /// the default branch, which may be unborn.
     #[instrument]
     pub fn get_head_info(&self) -> Result<ResolvedReferenceInfo> {
-        match self.find_reference(&"HEAD".into())? {
-            Some(reference) => self.resolve_reference(&reference),
-            None => Ok(ResolvedReferenceInfo {
-                oid: None,
+        let head = match self.find_reference(&"HEAD".into())? {
+            Some(head) => head,
+            None => {
+                return Ok(ResolvedReferenceInfo {
+                    oid: None,
+                    reference_name: None,
+                })
+            }
+        };
+
+        // Fast path: `HEAD` is frequently detached when using `git-branchless`,
+        // in which case there's no symbolic target to decode.
+        if head.inner.symbolic_target_bytes().is_none() {
+            return Ok(ResolvedReferenceInfo {
+                oid: head.peel_to_commit()?.map(|commit| commit.get_oid()),
                 reference_name: None,
-            }),
+            });
         }
+        self.resolve_reference(&head)
     }
 
-    /// Set the `HEAD` reference directly to the provided `oid`. Does not touch
-    /// the working copy.
+    /// Get the chain of symbolic references starting at `name`, for display
+    /// purposes. For example, if `HEAD` points to `refs/heads/foo`, then the
+    /// chain for `HEAD` is `[HEAD, refs/heads/foo]`. The chain ends at the
+    /// first reference which is not symbolic, or which doesn't exist (such as
+    /// an unborn branch).
+    ///
+    /// Unlike `resolve_reference`, which only reports the immediate target,
+    /// this shows every intermediate reference. If a reference appears twice,
+    /// the chain is cut off before the repetition.
     #[instrument]
-    pub fn set_head(&self, oid: NonZeroOid) -> Result<()> {
-        self.inner
-            .set_head_detached(oid.inner)
-            .map_err(|err| Error::SetHead { source: err, oid })?;
-        Ok(())
+    pub fn get_reference_chain(&self, name: &ReferenceName) -> Result<Vec<ReferenceName>> {
+        let mut chain = vec![name.clone()];
+        let mut current_name = name.clone();
+        while let Some(reference) = self.find_reference(&current_name)? {
+            let target = match reference.inner.kind() {
+                Some(git2::ReferenceType::Symbolic) => {
+                    match reference.inner.symbolic_target_bytes() {
+                        Some(target) => ReferenceName::from_bytes(target.to_vec())?,
+                        None => return Err(Error::DecodeUtf8 { item: "reference" }),
+                    }
+                }
+                Some(git2::ReferenceType::Direct) | None => break,
+            };
+            if chain.contains(&target) {
+                warn!(?chain, ?target, "Cycle detected in symbolic references");
+                break;
+            }
+            chain.push(target.clone());
+            current_name = target;
+        }
+        Ok(chain)
     }
 
-    /// Detach `HEAD` by making it point directly to its current OID, rather
-    /// than to a branch. If `HEAD` is unborn, logs a warning.
+    /// Determine whether the given reference is checked out in a worktree
+    /// other than this one, i.e. whether it's the target of that worktree's
+    /// `HEAD`. Moving such a reference would leave the other worktree's
+    /// working copy out of sync with its `HEAD`.
     #[instrument]
-    pub fn detach_head(&self, head_info: &ResolvedReferenceInfo) -> Result<()> {
-        match head_info.oid {
-            Some(oid) => self
-                .inner
-                .set_head_detached(oid.inner)
-                .map_err(|err| Error::SetHead { source: err, oid }),
-            None => {
-                warn!("Attempted to detach `HEAD` while `HEAD` is unborn");
-                Ok(())
+    pub fn is_reference_checked_out_elsewhere(&self, name: &ReferenceName) -> Result<bool> {
+        for worktree_repo in self.get_other_worktree_repos()? {
+            let head_info = worktree_repo.get_head_info()?;
+            if head_info.reference_name.as_ref() == Some(name) {
+                return Ok(true);
             }
         }
+        Ok(false)
     }
 
-    /// Detect if an interactive rebase has started but not completed.
-    ///
-    /// Git will send us spurious `post-rewrite` events marked as `amend` during an
-    /// interactive rebase, indicating that some of the commits have been rewritten
-    /// as part of the rebase plan, but not all of them. This function attempts to
-    /// detect when an interactive rebase is underway, and if the current
-    /// `post-rewrite` event is spurious.
-    ///
-    /// There are two practical issues for users as a result of this Git behavior:
-    ///
+    /// Get the path to the Git directory shared by all worktrees. For the main
+    /// worktree, this is the same as `get_path`.
+    fn get_common_dir_path(&self) -> Result<PathBuf> {
+        if !self.inner.is_worktree() {
+            return Ok(self.get_path().to_owned());
+        }
+
+        // The `commondir` file in a linked worktree's Git directory holds
+        // the path to the main Git directory, relative to the former.
+        let commondir_path = self.get_path().join("commondir");
+        let commondir =
+            std::fs::read_to_string(&commondir_path).map_err(|err| Error::ReadCommonDir {
+                source: err,
+                path: commondir_path.clone(),
+            })?;
+        Ok(self.get_path().join(commondir.trim_end()))
+    }
+
+    /// Open the repositories for each worktree other than this one, including
+    /// the main worktree. Worktrees whose directories are missing are skipped.
+    fn get_other_worktree_repos(&self) -> Result<Vec<Repo>> {
+        let mut worktree_repos = Vec::new();
+        if self.inner.is_worktree() {
+            let main_repo =
+                git2::Repository::open(self.get_common_dir_path()?).map_err(Error::OpenRepo)?;
+            worktree_repos.push(Repo::from_inner(main_repo));
+        }
+
+        let this_path = std::fs::canonicalize(self.get_path()).ok();
+        for worktree in self.get_worktrees()? {
+            let name = worktree.get_name();
+            if worktree.inner.validate().is_err() {
+                warn!(?name, "Skipping invalid worktree");
+                continue;
+            }
+            let worktree_repo = worktree.open()?;
+            if std::fs::canonicalize(worktree_repo.get_path()).ok() == this_path {
+                continue;
+            }
+            worktree_repos.push(worktree_repo);
+        }
+        Ok(worktree_repos)
+    }
+
+    /// Get the linked worktrees of this repository, which are those created
+    /// with `git worktree add`. The main worktree isn't included. This returns
+    /// the same worktrees whether it's called on the main worktree or on any
+    /// linked worktree.
+    #[instrument]
+    pub fn get_worktrees(&self) -> Result<Vec<Worktree>> {
+        let mut worktrees = Vec::new();
+        for name in self.inner.worktrees().map_err(Error::GetWorktrees)?.iter() {
+            let name = match name {
+                Some(name) => name,
+                None => {
+                    warn!("Worktree name could not be decoded");
+                    continue;
+                }
+            };
+            let worktree = self
+                .inner
+                .find_worktree(name)
+                .map_err(|err| Error::OpenWorktree {
+                    source: err,
+                    name: name.to_owned(),
+                })?;
+            worktrees.push(Worktree {
+                inner: worktree,
+                name: name.to_owned(),
+            });
+        }
+        Ok(worktrees)
+    }
+
+    /// Open the linked worktree with the given name as a `Repo` whose working
+    /// copy is that worktree. Returns `None` if there is no such worktree.
+    #[instrument]
+    pub fn open_worktree(&self, name: &str) -> Result<Option<Repo>> {
+        // `find_worktree` doesn't distinguish a missing worktree from other
+        // errors, so check the list of worktrees instead.
+        let worktree = match self
+            .get_worktrees()?
+            .into_iter()
+            .find(|worktree| worktree.get_name() == name)
+        {
+            Some(worktree) => worktree,
+            None => return Ok(None),
+        };
+        Ok(Some(worktree.open()?))
+    }
+
+    /// Get the OID that `ORIG_HEAD` points to, which is the position of `HEAD`
+    /// before the last operation which moved it (such as a rebase or reset).
+    /// Returns `None` if `ORIG_HEAD` doesn't exist.
+    #[instrument]
+    pub fn get_orig_head(&self) -> Result<Option<NonZeroOid>> {
+        match self.find_reference(&"ORIG_HEAD".into())? {
+            Some(reference) => Ok(self.resolve_reference(&reference)?.oid),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the `HEAD` reference directly to the provided `oid`. Does not touch
+    /// the working copy.
+    #[instrument]
+    pub fn set_head(&self, oid: NonZeroOid) -> Result<()> {
+        self.inner
+            .set_head_detached(oid.inner)
+            .map_err(|err| Error::SetHead { source: err, oid })?;
+        Ok(())
+    }
+
+    /// Detach `HEAD` by making it point directly to its current OID, rather
+    /// than to a branch. If `HEAD` is unborn, logs a warning.
+    #[instrument]
+    pub fn detach_head(&self, head_info: &ResolvedReferenceInfo) -> Result<()> {
+        match head_info.oid {
+            Some(oid) => self
+                .inner
+                .set_head_detached(oid.inner)
+                .map_err(|err| Error::SetHead { source: err, oid }),
+            None => {
+                warn!("Attempted to detach `HEAD` while `HEAD` is unborn");
+                Ok(())
+            }
+        }
+    }
+
+    /// Detect if an interactive rebase has started but not completed.
+    ///
+    /// Git will send us spurious `post-rewrite` events marked as `amend` during an
+    /// interactive rebase, indicating that some of the commits have been rewritten
+    /// as part of the rebase plan, but not all of them. This function attempts to
+    /// detect when an interactive rebase is underway, and if the current
+    /// `post-rewrite` event is spurious.
+    ///
+    /// There are two practical issues for users as a result of this Git behavior:
+    ///
     ///   * During an interactive rebase, we may see many "processing 1 rewritten
     ///   commit" messages, and then a final "processing X rewritten commits" message
     ///   once the rebase has concluded. This is potentially confusing for users, since
@@ -669,39 +1506,308 @@ impl Repo {
         }
     }
 
+    /// Count the number of commits reachable from `local` but not `upstream`,
+    /// and vice-versa. Returns `(ahead, behind)`.
+    ///
+    /// If `first_parent` is set, only commits along the first-parent chain
+    /// are counted, as with `git rev-list --first-parent --count`. This gives
+    /// more intuitive results for histories containing merge commits.
+    #[instrument]
+    pub fn ahead_behind(
+        &self,
+        local: NonZeroOid,
+        upstream: NonZeroOid,
+        first_parent: bool,
+    ) -> Result<(usize, usize)> {
+        let count_first_parent = |from: NonZeroOid, hide: NonZeroOid| -> Result<usize> {
+            let walk = || -> std::result::Result<usize, git2::Error> {
+                let mut walk = self.inner.revwalk()?;
+                walk.simplify_first_parent()?;
+                walk.push(from.inner)?;
+                walk.hide(hide.inner)?;
+                let mut count = 0;
+                for oid in walk {
+                    oid?;
+                    count += 1;
+                }
+                Ok(count)
+            };
+            walk().map_err(|err| Error::GetAheadBehind {
+                source: err,
+                local,
+                upstream,
+            })
+        };
+
+        if first_parent {
+            let ahead = count_first_parent(local, upstream)?;
+            let behind = count_first_parent(upstream, local)?;
+            Ok((ahead, behind))
+        } else {
+            self.inner
+                .graph_ahead_behind(local.inner, upstream.inner)
+                .map_err(|err| Error::GetAheadBehind {
+                    source: err,
+                    local,
+                    upstream,
+                })
+        }
+    }
+
+    /// Get the current branch, `HEAD` commit, and whether the working copy is
+    /// dirty. Like `render_prompt_info`, this only uses `libgit2` and never
+    /// opens the event log or DAG, so it's cheap enough to call on every
+    /// shell prompt.
+    #[instrument]
+    pub fn quick_status(&self) -> Result<QuickStatus> {
+        let head_info = self.get_head_info()?;
+        let branch_name = head_info.get_branch_name()?.map(|name| name.to_owned());
+
+        let is_dirty = {
+            let mut options = git2::StatusOptions::new();
+            options
+                .include_untracked(false)
+                .include_ignored(false)
+                .exclude_submodules(true);
+            let statuses = self
+                .inner
+                .statuses(Some(&mut options))
+                .map_err(Error::GetStatuses)?;
+            statuses
+                .iter()
+                .any(|entry| entry.status() != git2::Status::CURRENT)
+        };
+
+        Ok(QuickStatus {
+            branch_name,
+            head_oid: head_info.oid,
+            is_dirty,
+        })
+    }
+
+    /// Check for states of the repository which could cause problems for
+    /// operations that modify it, such as an operation already in progress or
+    /// unresolved merge conflicts. Commands can call this before starting
+    /// and refuse to run or warn as appropriate.
+    #[instrument]
+    pub fn check_repo_state(&self) -> Result<RepoStateWarnings> {
+        let QuickStatus {
+            branch_name,
+            head_oid,
+            is_dirty,
+        } = self.quick_status()?;
+
+        let unmerged_paths = {
+            let index = self.get_index()?;
+            if index.has_conflicts() {
+                index.get_conflicted_paths()?
+            } else {
+                Vec::new()
+            }
+        };
+
+        Ok(RepoStateWarnings {
+            operation_type: self.get_current_operation_type().map(|s| s.to_owned()),
+            unmerged_paths,
+            is_detached_and_dirty: branch_name.is_none() && head_oid.is_some() && is_dirty,
+            is_head_unborn: head_oid.is_none(),
+        })
+    }
+
+    /// Gather the information to display in a shell prompt. This only uses
+    /// `libgit2` and doesn't open the event log or DAG, since it's intended
+    /// to be called frequently.
+    #[instrument]
+    pub fn render_prompt_info(&self) -> Result<PromptInfo> {
+        let QuickStatus {
+            branch_name,
+            head_oid,
+            is_dirty,
+        } = self.quick_status()?;
+        let short_oid = match head_oid {
+            Some(oid) => Some(self.find_commit_or_fail(oid)?.get_short_oid()?),
+            None => None,
+        };
+
+        let ahead_behind = match (&branch_name, head_oid) {
+            (Some(branch_name), Some(head_oid)) => {
+                let upstream_oid = match self.find_branch(branch_name, BranchType::Local)? {
+                    Some(branch) => match branch.get_upstream_branch()? {
+                        Some(upstream) => upstream.get_oid()?,
+                        None => None,
+                    },
+                    None => None,
+                };
+                match upstream_oid {
+                    Some(upstream_oid) => Some(self.ahead_behind(head_oid, upstream_oid, false)?),
+                    None => None,
+                }
+            }
+            _ => None,
+        };
+
+        Ok(PromptInfo {
+            branch_name,
+            short_oid,
+            ahead_behind,
+            is_dirty,
+            operation_type: self.get_current_operation_type().map(|s| s.to_owned()),
+        })
+    }
+
+    /// Get the commits reachable from `to` but not from `from`, as with `git
+    /// log from..to`. The commits are returned in topological order, with
+    /// ancestors before their descendants.
+    ///
+    /// `from` doesn't need to be an ancestor of `to`; if it isn't, then the
+    /// commits which are only reachable from `from` are simply excluded from
+    /// the result.
+    #[instrument]
+    pub fn get_range(&self, from: NonZeroOid, to: NonZeroOid) -> Result<Vec<Commit>> {
+        let walk = || -> std::result::Result<Vec<git2::Oid>, git2::Error> {
+            let mut walk = self.inner.revwalk()?;
+            walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+            walk.push(to.inner)?;
+            walk.hide(from.inner)?;
+            walk.collect()
+        };
+        let oids = walk().map_err(|err| Error::GetRange {
+            source: err,
+            from,
+            to,
+        })?;
+        oids.into_iter()
+            .map(|oid| self.find_commit_or_fail(make_non_zero_oid(oid)))
+            .collect()
+    }
+
     /// Find the merge-base between two commits. Returns `None` if a merge-base
     /// could not be found.
+    ///
+    /// Results are cached for the lifetime of this `Repo`, so repeated queries
+    /// for the same pair of commits (in either order) are cheap.
     #[instrument]
     pub fn find_merge_base(&self, lhs: NonZeroOid, rhs: NonZeroOid) -> Result<Option<NonZeroOid>> {
-        match self.inner.merge_base(lhs.inner, rhs.inner) {
-            Ok(merge_base_oid) => Ok(Some(make_non_zero_oid(merge_base_oid))),
-            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
-            Err(err) => Err(Error::FindMergeBase {
-                source: err,
-                lhs,
-                rhs,
-            }),
+        let key = if lhs <= rhs { (lhs, rhs) } else { (rhs, lhs) };
+        if let Some(merge_base_oid) = self.merge_base_cache.borrow().get(&key) {
+            return Ok(*merge_base_oid);
         }
+
+        let merge_base_oid = match self.inner.merge_base(lhs.inner, rhs.inner) {
+            Ok(merge_base_oid) => Some(make_non_zero_oid(merge_base_oid)),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+            Err(err) => {
+                return Err(Error::FindMergeBase {
+                    source: err,
+                    lhs,
+                    rhs,
+                })
+            }
+        };
+        self.merge_base_cache
+            .borrow_mut()
+            .insert(key, merge_base_oid);
+        Ok(merge_base_oid)
     }
 
     /// Get the patch for a commit, i.e. the diff between that commit and its
     /// parent.
     ///
-    /// If the commit has more than one parent, returns `None`.
+    /// If the commit has more than one parent, returns `None`. See
+    /// `get_patch_for_merge_commit` to get the patch for a merge commit.
     #[instrument]
-    pub fn get_patch_for_commit(&self, effects: &Effects, commit: &Commit) -> Result<Option<Diff>> {
+    pub fn get_patch_for_commit(
+        &self,
+        effects: &Effects,
+        commit: &Commit,
+        options: &DiffOptions,
+    ) -> Result<Option<Diff>> {
         let changed_paths = match self.get_paths_touched_by_commit(commit)? {
             None => return Ok(None),
             Some(changed_paths) => changed_paths,
         };
-        let dehydrated_commit = self.dehydrate_commit(
+        let parent = commit.get_only_parent();
+        let diff = self.get_patch_against_parent(
+            effects,
+            commit,
+            parent.as_ref(),
+            changed_paths,
+            options,
+        )?;
+        Ok(Some(diff))
+    }
+
+    /// Get the number of files changed and lines added and removed by the
+    /// given commit, as with `git show --shortstat`. As with
+    /// `get_patch_for_commit`, returns `None` for merge commits.
+    #[instrument]
+    pub fn get_diff_stats_for_commit(
+        &self,
+        effects: &Effects,
+        commit: &Commit,
+        options: &DiffOptions,
+    ) -> Result<Option<DiffStats>> {
+        let diff = match self.get_patch_for_commit(effects, commit, options)? {
+            Some(diff) => diff,
+            None => return Ok(None),
+        };
+        let stats = diff.get_stats().map_err(Error::GetDiffStats)?;
+        Ok(Some(stats))
+    }
+
+    /// Get the patch for a commit relative to its `mainline`th parent, i.e.
+    /// the diff between that parent and the commit, as with `git show -m`.
+    /// The parent number starts from 1, as with `git cherry-pick -m`, so a
+    /// `mainline` of 1 corresponds to `git show --first-parent`.
+    ///
+    /// Unlike `get_patch_for_commit`, this works for merge commits. If the
+    /// commit has no such parent, returns `None`.
+    #[instrument]
+    pub fn get_patch_for_merge_commit(
+        &self,
+        effects: &Effects,
+        commit: &Commit,
+        mainline: u32,
+        options: &DiffOptions,
+    ) -> Result<Option<Diff>> {
+        let parents = commit.get_parents();
+        let parent = match usize::try_from(mainline)
+            .ok()
+            .and_then(|mainline| mainline.checked_sub(1))
+            .and_then(|index| parents.get(index))
+        {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+        let changed_paths = get_changed_paths_between_trees(
+            self,
+            Some(&parent.get_tree()?.inner),
+            Some(&commit.get_tree()?.inner),
+        )
+        .map_err(Error::GetChangedPaths)?;
+        let diff =
+            self.get_patch_against_parent(effects, commit, Some(parent), changed_paths, options)?;
+        Ok(Some(diff))
+    }
+
+    /// Diff the provided commit against `parent`, or against the empty tree if
+    /// `parent` is `None`. Only `changed_paths` are included in the diff.
+    fn get_patch_against_parent(
+        &self,
+        effects: &Effects,
+        commit: &Commit,
+        parent: Option<&Commit>,
+        changed_paths: HashSet<PathBuf>,
+        options: &DiffOptions,
+    ) -> Result<Diff> {
+        let dehydrated_commit = self.dehydrate_commit_onto(
             commit,
             changed_paths
                 .iter()
                 .map(|x| -> &Path { x })
                 .collect_vec()
                 .as_slice(),
-            true,
+            parent,
         )?;
 
         let parent = dehydrated_commit.get_only_parent();
@@ -710,8 +1816,7 @@ impl Repo {
             None => None,
         };
         let current_tree = dehydrated_commit.get_tree()?;
-        let diff = self.get_diff_between_trees(effects, parent_tree.as_ref(), &current_tree, 3)?;
-        Ok(Some(diff))
+        self.get_diff_between_trees(effects, parent_tree.as_ref(), &current_tree, options)
     }
 
     /// Get the diff between two trees. This is more performant than calling
@@ -723,7 +1828,7 @@ impl Repo {
         effects: &Effects,
         old_tree: Option<&Tree>,
         new_tree: &Tree,
-        num_context_lines: usize,
+        options: &DiffOptions,
     ) -> Result<Diff> {
         let (effects, _progress) = effects.start_operation(OperationType::CalculateDiff);
         let _effects = effects;
@@ -731,25 +1836,109 @@ impl Repo {
         let old_tree = old_tree.map(|tree| &tree.inner);
         let new_tree = Some(&new_tree.inner);
 
-        let diff = self
-            .inner
-            .diff_tree_to_tree(
-                old_tree,
-                new_tree,
-                Some(DiffOptions::new().context_lines(num_context_lines.try_into().unwrap())),
-            )
-            .map_err(|err| Error::DiffTreeToTree {
-                source: err,
-                old_tree: old_tree
-                    .map(|tree| MaybeZeroOid::from(tree.id()))
-                    .unwrap_or(MaybeZeroOid::Zero),
-                new_tree: new_tree
-                    .map(|tree| MaybeZeroOid::from(tree.id()))
-                    .unwrap_or(MaybeZeroOid::Zero),
-            })?;
+        let DiffOptions {
+            num_context_lines,
+            pathspec,
+            max_file_size,
+            normalize_line_endings,
+        } = options;
+        let mut diff_options = git2::DiffOptions::new();
+        diff_options.context_lines((*num_context_lines).try_into().unwrap());
+        // Always report changes to submodule pointers, regardless of the
+        // user's submodule configuration. They're rendered as changes to the
+        // `Subproject commit` line, as with `git diff`.
+        diff_options.ignore_submodules(false);
+        for path in pathspec {
+            diff_options.pathspec(path);
+        }
+        if let Some(max_file_size) = max_file_size {
+            diff_options.max_size(i64::try_from(*max_file_size).unwrap_or(i64::MAX));
+        }
+
+        let mut diff_trees = |old_tree: Option<&git2::Tree>, new_tree: Option<&git2::Tree>| {
+            self.inner
+                .diff_tree_to_tree(old_tree, new_tree, Some(&mut diff_options))
+                .map_err(|err| Error::DiffTreeToTree {
+                    source: err,
+                    old_tree: old_tree
+                        .map(|tree| MaybeZeroOid::from(tree.id()))
+                        .unwrap_or(MaybeZeroOid::Zero),
+                    new_tree: new_tree
+                        .map(|tree| MaybeZeroOid::from(tree.id()))
+                        .unwrap_or(MaybeZeroOid::Zero),
+                })
+        };
+        let diff = diff_trees(old_tree, new_tree)?;
+        if !*normalize_line_endings {
+            return Ok(Diff { inner: diff });
+        }
+
+        let normalized_old_tree = match old_tree {
+            Some(old_tree) => Some(self.normalize_line_endings_in_tree(&diff, old_tree, false)?),
+            None => None,
+        };
+        let normalized_new_tree = match new_tree {
+            Some(new_tree) => Some(self.normalize_line_endings_in_tree(&diff, new_tree, true)?),
+            None => None,
+        };
+        let diff = diff_trees(normalized_old_tree.as_ref(), normalized_new_tree.as_ref())?;
         Ok(Diff { inner: diff })
     }
 
+    /// For `DiffOptions::normalize_line_endings`: replace each blob in `tree`
+    /// which is touched by `diff` with a copy whose CRLF line endings are
+    /// converted to LF. Blobs which appear to be binary, since they contain a
+    /// NUL byte, are left as-is, as are all other kinds of whitespace.
+    ///
+    /// If `is_new_tree` is set, `tree` is the new side of `diff`; otherwise,
+    /// it's the old side.
+    fn normalize_line_endings_in_tree<'a>(
+        &'a self,
+        diff: &git2::Diff,
+        tree: &git2::Tree<'a>,
+        is_new_tree: bool,
+    ) -> Result<git2::Tree<'a>> {
+        let mut normalized_entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>> =
+            HashMap::new();
+        for delta in diff.deltas() {
+            let file = if is_new_tree {
+                delta.new_file()
+            } else {
+                delta.old_file()
+            };
+            let path = match file.path() {
+                Some(path) => path,
+                None => continue,
+            };
+            let file_mode = FileMode::from(file.mode());
+            if !matches!(file_mode, FileMode::Blob | FileMode::BlobExecutable) {
+                continue;
+            }
+            let blob_oid = match MaybeZeroOid::from(file.id()) {
+                MaybeZeroOid::NonZero(oid) => oid,
+                MaybeZeroOid::Zero => continue,
+            };
+            let blob = self.find_blob_or_fail(blob_oid)?;
+            let content = blob.get_content();
+            if content.contains(&0) || !content.contains_str("\r\n") {
+                continue;
+            }
+            let normalized_oid = self.create_blob_from_contents(&content.replace("\r\n", "\n"))?;
+            normalized_entries.insert(path.to_path_buf(), Some((normalized_oid, file_mode)));
+        }
+
+        if normalized_entries.is_empty() {
+            return Ok(tree.clone());
+        }
+        let tree = Tree {
+            inner: tree.clone(),
+        };
+        let normalized_tree_oid =
+            hydrate_tree(self, Some(&tree), normalized_entries).map_err(Error::HydrateTree)?;
+        let normalized_tree = self.find_tree_or_fail(normalized_tree_oid)?;
+        Ok(normalized_tree.inner)
+    }
+
     /// Returns the set of paths currently staged to the repository's index.
     #[instrument]
     pub fn get_staged_paths(&self) -> Result<HashSet<PathBuf>> {
@@ -783,6 +1972,8 @@ impl Repo {
     /// commit's tree.
     ///
     /// If the commit has more than one parent, returns `None`.
+    ///
+    /// See `get_commit_deltas` for the kind of change made to each path.
     #[instrument]
     pub fn get_paths_touched_by_commit(&self, commit: &Commit) -> Result<Option<HashSet<PathBuf>>> {
         let parent_commits = commit.get_parents();
@@ -799,60 +1990,367 @@ impl Repo {
         Ok(Some(changed_paths))
     }
 
-    /// Get the patch ID for this commit.
+    /// Get the files which were added, removed, or changed by the given commit,
+    /// along with the kind of each change and the file modes before and after.
+    /// Renames are detected and reported as a single delta. The deltas are
+    /// ordered by path.
+    ///
+    /// If the commit has no parents, every file in the commit's tree is
+    /// reported as added.
+    ///
+    /// If the commit has more than one parent, returns `None`.
+    ///
+    /// Use `get_paths_touched_by_commit` instead if only the paths are needed,
+    /// since it doesn't need to detect renames.
     #[instrument]
-    pub fn get_patch_id(&self, effects: &Effects, commit: &Commit) -> Result<Option<PatchId>> {
-        let patch = match self.get_patch_for_commit(effects, commit)? {
-            None => return Ok(None),
-            Some(diff) => diff,
-        };
-        let patch_id = {
-            let (_effects, _progress) = effects.start_operation(OperationType::CalculatePatchId);
-            patch.inner.patchid(None).map_err(Error::GetPatchId)?
+    pub fn get_commit_deltas(&self, commit: &Commit) -> Result<Option<Vec<FileDelta>>> {
+        let parent_commits = commit.get_parents();
+        let parent_tree = match parent_commits.as_slice() {
+            [] => None,
+            [only_parent] => Some(only_parent.get_tree()?.inner),
+            [..] => return Ok(None),
         };
-        Ok(Some(PatchId { patch_id }))
-    }
 
-    /// Attempt to parse the user-provided object descriptor.
-    pub fn revparse_single_commit(&self, spec: &str) -> Result<Option<Commit>> {
-        if spec.ends_with('@') && spec.len() > 1 {
-            // Weird bug in `libgit2`; it seems that it treats a name like
-            // `foo-@` the same as `@`, and ignores the leading `foo`.
-            return Err(Error::UnsupportedRevParseSpec(spec.to_owned()));
+        let current_tree = commit.get_tree()?.inner;
+        let changed_paths =
+            get_changed_paths_between_trees(self, parent_tree.as_ref(), Some(&current_tree))
+                .map_err(Error::GetChangedPaths)?;
+        if changed_paths.is_empty() {
+            // An empty pathspec would match every path.
+            return Ok(Some(Vec::new()));
         }
 
-        match self.inner.revparse_single(spec) {
-            Ok(object) => match object.into_commit() {
-                Ok(commit) => Ok(Some(Commit { inner: commit })),
-                Err(_) => Ok(None),
-            },
-            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
-            Err(err) => Err(Error::Git(err)),
+        // Limit the diff to the already-computed changed paths, so that the
+        // rest of the tree doesn't need to be examined again.
+        let mut diff_options = git2::DiffOptions::new();
+        diff_options
+            .ignore_submodules(false)
+            .disable_pathspec_match(true);
+        for path in changed_paths.iter().sorted() {
+            diff_options.pathspec(path);
         }
+        let mut diff = self
+            .inner
+            .diff_tree_to_tree(
+                parent_tree.as_ref(),
+                Some(&current_tree),
+                Some(&mut diff_options),
+            )
+            .map_err(|err| Error::DiffTreeToTree {
+                source: err,
+                old_tree: parent_tree
+                    .as_ref()
+                    .map(|tree| MaybeZeroOid::from(tree.id()))
+                    .unwrap_or(MaybeZeroOid::Zero),
+                new_tree: MaybeZeroOid::from(current_tree.id()),
+            })?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+            .map_err(Error::FindRenames)?;
+
+        let deltas = diff.deltas().map(FileDelta::from).collect();
+        Ok(Some(deltas))
     }
 
-    /// Find all references in the repository.
+    /// Load the mailmap for this repository, from the `.mailmap` file in the
+    /// working copy and the `mailmap.file` and `mailmap.blob` configuration
+    /// options. If none of these exist, the mailmap is empty.
+    ///
+    /// Loading the mailmap requires reading from disk, so callers resolving
+    /// many signatures should load it once and reuse it.
+    #[instrument]
+    pub fn get_mailmap(&self) -> Result<Mailmap> {
+        let mailmap = self.inner.mailmap().map_err(Error::ReadMailmap)?;
+        Ok(Mailmap { inner: mailmap })
+    }
+
+    /// Map the name and email of the provided signature to their canonical
+    /// forms using this repository's mailmap. If no mailmap exists, the
+    /// signature is returned unchanged.
+    #[instrument]
+    pub fn resolve_mailmap(&self, signature: &Signature) -> Result<Signature<'static>> {
+        self.get_mailmap()?.resolve_signature(signature)
+    }
+
+    /// Like `get_paths_touched_by_commit`, but results are cached for the
+    /// lifetime of this `Repo`, so repeated queries for the same commit (such
+    /// as during smartlog rendering or revset evaluation) are cheap.
+    #[instrument]
+    pub fn get_paths_touched_by_commit_cached(
+        &self,
+        commit: &Commit,
+    ) -> Result<Option<HashSet<PathBuf>>> {
+        let oid = commit.get_oid();
+        if let Some(touched_paths) = self.touched_paths_cache.borrow().get(&oid) {
+            return Ok(touched_paths.clone());
+        }
+
+        let touched_paths = self.get_paths_touched_by_commit(commit)?;
+        self.touched_paths_cache
+            .borrow_mut()
+            .insert(oid, touched_paths.clone());
+        Ok(touched_paths)
+    }
+
+    /// Determine if the commit added, removed, or changed any path matching
+    /// the provided glob pattern.
+    ///
+    /// If the commit has more than one parent, returns `None`.
+    #[instrument]
+    pub fn contains_touched_path_matching(
+        &self,
+        commit: &Commit,
+        pattern: &glob::Pattern,
+    ) -> Result<Option<bool>> {
+        let touched_paths = match self.get_paths_touched_by_commit_cached(commit)? {
+            Some(touched_paths) => touched_paths,
+            None => return Ok(None),
+        };
+        let result = touched_paths.iter().any(|path| pattern.matches_path(path));
+        Ok(Some(result))
+    }
+
+    /// Get the patch ID for this commit. This depends only on the changes
+    /// introduced by the commit, and not on its metadata (such as its message,
+    /// author, or timestamps), so it can be used to find duplicate commits.
+    ///
+    /// Returns `None` for merge commits. Also returns `None` if the commit
+    /// changes files, but its patch is empty after applying
+    /// `DiffOptions::normalize_line_endings` (see `Diff::is_empty`), since it
+    /// would otherwise be mistaken for a duplicate of every commit which
+    /// doesn't change anything.
+    #[instrument]
+    pub fn get_patch_id(
+        &self,
+        effects: &Effects,
+        commit: &Commit,
+        options: &DiffOptions,
+    ) -> Result<Option<PatchId>> {
+        let patch = match self.get_patch_for_commit(effects, commit, options)? {
+            None => return Ok(None),
+            Some(diff) => diff,
+        };
+        if options.normalize_line_endings
+            && !commit.is_empty()
+            && patch.is_empty().map_err(Error::CheckEmptyPatch)?
+        {
+            return Ok(None);
+        }
+        let patch_id = {
+            let (_effects, _progress) = effects.start_operation(OperationType::CalculatePatchId);
+            patch.inner.patchid(None).map_err(Error::GetPatchId)?
+        };
+        Ok(Some(PatchId { patch_id }))
+    }
+
+    /// Get the patch IDs for each of the provided commits, keyed by commit
+    /// OID. Commits which don't have a patch ID, such as merge commits, are
+    /// omitted from the result.
+    ///
+    /// This reports progress for the batch as a whole, so it should be
+    /// preferred over calling `get_patch_id` in a loop.
+    #[instrument(skip(commits))]
+    pub fn get_patch_ids(
+        &self,
+        effects: &Effects,
+        commits: &[&Commit],
+        options: &DiffOptions,
+    ) -> Result<HashMap<NonZeroOid, PatchId>> {
+        let (effects, progress) = effects.start_operation(OperationType::CalculatePatchId);
+        progress.notify_progress(0, commits.len());
+        let mut result = HashMap::new();
+        for commit in commits {
+            if commit.get_parent_count() <= 1 {
+                if let Some(patch_id) = self.get_patch_id(&effects, commit, options)? {
+                    result.insert(commit.get_oid(), patch_id);
+                }
+            }
+            progress.notify_progress_inc(1);
+        }
+        Ok(result)
+    }
+
+    /// Apply the given diff to the working copy and/or index.
+    ///
+    /// If a hunk fails to apply, the returned error contains the path of the
+    /// file and the line in the original file at which the hunk starts.
+    #[instrument(skip(diff))]
+    pub fn apply_diff(&self, diff: &Diff, location: ApplyLocation) -> Result<()> {
+        // `libgit2` doesn't report which hunk failed to apply, so keep track
+        // of the last delta and hunk it attempted to apply.
+        let current_path: RefCell<Option<PathBuf>> = Default::default();
+        let current_line: RefCell<Option<u32>> = Default::default();
+        let result = {
+            let mut options = git2::ApplyOptions::new();
+            options.delta_callback(|delta| {
+                *current_path.borrow_mut() = delta.and_then(|delta| {
+                    delta
+                        .new_file()
+                        .path()
+                        .or_else(|| delta.old_file().path())
+                        .map(|path| path.to_path_buf())
+                });
+                *current_line.borrow_mut() = None;
+                true
+            });
+            options.hunk_callback(|hunk| {
+                *current_line.borrow_mut() = hunk.map(|hunk| hunk.old_start());
+                true
+            });
+            self.inner
+                .apply(&diff.inner, location.into(), Some(&mut options))
+        };
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(source) => match (current_path.into_inner(), current_line.into_inner()) {
+                (Some(path), Some(line)) => Err(Error::ApplyDiffHunk {
+                    source,
+                    location,
+                    path,
+                    line,
+                }),
+                _ => Err(Error::ApplyDiff { source, location }),
+            },
+        }
+    }
+
+    /// Attempt to parse the user-provided object descriptor.
+    pub fn revparse_single_commit(&self, spec: &str) -> Result<Option<Commit>> {
+        if spec.ends_with('@') && spec.len() > 1 {
+            // Weird bug in `libgit2`; it seems that it treats a name like
+            // `foo-@` the same as `@`, and ignores the leading `foo`.
+            return Err(Error::UnsupportedRevParseSpec(spec.to_owned()));
+        }
+
+        match self.inner.revparse_single(spec) {
+            Ok(object) => match object.into_commit() {
+                Ok(commit) => Ok(Some(Commit { inner: commit })),
+                Err(_) => Ok(None),
+            },
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(Error::Git(err)),
+        }
+    }
+
+    /// Parse the user-provided object descriptor and peel it to a tree. This
+    /// accepts anything which resolves to a commit or tree, such as `main`,
+    /// `main^{tree}`, or `main:`. Returns `None` if the object could not be
+    /// found or could not be peeled to a tree.
+    #[instrument]
+    pub fn resolve_tree(&self, spec: &str) -> Result<Option<Tree>> {
+        if spec.ends_with('@') && spec.len() > 1 {
+            // See `revparse_single_commit`.
+            return Err(Error::UnsupportedRevParseSpec(spec.to_owned()));
+        }
+
+        match self.inner.revparse_single(spec) {
+            Ok(object) => match object.peel_to_tree() {
+                Ok(tree) => Ok(Some(Tree { inner: tree })),
+                Err(_) => Ok(None),
+            },
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(Error::Git(err)),
+        }
+    }
+
+    /// Get the tree associated with the given commit.
+    #[instrument]
+    pub fn peel_to_tree(&self, commit: &Commit) -> Result<Tree> {
+        let tree_oid = commit.inner.tree_id();
+        match self.inner.find_tree(tree_oid) {
+            Ok(tree) => Ok(Tree { inner: tree }),
+            Err(err) => Err(Error::FindTree {
+                source: err,
+                oid: tree_oid.into(),
+            }),
+        }
+    }
+
+    /// Find all references in the repository.
     #[instrument]
     pub fn get_all_references(&self) -> Result<Vec<Reference>> {
         let mut all_references = Vec::new();
+        self.for_each_reference(|reference| {
+            all_references.push(reference);
+            Ok(())
+        })?;
+        Ok(all_references)
+    }
+
+    /// Call `f` on each reference in the repository. Unlike
+    /// `get_all_references`, this doesn't keep all of the references in memory
+    /// at once. If `f` returns an error, iteration stops and the error is
+    /// returned.
+    #[instrument(skip(f))]
+    pub fn for_each_reference<'a>(
+        &'a self,
+        mut f: impl FnMut(Reference<'a>) -> Result<()>,
+    ) -> Result<()> {
         for reference in self.inner.references().map_err(Error::GetReferences)? {
             let reference = reference.map_err(Error::ReadReference)?;
-            all_references.push(Reference { inner: reference });
+            f(Reference {
+                repo: self,
+                inner: reference,
+            })?;
         }
-        Ok(all_references)
+        Ok(())
+    }
+
+    /// Get the commit which each reference in the repository points to, peeling
+    /// tags as necessary. This avoids creating a `Reference` for each entry,
+    /// unlike calling `get_all_references` and `Reference::peel_to_commit`.
+    ///
+    /// References which don't point to a commit (such as tags of trees or
+    /// blobs, or dangling symbolic references) are skipped with a warning.
+    #[instrument]
+    pub fn get_reference_targets(&self) -> Result<HashMap<ReferenceName, NonZeroOid>> {
+        let mut result = HashMap::new();
+        for reference in self.inner.references().map_err(Error::GetReferences)? {
+            let reference = reference.map_err(Error::ReadReference)?;
+            let reference_name = ReferenceName::from_bytes(reference.name_bytes().to_vec())?;
+            match reference.peel(git2::ObjectType::Commit) {
+                Ok(object) => {
+                    result.insert(reference_name, make_non_zero_oid(object.id()));
+                }
+                Err(err)
+                    if matches!(
+                        err.code(),
+                        git2::ErrorCode::NotFound
+                            | git2::ErrorCode::InvalidSpec
+                            | git2::ErrorCode::Peel
+                    ) =>
+                {
+                    warn!(
+                        ?reference_name,
+                        ?err,
+                        "Skipping reference which does not point to a commit"
+                    );
+                }
+                Err(err) => return Err(Error::ResolveReference(err)),
+            }
+        }
+        Ok(result)
     }
 
     /// Check if the repository has staged or unstaged changes. Untracked files
     /// are not included. This operation may take a while.
+    ///
+    /// Of the provided `options`, only `StatusOptions::ignore_submodule_dirty`
+    /// applies.
     #[instrument]
-    pub fn has_changed_files(&self, effects: &Effects, git_run_info: &GitRunInfo) -> Result<bool> {
+    pub fn has_changed_files(
+        &self,
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        options: &StatusOptions,
+    ) -> Result<bool> {
+        let args = ["diff", "--quiet"]
+            .into_iter()
+            .chain(get_ignore_submodules_args(options).iter().copied())
+            .collect_vec();
+        // This is not a mutating operation, so we don't need a transaction ID.
         let exit_code = git_run_info
-            .run(
-                effects,
-                // This is not a mutating operation, so we don't need a transaction ID.
-                None,
-                &["diff", "--quiet"],
-            )
+            .run(effects, None, &args)
             .map_err(Error::ExecGit)?;
         if exit_code.is_success() {
             Ok(false)
@@ -861,7 +2359,8 @@ impl Repo {
         }
     }
 
-    /// Returns the current status of the repo index and working copy.
+    /// Returns the current status of the repo index and working copy, using
+    /// the default `StatusOptions`.
     pub fn get_status(
         &self,
         effects: &Effects,
@@ -870,20 +2369,99 @@ impl Repo {
         head_info: &ResolvedReferenceInfo,
         event_tx_id: Option<EventTransactionId>,
     ) -> Result<(WorkingCopySnapshot, Vec<StatusEntry>)> {
+        self.get_status_with_options(
+            effects,
+            git_run_info,
+            index,
+            head_info,
+            event_tx_id,
+            &StatusOptions::default(),
+        )
+    }
+
+    /// Like `get_status`, but with the provided `StatusOptions`.
+    pub fn get_status_with_options(
+        &self,
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        index: &Index,
+        head_info: &ResolvedReferenceInfo,
+        event_tx_id: Option<EventTransactionId>,
+        options: &StatusOptions,
+    ) -> Result<(WorkingCopySnapshot, Vec<StatusEntry>)> {
+        let (snapshot, statuses, _header) = self.get_status_inner(
+            effects,
+            git_run_info,
+            index,
+            head_info,
+            event_tx_id,
+            options,
+            false,
+        )?;
+        Ok((snapshot, statuses))
+    }
+
+    /// Like `get_status`, but also returns the branch information reported by
+    /// `git status --branch`, such as the current branch and how far it is
+    /// ahead of or behind its upstream branch.
+    pub fn get_status_with_header(
+        &self,
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        index: &Index,
+        head_info: &ResolvedReferenceInfo,
+        event_tx_id: Option<EventTransactionId>,
+    ) -> Result<(WorkingCopySnapshot, Vec<StatusEntry>, StatusHeader)> {
+        self.get_status_inner(
+            effects,
+            git_run_info,
+            index,
+            head_info,
+            event_tx_id,
+            &StatusOptions::default(),
+            true,
+        )
+    }
+
+    fn get_status_inner(
+        &self,
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        index: &Index,
+        head_info: &ResolvedReferenceInfo,
+        event_tx_id: Option<EventTransactionId>,
+        options: &StatusOptions,
+        include_header: bool,
+    ) -> Result<(WorkingCopySnapshot, Vec<StatusEntry>, StatusHeader)> {
+        if self.is_bare() {
+            return Err(Error::NoWorkingCopyPath);
+        }
+
         let (effects, _progress) = effects.start_operation(OperationType::QueryWorkingCopy);
         let _effects = effects;
 
+        let args = [
+            "status",
+            "--porcelain=v2",
+            "--untracked-files=no",
+            "-z",
+            if options.detect_renames {
+                "--find-renames"
+            } else {
+                "--no-renames"
+            },
+        ]
+        .into_iter()
+        .chain(include_header.then(|| "--branch"))
+        .chain(get_ignore_submodules_args(options).iter().copied())
+        .collect_vec();
         let output = git_run_info
-            .run_silent(
-                self,
-                event_tx_id,
-                &["status", "--porcelain=v2", "--untracked-files=no", "-z"],
-                Default::default(),
-            )
+            .run_silent(self, event_tx_id, &args, Default::default())
             .map_err(Error::ExecGit)?
             .stdout;
 
         let not_null_terminator = |c: &u8| *c != 0_u8;
+        let mut header = StatusHeader::default();
         let mut statuses = Vec::new();
         let mut status_bytes = output.into_iter().peekable();
 
@@ -912,6 +2490,15 @@ impl Repo {
                     line.extend(status_bytes.by_ref().take_while(not_null_terminator));
                     line
                 }
+                // Header line, only present with `--branch`.
+                b'#' => {
+                    let line = status_bytes
+                        .by_ref()
+                        .take_while(not_null_terminator)
+                        .collect_vec();
+                    header.parse_line(&line).map_err(Error::ParseStatusHeader)?;
+                    continue;
+                }
                 _ => {
                     return Err(Error::UnknownStatusLinePrefix {
                         prefix: *line_prefix,
@@ -927,7 +2514,7 @@ impl Repo {
 
         let snapshot = WorkingCopySnapshot::create(self, index, head_info, &statuses)
             .map_err(Error::CreateSnapshot)?;
-        Ok((snapshot, statuses))
+        Ok((snapshot, statuses, header))
     }
 
     /// Create a new reference or update an existing one.
@@ -943,7 +2530,67 @@ impl Repo {
             .inner
             .reference(name.as_str(), oid.inner, force, log_message)
             .map_err(Error::CreateReference)?;
-        Ok(Reference { inner: reference })
+        Ok(Reference {
+            repo: self,
+            inner: reference,
+        })
+    }
+
+    /// Create a new reference or update an existing one, attributing the
+    /// reflog entry to `signature` rather than to the identity in the
+    /// repository's configuration.
+    #[instrument]
+    pub fn create_reference_with_signature(
+        &self,
+        name: &ReferenceName,
+        oid: NonZeroOid,
+        force: bool,
+        log_message: &str,
+        signature: &Signature,
+    ) -> Result<Reference> {
+        let mut transaction = self.inner.transaction().map_err(Error::CreateReference)?;
+        transaction
+            .lock_ref(name.as_str())
+            .map_err(Error::CreateReference)?;
+        if !force && self.find_reference(name)?.is_some() {
+            return Err(Error::CreateReference(git2::Error::new(
+                git2::ErrorCode::Exists,
+                git2::ErrorClass::Reference,
+                format!("reference '{}' already exists", name.as_str()),
+            )));
+        }
+        transaction
+            .set_target(
+                name.as_str(),
+                oid.inner,
+                Some(&signature.inner),
+                log_message,
+            )
+            .map_err(Error::CreateReference)?;
+        transaction.commit().map_err(Error::CreateReference)?;
+
+        let reference =
+            self.inner
+                .find_reference(name.as_str())
+                .map_err(|err| Error::FindReference {
+                    source: err,
+                    name: name.clone(),
+                })?;
+        Ok(Reference {
+            repo: self,
+            inner: reference,
+        })
+    }
+
+    /// Start a transaction to update multiple references at once. None of the
+    /// updates are applied until [`RefTransaction::commit`] is called.
+    #[instrument]
+    pub fn reference_transaction(&self) -> Result<RefTransaction> {
+        let transaction = self
+            .inner
+            .transaction()
+            .map_err(Error::CreateReferenceTransaction)?;
+        Ok(RefTransaction { inner: transaction })
     }
 
     /// Get a list of all remote names.
@@ -965,10 +2612,20 @@ impl Repo {
     }
 
     /// Look up a reference with the given name. Returns `None` if not found.
+    ///
+    /// The name `@` is accepted as an alias for `HEAD`, as in Git revision
+    /// specifications.
     #[instrument]
     pub fn find_reference(&self, name: &ReferenceName) -> Result<Option<Reference>> {
-        match self.inner.find_reference(name.as_str()) {
-            Ok(reference) => Ok(Some(Reference { inner: reference })),
+        let lookup_name = match name.as_str() {
+            "@" => "HEAD",
+            name => name,
+        };
+        match self.inner.find_reference(lookup_name) {
+            Ok(reference) => Ok(Some(Reference {
+                repo: self,
+                inner: reference,
+            })),
             Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
             Err(err) => Err(Error::FindReference {
                 source: err,
@@ -995,6 +2652,106 @@ impl Repo {
         Ok(all_branches)
     }
 
+    /// Get information about all local branches in the repository. This is
+    /// more efficient than calling the individual accessors on each `Branch`,
+    /// since the configuration is only read once.
+    #[instrument]
+    pub fn get_local_branch_infos(&self) -> eyre::Result<Vec<BranchInfo>> {
+        let config = self.get_readonly_config()?;
+        let mut branch_infos = Vec::new();
+        for branch in self.get_all_local_branches()? {
+            let name = branch.get_name()?.to_owned();
+            let oid = branch.get_oid()?;
+            let upstream_name = match branch.get_upstream_branch()? {
+                Some(upstream_branch) => Some(upstream_branch.get_name()?.to_owned()),
+                None => None,
+            };
+            let push_remote_name = match config.get(format!("branch.{name}.pushRemote"))? {
+                Some(remote_name) => Some(remote_name),
+                None => config.get(format!("branch.{name}.remote"))?,
+            };
+            let description = config.get(format!("branch.{name}.description"))?;
+            branch_infos.push(BranchInfo {
+                name,
+                oid,
+                upstream_name,
+                push_remote_name,
+                description,
+            });
+        }
+        Ok(branch_infos)
+    }
+
+    /// Get all entries in the stash list, ordered from most to least recent.
+    #[instrument]
+    pub fn get_stashes(&self) -> Result<Vec<StashEntry>> {
+        // `stash_foreach` requires a mutable repository, so open a new copy of
+        // it rather than requiring the caller to have exclusive access.
+        let mut repo = git2::Repository::open(self.get_path()).map_err(Error::OpenRepo)?;
+        let mut stashes = Vec::new();
+        repo.stash_foreach(|index, message, oid| {
+            stashes.push(StashEntry {
+                index,
+                message: message.to_owned(),
+                commit_oid: make_non_zero_oid(*oid),
+            });
+            true
+        })
+        .map_err(Error::GetStashes)?;
+        Ok(stashes)
+    }
+
+    /// Determine whether checking out `target` would overwrite uncommitted
+    /// changes in the index or working copy, in which case a plain `git
+    /// checkout` would refuse to proceed. The index and working copy aren't
+    /// modified.
+    #[instrument]
+    pub fn would_check_out_overwrite_changes(&self, target: &Commit) -> Result<bool> {
+        let mut checkout_builder = git2::build::CheckoutBuilder::new();
+        checkout_builder.safe().dry_run();
+        match self
+            .inner
+            .checkout_tree(target.inner.as_object(), Some(&mut checkout_builder))
+        {
+            Ok(()) => Ok(false),
+            Err(err) if err.code() == git2::ErrorCode::Conflict => Ok(true),
+            Err(err) => Err(Error::CheckOutDryRun {
+                source: err,
+                oid: target.get_oid(),
+            }),
+        }
+    }
+
+    /// Get all local branches in the repository, sorted by the committer time
+    /// of the commits they point to, from most to least recently updated.
+    /// Branches which don't point directly to a commit are skipped.
+    #[instrument]
+    pub fn get_local_branches_sorted_by_recency(&self) -> Result<Vec<Branch>> {
+        let mut branches = Vec::new();
+        for branch in self.get_all_local_branches()? {
+            let commit = match branch.get_oid()? {
+                Some(oid) => match self.find_commit(oid)? {
+                    Some(commit) => commit,
+                    None => continue,
+                },
+                None => continue,
+            };
+            branches.push((commit.get_committer().get_time(), branch));
+        }
+
+        // Sort by name as a tie-breaker, for determinism.
+        branches.sort_by(|(lhs_time, lhs_branch), (rhs_time, rhs_branch)| {
+            rhs_time.cmp(lhs_time).then_with(|| {
+                lhs_branch
+                    .inner
+                    .name_bytes()
+                    .ok()
+                    .cmp(&rhs_branch.inner.name_bytes().ok())
+            })
+        });
+        Ok(branches.into_iter().map(|(_time, branch)| branch).collect())
+    }
+
     /// Look up the branch with the given name. Returns `None` if not found.
     #[instrument]
     pub fn find_branch(&self, name: &str, branch_type: BranchType) -> Result<Option<Branch>> {
@@ -1011,6 +2768,61 @@ impl Repo {
         }
     }
 
+    /// Find all branches of the given type whose names match the provided glob
+    /// pattern, such as `feat/*`. The pattern is matched against the short
+    /// branch name (i.e. without the `refs/heads/` or `refs/remotes/` prefix),
+    /// and the returned branches are sorted by name.
+    #[instrument]
+    pub fn find_branches_matching(
+        &self,
+        glob: &str,
+        branch_type: BranchType,
+    ) -> Result<Vec<Branch>> {
+        let invalid_glob = |reason: String| Error::InvalidBranchGlob {
+            pattern: glob.to_owned(),
+            reason,
+        };
+        if glob.is_empty() {
+            return Err(invalid_glob("pattern is empty".to_string()));
+        }
+        if glob.starts_with("refs/") {
+            return Err(invalid_glob(
+                "pattern should not include the `refs/` prefix".to_string(),
+            ));
+        }
+        if has_unterminated_bracket(glob) {
+            return Err(invalid_glob("unterminated `[`".to_string()));
+        }
+
+        let namespace = match branch_type {
+            BranchType::Local => "refs/heads/",
+            BranchType::Remote => "refs/remotes/",
+        };
+        let full_glob = format!("{namespace}{glob}");
+        let references =
+            self.inner
+                .references_glob(&full_glob)
+                .map_err(|err| Error::FindBranchesMatching {
+                    source: err,
+                    pattern: glob.to_owned(),
+                })?;
+        let mut branches = Vec::new();
+        for reference in references {
+            let reference = reference.map_err(Error::ReadBranch)?;
+            branches.push(Branch {
+                repo: self,
+                inner: git2::Branch::wrap(reference),
+            });
+        }
+        branches.sort_by(|lhs, rhs| {
+            lhs.inner
+                .get()
+                .name_bytes()
+                .cmp(rhs.inner.get().name_bytes())
+        });
+        Ok(branches)
+    }
+
     /// Look up a commit with the given OID. Returns `None` if not found.
     #[instrument]
     pub fn find_commit(&self, oid: NonZeroOid) -> Result<Option<Commit>> {
@@ -1021,24 +2833,76 @@ impl Repo {
         }
     }
 
-    /// Like `find_commit`, but raises a generic error if the commit could not
-    /// be found.
+    /// Look up a commit given an abbreviated (or full) hex OID, such as one
+    /// pasted by the user. Returns `None` if no object matches the prefix, or
+    /// if the matching object is not a commit. Returns
+    /// `Error::AmbiguousPrefix` if more than one object matches.
+    ///
+    /// Unlike `revparse_single_commit`, this only considers object IDs, and not
+    /// reference names or other revision syntax.
     #[instrument]
-    pub fn find_commit_or_fail(&self, oid: NonZeroOid) -> Result<Commit> {
-        match self.inner.find_commit(oid.inner) {
-            Ok(commit) => Ok(Commit { inner: commit }),
-            Err(err) => Err(Error::FindCommit { source: err, oid }),
+    pub fn find_commit_by_prefix(&self, prefix: &str) -> Result<Option<Commit>> {
+        // libgit2 refuses to look up prefixes shorter than this.
+        const MIN_PREFIX_LEN: usize = 4;
+        if prefix.len() < MIN_PREFIX_LEN
+            || prefix.len() > git2::Oid::zero().as_bytes().len() * 2
+            || !prefix.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Ok(None);
         }
-    }
 
-    /// Look up a blob with the given OID. Returns `None` if not found.
-    #[instrument]
-    pub fn find_blob(&self, oid: NonZeroOid) -> Result<Option<Blob>> {
-        match self.inner.find_blob(oid.inner) {
-            Ok(blob) => Ok(Some(Blob { inner: blob })),
-            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
-            Err(err) => Err(Error::FindBlob { source: err, oid }),
-        }
+        let short_oid = git2::Oid::from_str(prefix).map_err(Error::Git)?;
+        let odb = self.inner.odb().map_err(Error::Git)?;
+        let oid = match odb.exists_prefix(short_oid, prefix.len()) {
+            Ok(oid) => oid,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) if err.code() == git2::ErrorCode::Ambiguous => {
+                // libgit2 doesn't report the candidates, so find them
+                // ourselves. This is slow, but only happens in the error case.
+                let prefix = prefix.to_ascii_lowercase();
+                let mut candidates = Vec::new();
+                odb.foreach(|oid| {
+                    if oid.to_string().starts_with(&prefix) {
+                        candidates.push(make_non_zero_oid(*oid));
+                    }
+                    true
+                })
+                .map_err(Error::Git)?;
+                candidates.sort_unstable();
+                candidates.dedup();
+                return Err(Error::AmbiguousPrefix { prefix, candidates });
+            }
+            Err(err) => return Err(Error::Git(err)),
+        };
+
+        match self.inner.find_commit(oid) {
+            Ok(commit) => Ok(Some(Commit { inner: commit })),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(Error::FindCommit {
+                source: err,
+                oid: make_non_zero_oid(oid),
+            }),
+        }
+    }
+
+    /// Like `find_commit`, but raises a generic error if the commit could not
+    /// be found.
+    #[instrument]
+    pub fn find_commit_or_fail(&self, oid: NonZeroOid) -> Result<Commit> {
+        match self.inner.find_commit(oid.inner) {
+            Ok(commit) => Ok(Commit { inner: commit }),
+            Err(err) => Err(Error::FindCommit { source: err, oid }),
+        }
+    }
+
+    /// Look up a blob with the given OID. Returns `None` if not found.
+    #[instrument]
+    pub fn find_blob(&self, oid: NonZeroOid) -> Result<Option<Blob>> {
+        match self.inner.find_blob(oid.inner) {
+            Ok(blob) => Ok(Some(Blob { inner: blob })),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(Error::FindBlob { source: err, oid }),
+        }
     }
 
     /// Like `find_blob`, but raises a generic error if the blob could not be
@@ -1051,6 +2915,126 @@ impl Repo {
         }
     }
 
+    /// Read the note attached to the given object in the notes reference
+    /// `notes_ref` (such as `refs/notes/commits`). Returns `None` if the object
+    /// has no note, or if the notes reference doesn't exist.
+    #[instrument]
+    pub fn read_note(&self, notes_ref: &str, oid: NonZeroOid) -> Result<Option<BString>> {
+        match self.inner.find_note(Some(notes_ref), oid.inner) {
+            Ok(note) => Ok(Some(BString::from(note.message_bytes()))),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(Error::ReadNote {
+                source: err,
+                notes_ref: notes_ref.to_owned(),
+                oid,
+            }),
+        }
+    }
+
+    /// Attach a note with the given contents to the given object in the notes
+    /// reference `notes_ref`, creating the reference if necessary. Any
+    /// existing note for the object is replaced, as with `git notes add -f`.
+    /// Returns the OID of the note blob.
+    #[instrument]
+    pub fn write_note(
+        &self,
+        notes_ref: &str,
+        oid: NonZeroOid,
+        contents: &str,
+        author: &Signature,
+        committer: &Signature,
+    ) -> Result<NonZeroOid> {
+        let note_oid = self
+            .inner
+            .note(
+                &author.inner,
+                &committer.inner,
+                Some(notes_ref),
+                oid.inner,
+                contents,
+                true,
+            )
+            .map_err(|err| Error::WriteNote {
+                source: err,
+                notes_ref: notes_ref.to_owned(),
+                oid,
+            })?;
+        Ok(make_non_zero_oid(note_oid))
+    }
+
+    /// Read the contents of the file at `path` as of the given commit. The path
+    /// is relative to the root of the repository and may contain slashes to
+    /// refer to files in subdirectories.
+    ///
+    /// Returns `None` if there is no entry at that path, or if the entry is not
+    /// a file (i.e. it's a directory or a submodule). For symlinks, the
+    /// contents are the link target.
+    #[instrument]
+    pub fn read_file_at_commit(&self, commit: &Commit, path: &Path) -> Result<Option<Vec<u8>>> {
+        let tree = commit.get_tree()?;
+        let entry = match tree.get_path(path).map_err(Error::ReadTreeEntry)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        match entry.get_filemode() {
+            FileMode::Blob | FileMode::BlobExecutable | FileMode::Link => {}
+            FileMode::Tree | FileMode::Commit | FileMode::Unreadable => return Ok(None),
+        }
+        let blob = self.find_blob_or_fail(entry.get_oid())?;
+        Ok(Some(blob.get_content().to_vec()))
+    }
+
+    /// Create an annotated tag named `name` pointing to the given object, and
+    /// a `refs/tags/` reference pointing to the tag. Returns the OID of the
+    /// new tag object.
+    ///
+    /// If `force` is set, an existing tag with the same name is overwritten;
+    /// otherwise, an error is returned.
+    #[instrument]
+    pub fn create_tag(
+        &self,
+        name: &str,
+        target: NonZeroOid,
+        tagger: &Signature,
+        message: &str,
+        force: bool,
+    ) -> Result<NonZeroOid> {
+        let target_object = match self.inner.find_object(target.inner, None) {
+            Ok(target_object) => target_object,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => {
+                return Err(Error::FindObject { oid: target })
+            }
+            Err(err) => {
+                return Err(Error::CreateTag {
+                    source: err,
+                    name: name.to_owned(),
+                })
+            }
+        };
+        let oid = self
+            .inner
+            .tag(name, &target_object, &tagger.inner, message, force)
+            .map_err(|err| Error::CreateTag {
+                source: err,
+                name: name.to_owned(),
+            })?;
+        Ok(make_non_zero_oid(oid))
+    }
+
+    /// Look up an annotated tag with the given OID. Returns `None` if not
+    /// found.
+    ///
+    /// Lightweight tags aren't objects of their own; look them up with
+    /// `find_reference` instead.
+    #[instrument]
+    pub fn find_tag(&self, oid: NonZeroOid) -> Result<Option<Tag>> {
+        match self.inner.find_tag(oid.inner) {
+            Ok(tag) => Ok(Some(Tag { inner: tag })),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(Error::FindTag { source: err, oid }),
+        }
+    }
+
     /// Look up the commit with the given OID and render a friendly description
     /// of it, or render an error message if not found.
     pub fn friendly_describe_commit_from_oid(
@@ -1072,8 +3056,23 @@ impl Repo {
 
     /// Read a file from disk and create a blob corresponding to its contents.
     /// If the file doesn't exist on disk, returns `None` instead.
+    ///
+    /// If the path is a symbolic link, it's not followed. Instead, the blob
+    /// contains the link target, as Git stores it for entries with
+    /// `FileMode::Link`.
     #[instrument]
     pub fn create_blob_from_path(&self, path: &Path) -> Result<Option<NonZeroOid>> {
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_symlink() {
+                let target = std::fs::read_link(path).map_err(|err| Error::ReadSymlink {
+                    source: err,
+                    path: path.to_owned(),
+                })?;
+                let oid = self.create_blob_from_contents(&Vec::from_path_lossy(&target))?;
+                return Ok(Some(oid));
+            }
+        }
+
         match self.inner.blob_path(path) {
             Ok(oid) => Ok(Some(make_non_zero_oid(oid))),
             Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
@@ -1084,6 +3083,35 @@ impl Repo {
         }
     }
 
+    /// Create a new blob with the contents read from `reader`. The contents are
+    /// written to the object database incrementally, so that large files
+    /// don't need to be loaded into memory all at once.
+    #[instrument(skip(reader))]
+    pub fn create_blob_from_stream(&self, mut reader: impl Read) -> Result<NonZeroOid> {
+        let mut writer = self.inner.blob_writer(None).map_err(Error::CreateBlob)?;
+        io::copy(&mut reader, &mut writer).map_err(Error::WriteBlobStream)?;
+        let oid = writer.commit().map_err(Error::CreateBlob)?;
+        Ok(make_non_zero_oid(oid))
+    }
+
+    /// Get the commit currently checked out in the submodule at the given
+    /// path on disk. Returns `None` if the submodule isn't populated or its
+    /// `HEAD` is unborn.
+    #[instrument]
+    pub fn get_submodule_head_oid(&self, path: &Path) -> Result<Option<NonZeroOid>> {
+        let submodule_repo = match git2::Repository::open_ext(
+            path,
+            git2::RepositoryOpenFlags::NO_SEARCH,
+            std::iter::empty::<&Path>(),
+        ) {
+            Ok(repo) => Repo::from_inner(repo),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(Error::OpenRepo(err)),
+        };
+        let head_info = submodule_repo.get_head_info()?;
+        Ok(head_info.oid)
+    }
+
     /// Create a blob corresponding to the provided byte slice.
     #[instrument]
     pub fn create_blob_from_contents(&self, contents: &[u8]) -> Result<NonZeroOid> {
@@ -1120,6 +3148,396 @@ impl Repo {
         Ok(make_non_zero_oid(oid))
     }
 
+    /// Create a new merge commit with the given parents. This is like
+    /// `create_commit`, except that it fails unless at least two distinct
+    /// parents are provided, to guard against accidentally creating a
+    /// single-parent "merge".
+    ///
+    /// The provided tree is used as-is, so any merge conflicts must have
+    /// already been resolved when producing it.
+    #[instrument]
+    pub fn create_merge_commit(
+        &self,
+        message: &str,
+        parents: &[&Commit],
+        resolved_tree: &Tree,
+        author: &Signature,
+        committer: &Signature,
+    ) -> Result<NonZeroOid> {
+        let parent_oids = parents.iter().map(|parent| parent.get_oid()).collect_vec();
+        let num_distinct_parents = parent_oids.iter().collect::<HashSet<_>>().len();
+        if parent_oids.len() < 2 || num_distinct_parents != parent_oids.len() {
+            return Err(Error::InvalidMergeParents { parent_oids });
+        }
+        self.create_commit(
+            None,
+            author,
+            committer,
+            message,
+            resolved_tree,
+            parents.to_vec(),
+        )
+    }
+
+    /// Create a new commit with additional headers, which are written after
+    /// the standard headers (`tree`, `parent`, `author`, `committer`) and
+    /// before the commit message. Header values may span multiple lines.
+    ///
+    /// Header names must be non-empty, must not contain whitespace, and must
+    /// not be one of the standard headers; otherwise, an error is returned.
+    /// The new commit is written to the object database, but no references
+    /// are updated.
+    #[instrument]
+    pub fn create_commit_with_headers(
+        &self,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &Tree,
+        parents: Vec<&Commit>,
+        headers: &[(String, String)],
+    ) -> Result<NonZeroOid> {
+        for (name, _value) in headers {
+            let is_valid = !name.is_empty()
+                && !name.chars().any(|c| c.is_whitespace() || c.is_control())
+                && !matches!(
+                    name.as_str(),
+                    "tree" | "parent" | "author" | "committer" | "encoding"
+                );
+            if !is_valid {
+                return Err(Error::InvalidCommitHeaderName(name.clone()));
+            }
+        }
+
+        let parents = parents
+            .iter()
+            .map(|commit| &commit.inner)
+            .collect::<Vec<_>>();
+        let buffer = self
+            .inner
+            .commit_create_buffer(
+                &author.inner,
+                &committer.inner,
+                message,
+                &tree.inner,
+                parents.as_slice(),
+            )
+            .map_err(Error::CreateCommit)?;
+
+        // The standard headers are separated from the message by the first
+        // blank line.
+        let (header_bytes, message_bytes) =
+            match buffer.windows(2).position(|window| window == b"\n\n") {
+                Some(index) => buffer.split_at(index + 1),
+                None => (&*buffer, &b"\n"[..]),
+            };
+        let mut contents = header_bytes.to_vec();
+        for (name, value) in headers {
+            // Continuation lines of a multi-line header value are indented by a
+            // single space.
+            contents.extend(name.as_bytes());
+            contents.push(b' ');
+            contents.extend(value.replace('\n', "\n ").as_bytes());
+            contents.push(b'\n');
+        }
+        contents.extend(message_bytes);
+
+        let odb = self.inner.odb().map_err(Error::CreateCommit)?;
+        let oid = odb
+            .write(git2::ObjectType::Commit, &contents)
+            .map_err(Error::CreateCommit)?;
+        Ok(make_non_zero_oid(oid))
+    }
+
+    /// Create a new commit whose message is the provided bytes, which need not
+    /// be valid UTF-8. If `encoding` is provided, it's recorded in the
+    /// commit's `encoding` header, so that Git can re-encode the message when
+    /// displaying it; the message itself is written as-is. This can be used to
+    /// faithfully recreate a commit whose message isn't UTF-8 (see
+    /// `Commit::get_message_encoding`).
+    ///
+    /// The new commit is written to the object database, but no references
+    /// are updated.
+    #[instrument]
+    pub fn create_commit_bytes(
+        &self,
+        author: &Signature,
+        committer: &Signature,
+        message: &[u8],
+        encoding: Option<&str>,
+        tree: &Tree,
+        parents: Vec<&Commit>,
+    ) -> Result<NonZeroOid> {
+        if let Some(encoding) = encoding {
+            if encoding.is_empty()
+                || encoding
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control())
+            {
+                return Err(Error::InvalidCommitEncoding(encoding.to_owned()));
+            }
+        }
+
+        let parents = parents
+            .iter()
+            .map(|commit| &commit.inner)
+            .collect::<Vec<_>>();
+        // Build the standard headers with an empty message, and then add the
+        // `encoding` header and the real message.
+        let buffer = self
+            .inner
+            .commit_create_buffer(
+                &author.inner,
+                &committer.inner,
+                "",
+                &tree.inner,
+                parents.as_slice(),
+            )
+            .map_err(Error::CreateCommit)?;
+        let header_bytes = match buffer.windows(2).position(|window| window == b"\n\n") {
+            Some(index) => &buffer[..index + 1],
+            None => &*buffer,
+        };
+        let mut contents = header_bytes.to_vec();
+        if let Some(encoding) = encoding {
+            contents.extend(b"encoding ");
+            contents.extend(encoding.as_bytes());
+            contents.push(b'\n');
+        }
+        contents.push(b'\n');
+        contents.extend(message);
+
+        let odb = self.inner.odb().map_err(Error::CreateCommit)?;
+        let oid = odb
+            .write(git2::ObjectType::Commit, &contents)
+            .map_err(Error::CreateCommit)?;
+        Ok(make_non_zero_oid(oid))
+    }
+
+    /// Create a new commit, as with `create_commit`, but sign it according to
+    /// `commit_signing`. If `commit_signing` is `None`, the commit is created
+    /// unsigned. Callers creating many commits should read the signing
+    /// settings once with `get_commit_signing`.
+    ///
+    /// The new commit is written to the object database, but no references
+    /// are updated.
+    #[instrument]
+    pub fn create_signed_commit(
+        &self,
+        commit_signing: Option<&CommitSigning>,
+        author: &Signature,
+        committer: &Signature,
+        message: &str,
+        tree: &Tree,
+        parents: Vec<&Commit>,
+    ) -> Result<NonZeroOid> {
+        let commit_signing = match commit_signing {
+            Some(commit_signing) => commit_signing,
+            None => return self.create_commit(None, author, committer, message, tree, parents),
+        };
+
+        let parents = parents
+            .iter()
+            .map(|commit| &commit.inner)
+            .collect::<Vec<_>>();
+        let buffer = self
+            .inner
+            .commit_create_buffer(
+                &author.inner,
+                &committer.inner,
+                message,
+                &tree.inner,
+                parents.as_slice(),
+            )
+            .map_err(Error::CreateCommit)?;
+        let buffer = buffer.as_str().ok_or(Error::DecodeUtf8 {
+            item: "commit buffer",
+        })?;
+
+        let signature = self.sign_buffer(commit_signing, committer, buffer.as_bytes())?;
+        let oid = self
+            .inner
+            .commit_signed(buffer, &signature, None)
+            .map_err(Error::CreateCommit)?;
+        Ok(make_non_zero_oid(oid))
+    }
+
+    /// Amend `commit` as with `Commit::amend_commit`, but sign the new commit
+    /// according to `commit_signing`. If `commit_signing` is `None`, the new
+    /// commit is created unsigned.
+    ///
+    /// If `update_ref` is provided, that reference (or the reference it
+    /// symbolically points to) is updated to point to the new commit.
+    #[instrument]
+    pub fn amend_signed_commit(
+        &self,
+        commit_signing: Option<&CommitSigning>,
+        commit: &Commit,
+        update_ref: Option<&str>,
+        author: Option<&Signature>,
+        committer: Option<&Signature>,
+        message: Option<&str>,
+        tree: Option<&Tree>,
+    ) -> Result<NonZeroOid> {
+        if commit_signing.is_none() {
+            return commit.amend_commit(update_ref, author, committer, message, tree);
+        }
+
+        let original_author = commit.get_author();
+        let original_committer = commit.get_committer();
+        let original_message = commit.get_message_raw()?;
+        let message = match message {
+            Some(message) => message,
+            None => original_message.to_str().map_err(|_| Error::DecodeUtf8 {
+                item: "commit message",
+            })?,
+        };
+        let original_tree = commit.get_tree()?;
+        let parents = commit.get_parents();
+        let oid = self.create_signed_commit(
+            commit_signing,
+            author.unwrap_or(&original_author),
+            committer.unwrap_or(&original_committer),
+            message,
+            tree.unwrap_or(&original_tree),
+            parents.iter().collect(),
+        )?;
+
+        if let Some(update_ref) = update_ref {
+            self.inner
+                .find_reference(update_ref)
+                .and_then(|reference| reference.resolve())
+                .and_then(|mut reference| {
+                    reference.set_target(oid.inner, "commit (amend)")?;
+                    Ok(())
+                })
+                .map_err(Error::Amend)?;
+        }
+        Ok(oid)
+    }
+
+    /// Produce a detached signature for the provided buffer by invoking the
+    /// configured signing program, in the same way that `git commit -S` does.
+    fn sign_buffer(
+        &self,
+        commit_signing: &CommitSigning,
+        committer: &Signature,
+        buffer: &[u8],
+    ) -> Result<String> {
+        let CommitSigning {
+            format,
+            program,
+            signing_key,
+        } = commit_signing;
+        let sign_error = |message: String| Error::SignCommit {
+            program: program.clone(),
+            message,
+        };
+        let run_program = |args: &[&OsStr], input: Option<&[u8]>| -> Result<Output> {
+            let mut child = Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|err| sign_error(format!("could not start signing program: {err}")))?;
+            if let Some(input) = input {
+                let mut stdin = child.stdin.take().unwrap();
+                stdin
+                    .write_all(input)
+                    .map_err(|err| sign_error(format!("could not write commit: {err}")))?;
+            }
+            let output = child
+                .wait_with_output()
+                .map_err(|err| sign_error(format!("could not wait for signing program: {err}")))?;
+            if !output.status.success() {
+                return Err(sign_error(format!(
+                    "signing program failed ({}): {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            Ok(output)
+        };
+
+        let signature = match format {
+            CommitSigningFormat::OpenPgp | CommitSigningFormat::X509 => {
+                let key = match signing_key {
+                    Some(signing_key) => signing_key.clone(),
+                    None => committer.inner.to_string(),
+                };
+                let output = run_program(
+                    &["--status-fd=2".as_ref(), "-bsau".as_ref(), key.as_ref()],
+                    Some(buffer),
+                )?;
+                if output.stdout.is_empty() {
+                    return Err(sign_error(
+                        "signing program produced no signature".to_string(),
+                    ));
+                }
+                output.stdout
+            }
+
+            CommitSigningFormat::Ssh => {
+                let signing_key = signing_key.as_ref().ok_or_else(|| {
+                    sign_error("user.signingKey must be set to sign with an SSH key".to_string())
+                })?;
+                let tempfile_dir = self.get_tempfile_dir();
+                let make_tempfile = |contents: &[u8]| -> Result<NamedTempFile> {
+                    std::fs::create_dir_all(&tempfile_dir)
+                        .and_then(|()| NamedTempFile::new_in(&tempfile_dir))
+                        .and_then(|mut file| {
+                            file.write_all(contents)?;
+                            Ok(file)
+                        })
+                        .map_err(|err| sign_error(format!("could not write temporary file: {err}")))
+                };
+
+                // As with Git, the key may be given literally rather than as
+                // the path to a key file.
+                let literal_key = match signing_key.strip_prefix("key::") {
+                    Some(literal_key) => Some(literal_key),
+                    None => signing_key
+                        .starts_with("ssh-")
+                        .then(|| signing_key.as_str()),
+                };
+                let key_file = literal_key
+                    .map(|literal_key| make_tempfile(literal_key.as_bytes()))
+                    .transpose()?;
+                let key_path = match &key_file {
+                    Some(key_file) => key_file.path(),
+                    None => Path::new(signing_key),
+                };
+
+                let buffer_file = make_tempfile(buffer)?;
+                run_program(
+                    &[
+                        "-Y".as_ref(),
+                        "sign".as_ref(),
+                        "-n".as_ref(),
+                        "git".as_ref(),
+                        "-f".as_ref(),
+                        key_path.as_os_str(),
+                        buffer_file.path().as_os_str(),
+                    ],
+                    None,
+                )?;
+
+                let mut signature_path = buffer_file.path().as_os_str().to_owned();
+                signature_path.push(".sig");
+                let signature = std::fs::read(&signature_path)
+                    .map_err(|err| sign_error(format!("could not read signature: {err}")));
+                if let Err(err) = std::fs::remove_file(&signature_path) {
+                    warn!(?err, ?signature_path, "Could not remove signature file");
+                }
+                signature?
+            }
+        };
+
+        String::from_utf8(signature)
+            .map_err(|_| sign_error("signature was not valid UTF-8".to_string()))
+    }
+
     /// Cherry-pick a commit in memory and return the resulting index.
     #[instrument]
     pub fn cherry_pick_commit(
@@ -1139,7 +3557,8 @@ impl Repo {
         Ok(Index { inner: index })
     }
 
-    /// Cherry-pick a commit in memory and return the resulting tree.
+    /// Cherry-pick a commit in memory and return the resulting tree, along
+    /// with whether the commit became empty as a result.
     ///
     /// The `libgit2` routines operate on entire `Index`es, which contain one
     /// entry per file in the repository. When operating on a large repository,
@@ -1148,45 +3567,98 @@ impl Repo {
     /// involved indexes by filtering out any unchanged entries from the input
     /// trees, then call into `libgit2`, then add back the unchanged entries to
     /// the output tree.
+    ///
+    /// If the commit touches more paths than allowed by
+    /// `options.max_dehydrated_paths`, then the full indexes are used instead,
+    /// since the filtering would cost more than it saves.
     #[instrument]
     pub fn cherry_pick_fast<'repo>(
         &'repo self,
         patch_commit: &'repo Commit,
         target_commit: &'repo Commit,
         options: &CherryPickFastOptions,
-    ) -> std::result::Result<Tree<'repo>, CherryPickFastError> {
+    ) -> std::result::Result<CherryPickFastResult<'repo>, CherryPickFastError> {
         let CherryPickFastOptions {
             reuse_parent_tree_if_possible,
+            mainline,
+            max_dehydrated_paths,
         } = options;
 
+        let patch_parent = match mainline {
+            None => patch_commit.get_only_parent(),
+            Some(mainline) => {
+                let parents = patch_commit.get_parents();
+                let parent_count = parents.len();
+                match usize::try_from(*mainline)
+                    .ok()
+                    .and_then(|mainline| mainline.checked_sub(1))
+                    .and_then(|index| parents.into_iter().nth(index))
+                {
+                    Some(parent) if parent_count > 1 => Some(parent),
+                    Some(_) | None => {
+                        return Err(CherryPickFastError::InvalidMainline {
+                            commit: patch_commit.get_oid(),
+                            mainline: *mainline,
+                            parent_count,
+                        })
+                    }
+                }
+            }
+        };
+
         if *reuse_parent_tree_if_possible {
-            if let Some(only_parent) = patch_commit.get_only_parent() {
+            if let Some(only_parent) = &patch_parent {
                 if only_parent.get_tree()?.get_oid() == target_commit.get_tree()?.get_oid() {
                     // If this patch is being applied to the same commit it was
                     // originally based on, then we can skip cherry-picking
                     // altogether, and use its tree directly. This is common e.g.
                     // when only rewording a commit message.
-                    return Ok(patch_commit.get_tree()?);
+                    return Ok(CherryPickFastResult {
+                        tree: patch_commit.get_tree()?,
+                        became_empty: false,
+                    });
                 }
             };
         }
 
-        let changed_pathbufs = self
-            .get_paths_touched_by_commit(patch_commit)?
-            .ok_or_else(|| CherryPickFastError::GetPatch {
-                commit: patch_commit.get_oid(),
-            })?
-            .into_iter()
-            .collect_vec();
+        let changed_pathbufs = match (mainline, &patch_parent) {
+            (Some(_), Some(patch_parent)) => get_changed_paths_between_trees(
+                self,
+                Some(&patch_parent.get_tree()?.inner),
+                Some(&patch_commit.get_tree()?.inner),
+            )
+            .map_err(Error::GetChangedPaths)?,
+            _ => self
+                .get_paths_touched_by_commit(patch_commit)?
+                .ok_or_else(|| CherryPickFastError::GetPatch {
+                    commit: patch_commit.get_oid(),
+                })?,
+        }
+        .into_iter()
+        .collect_vec();
         let changed_paths = changed_pathbufs.iter().map(PathBuf::borrow).collect_vec();
+        let patch_was_empty = changed_pathbufs.is_empty();
+
+        // The cost of dehydrating and rehydrating trees grows with the number
+        // of changed paths, so for sufficiently large patches, it's faster to
+        // cherry-pick using the full index.
+        let use_full_index = matches!(
+            max_dehydrated_paths,
+            Some(max_dehydrated_paths) if changed_paths.len() > *max_dehydrated_paths
+        );
 
-        let dehydrated_patch_commit =
-            self.dehydrate_commit(patch_commit, changed_paths.as_slice(), true)?;
-        let dehydrated_target_commit =
-            self.dehydrate_commit(target_commit, changed_paths.as_slice(), false)?;
-
-        let rebased_index =
-            self.cherry_pick_commit(&dehydrated_patch_commit, &dehydrated_target_commit, 0)?;
+        let mut rebased_index = if use_full_index {
+            self.cherry_pick_commit(patch_commit, target_commit, mainline.unwrap_or(0))?
+        } else {
+            let dehydrated_patch_commit = self.dehydrate_commit_onto(
+                patch_commit,
+                changed_paths.as_slice(),
+                patch_parent.as_ref(),
+            )?;
+            let dehydrated_target_commit =
+                self.dehydrate_commit(target_commit, changed_paths.as_slice(), false)?;
+            self.cherry_pick_commit(&dehydrated_patch_commit, &dehydrated_target_commit, 0)?
+        };
         let rebased_tree = {
             if rebased_index.has_conflicts() {
                 let conflicting_paths = {
@@ -1238,6 +3710,15 @@ impl Repo {
 
                 return Err(CherryPickFastError::MergeConflict { conflicting_paths });
             }
+            if use_full_index {
+                let rebased_tree_oid = self.write_index_to_tree(&mut rebased_index)?;
+                return Ok(CherryPickFastResult {
+                    became_empty: !patch_was_empty
+                        && rebased_tree_oid == target_commit.get_tree()?.get_oid(),
+                    tree: self.find_tree_or_fail(rebased_tree_oid)?,
+                });
+            }
+
             let rebased_entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>> =
                 changed_pathbufs
                     .into_iter()
@@ -1270,7 +3751,46 @@ impl Repo {
                     .map_err(CherryPickFastError::HydrateTree)?;
             self.find_tree_or_fail(rebased_tree_oid)?
         };
-        Ok(rebased_tree)
+        let became_empty =
+            !patch_was_empty && rebased_tree.get_oid() == target_commit.get_tree()?.get_oid();
+        Ok(CherryPickFastResult {
+            tree: rebased_tree,
+            became_empty,
+        })
+    }
+
+    /// Determine whether applying `patch_commit` onto `target_commit` would
+    /// produce no changes, i.e. whether `target_commit` already contains its
+    /// changes. A patch which is already empty is also empty when applied.
+    /// If applying the patch would conflict, then it's not considered empty.
+    ///
+    /// This uses `cherry_pick_fast`, so it's cheap when `target_commit` has the
+    /// same tree as the patch's parent.
+    #[instrument]
+    pub fn would_be_empty(
+        &self,
+        patch_commit: &Commit,
+        target_commit: &Commit,
+    ) -> std::result::Result<bool, CherryPickFastError> {
+        match self.cherry_pick_fast(
+            patch_commit,
+            target_commit,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: true,
+                mainline: None,
+                // The result doesn't depend on how the patch is applied.
+                max_dehydrated_paths: None,
+            },
+        ) {
+            Ok(CherryPickFastResult {
+                tree,
+                became_empty: _,
+            }) => Ok(tree.get_oid() == target_commit.get_tree()?.get_oid()),
+            Err(CherryPickFastError::MergeConflict {
+                conflicting_paths: _,
+            }) => Ok(false),
+            Err(err) => Err(err),
+        }
     }
 
     #[instrument]
@@ -1279,6 +3799,22 @@ impl Repo {
         commit: &Commit,
         changed_paths: &[&Path],
         base_on_parent: bool,
+    ) -> Result<Commit> {
+        let parent = if base_on_parent {
+            commit.get_only_parent()
+        } else {
+            None
+        };
+        self.dehydrate_commit_onto(commit, changed_paths, parent.as_ref())
+    }
+
+    /// Like `dehydrate_commit`, but base the dehydrated commit on the
+    /// dehydrated version of the provided parent, if any.
+    fn dehydrate_commit_onto(
+        &self,
+        commit: &Commit,
+        changed_paths: &[&Path],
+        parent: Option<&Commit>,
     ) -> Result<Commit> {
         let tree = commit.get_tree()?;
         let dehydrated_tree_oid =
@@ -1293,16 +3829,12 @@ impl Repo {
             commit.get_oid()
         );
 
-        let parents = if base_on_parent {
-            match commit.get_only_parent() {
-                Some(parent) => {
-                    let dehydrated_parent = self.dehydrate_commit(&parent, changed_paths, false)?;
-                    vec![dehydrated_parent]
-                }
-                None => vec![],
+        let parents = match parent {
+            Some(parent) => {
+                let dehydrated_parent = self.dehydrate_commit(parent, changed_paths, false)?;
+                vec![dehydrated_parent]
             }
-        } else {
-            vec![]
+            None => vec![],
         };
         let dehydrated_commit_oid = self.create_commit(
             None,
@@ -1356,12 +3888,18 @@ impl Repo {
     /// Amends the provided parent commit in memory and returns the resulting tree.
     ///
     /// Only amends the files provided in the options, and only supports amending from
-    /// either the working tree or the index, but not both.
+    /// either the working tree or the index, but not both. Changes to a file's
+    /// mode (such as setting the executable bit) are amended even if the
+    /// contents of the file are unchanged.
     ///
     /// See `Repo::cherry_pick_fast` for motivation for performing the operation
     /// in-memory.
     #[instrument]
     pub fn amend_fast(&self, parent_commit: &Commit, opts: &AmendFastOptions) -> Result<Tree> {
+        if self.is_bare() {
+            return Err(Error::NoWorkingCopyPath);
+        }
+
         let parent_commit_pathbufs = self
             .get_paths_touched_by_commit(parent_commit)?
             .ok_or_else(|| Error::GetPatch {
@@ -1456,6 +3994,75 @@ impl Repo {
 
         Ok(amended_tree)
     }
+
+    /// Amends the provided parent commit in memory as with `amend_fast`, and
+    /// creates the resulting commit.
+    ///
+    /// The new commit keeps the author and parents of the original commit. Its
+    /// committer timestamp is updated to `now`, unless `preserve_timestamps`
+    /// is set. If `new_message` is provided, it replaces the original commit
+    /// message. The new commit is signed according to `commit_signing`, as
+    /// with `create_signed_commit`.
+    ///
+    /// If `update_ref` is provided, that reference (or the reference it
+    /// symbolically points to) is updated to point to the new commit. Otherwise,
+    /// it's up to the caller to move `HEAD` or any branches to the new commit.
+    #[instrument]
+    pub fn amend_fast_commit(
+        &self,
+        parent_commit: &Commit,
+        opts: &AmendFastOptions,
+        new_message: Option<&str>,
+        update_ref: Option<&str>,
+        commit_signing: Option<&CommitSigning>,
+        preserve_timestamps: bool,
+        now: SystemTime,
+    ) -> Result<NonZeroOid> {
+        let amended_tree = self.amend_fast(parent_commit, opts)?;
+
+        let committer = parent_commit.get_committer();
+        let committer = if preserve_timestamps {
+            committer
+        } else {
+            committer.update_timestamp(now)?
+        };
+
+        self.amend_signed_commit(
+            commit_signing,
+            parent_commit,
+            update_ref,
+            None,
+            Some(&committer),
+            new_message,
+            Some(&amended_tree),
+        )
+    }
+}
+
+/// The mailmap for a repository, used to map the names and emails in commit
+/// signatures to their canonical forms. See `git help gitmailmap`.
+pub struct Mailmap {
+    inner: git2::Mailmap,
+}
+
+impl std::fmt::Debug for Mailmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Mailmap>")
+    }
+}
+
+impl Mailmap {
+    /// Map the name and email of the provided signature to their canonical
+    /// forms. If there's no applicable mailmap entry, the signature is returned
+    /// unchanged.
+    #[instrument]
+    pub fn resolve_signature(&self, signature: &Signature) -> Result<Signature<'static>> {
+        let signature = self
+            .inner
+            .resolve_signature(&signature.inner)
+            .map_err(Error::ResolveMailmap)?;
+        Ok(Signature { inner: signature })
+    }
 }
 
 /// The signature of a commit, identifying who it was made by and when it was made.
@@ -1470,6 +4077,7 @@ impl std::fmt::Debug for Signature<'_> {
 }
 
 impl<'repo> Signature<'repo> {
+    /// Create a signature for commits made automatically by `git-branchless`.
     #[instrument]
     pub fn automated() -> Result<Self> {
         Ok(Signature {
@@ -1482,16 +4090,41 @@ impl<'repo> Signature<'repo> {
         })
     }
 
-    /// Update the timestamp of this signature to a new time.
+    /// Update the timestamp of this signature to a new time. The timezone
+    /// offset of the original signature is kept; use
+    /// `update_timestamp_with_offset` to set it as well.
     #[instrument]
     pub fn update_timestamp(self, now: SystemTime) -> Result<Signature<'repo>> {
+        let offset_minutes = self.inner.when().offset_minutes();
+        self.update_timestamp_with_offset(now, offset_minutes)
+    }
+
+    /// Update the timestamp of this signature to a new time, recorded in the
+    /// timezone `offset_minutes` minutes east of UTC.
+    #[instrument]
+    pub fn update_timestamp_with_offset(
+        self,
+        now: SystemTime,
+        offset_minutes: i32,
+    ) -> Result<Signature<'repo>> {
         let seconds: i64 = now
             .duration_since(SystemTime::UNIX_EPOCH)
             .map_err(Error::SystemTime)?
             .as_secs()
             .try_into()
             .map_err(Error::IntegerConvert)?;
-        let time = git2::Time::new(seconds, self.inner.when().offset_minutes());
+        self.with_time(git2::Time::new(seconds, offset_minutes))
+    }
+
+    /// Change the timezone of this signature to `offset_minutes` minutes east
+    /// of UTC, without changing the instant that it refers to.
+    #[instrument]
+    pub fn with_offset(self, offset_minutes: i32) -> Result<Signature<'repo>> {
+        let seconds = self.inner.when().seconds();
+        self.with_time(git2::Time::new(seconds, offset_minutes))
+    }
+
+    fn with_time(self, time: git2::Time) -> Result<Signature<'repo>> {
         let name = match self.inner.name() {
             Some(name) => name,
             None => {
@@ -1519,10 +4152,12 @@ impl<'repo> Signature<'repo> {
         }
     }
 
+    /// Get the name of the signer, if it's valid UTF-8.
     pub fn get_name(&self) -> Option<&str> {
         self.inner.name()
     }
 
+    /// Get the email of the signer, if it's valid UTF-8.
     pub fn get_email(&self) -> Option<&str> {
         self.inner.email()
     }
@@ -1646,6 +4281,16 @@ impl<'repo> Commit<'repo> {
         Ok(BString::from(self.inner.message_raw_bytes()))
     }
 
+    /// Get the encoding of the commit message, as recorded in the commit's
+    /// `encoding` header. Returns `None` if there is no such header, in which
+    /// case the message is assumed to be UTF-8.
+    #[instrument]
+    pub fn get_message_encoding(&self) -> Option<String> {
+        self.inner
+            .message_encoding()
+            .map(|encoding| encoding.to_owned())
+    }
+
     /// Get the author of this commit.
     #[instrument]
     pub fn get_author(&self) -> Signature {
@@ -1662,6 +4307,17 @@ impl<'repo> Commit<'repo> {
         }
     }
 
+    /// Whether the author and committer of this commit have the same name and
+    /// email. Timestamps are not compared. A mismatch may indicate that the
+    /// commit was rewritten by someone other than its author.
+    #[instrument]
+    pub fn author_committer_match(&self) -> bool {
+        let author = self.inner.author();
+        let committer = self.inner.committer();
+        author.name_bytes() == committer.name_bytes()
+            && author.email_bytes() == committer.email_bytes()
+    }
+
     /// Get the `Tree` object associated with this commit.
     #[instrument]
     pub fn get_tree(&self) -> Result<Tree> {
@@ -1692,6 +4348,72 @@ impl<'repo> Commit<'repo> {
         Ok(result)
     }
 
+    /// Produce a message for rewording this commit to `new_message`, keeping
+    /// the trailers (such as `Signed-off-by` or `Change-Id`) from this commit's
+    /// current message. The new message is prettified as with
+    /// `message_prettify`.
+    ///
+    /// If `new_message` has its own trailer block, then its trailers take
+    /// precedence: any existing trailer whose key appears there is assumed to
+    /// have been edited by the caller and is not carried over. The remaining
+    /// existing trailers are appended to the new message's trailer block, in
+    /// their original order.
+    #[instrument]
+    pub fn reword_preserving_trailers(
+        &self,
+        new_message: &str,
+        comment_char: Option<char>,
+    ) -> Result<String> {
+        let new_message = message_prettify(new_message, comment_char)?;
+        let new_trailers =
+            message_trailers_bytes(new_message.as_str()).map_err(Error::ReadMessageTrailer)?;
+        let new_trailer_keys: HashSet<String> = new_trailers
+            .iter()
+            .filter_map(|(key, _value)| std::str::from_utf8(key).ok())
+            .map(|key| key.to_ascii_lowercase())
+            .collect();
+        let preserved_trailers = self
+            .get_trailers()?
+            .into_iter()
+            .filter(|(key, _value)| !new_trailer_keys.contains(&key.to_ascii_lowercase()))
+            .collect_vec();
+        if preserved_trailers.is_empty() {
+            return Ok(new_message);
+        }
+
+        let mut result = new_message;
+        if !result.is_empty() && new_trailer_keys.is_empty() {
+            // Separate the new trailer block from the rest of the message.
+            result.push('\n');
+        }
+        for (key, value) in preserved_trailers {
+            result.push_str(&format!("{key}: {value}\n"));
+        }
+        Ok(result)
+    }
+
+    /// Get the change ID for this commit from its `Change-Id` trailer, if any.
+    /// Unlike the commit OID, the change ID is preserved when the commit is
+    /// rewritten, so it can be used to follow a change across rebases.
+    #[instrument]
+    pub fn get_change_id(&self) -> Result<Option<String>> {
+        self.get_change_id_from_trailer(DEFAULT_CHANGE_ID_TRAILER)
+    }
+
+    /// Same as `get_change_id`, but read the change ID from the trailer with
+    /// the given name. If there are multiple such trailers, the last one is
+    /// used.
+    #[instrument]
+    pub fn get_change_id_from_trailer(&self, trailer_name: &str) -> Result<Option<String>> {
+        let change_id = self
+            .get_trailers()?
+            .into_iter()
+            .rev()
+            .find(|(key, _value)| key.eq_ignore_ascii_case(trailer_name))
+            .map(|(_key, value)| value.trim().to_owned());
+        Ok(change_id)
+    }
+
     /// Print a one-line description of this commit containing its OID and
     /// summary.
     #[instrument]
@@ -1702,11 +4424,11 @@ impl<'repo> Commit<'repo> {
                 commit: self.clone(),
             },
             &mut [
-                &mut CommitOidDescriptor::new(true).map_err(|err| Error::DescribeCommit {
+                &mut CommitOidDescriptor::new(true, None).map_err(|err| Error::DescribeCommit {
                     source: err,
                     commit: self.get_oid(),
                 })?,
-                &mut CommitMessageDescriptor::new(&Redactor::Disabled).map_err(|err| {
+                &mut CommitMessageDescriptor::new(&Redactor::Disabled, false).map_err(|err| {
                     Error::DescribeCommit {
                         source: err,
                         commit: self.get_oid(),
@@ -1818,6 +4540,117 @@ impl<'repo> Blob<'repo> {
     pub fn get_content(&self) -> &[u8] {
         self.inner.content()
     }
+
+    /// Get the size of the blob's contents, in bytes.
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    /// Determine whether the blob's contents appear to be binary, using the
+    /// same heuristic as Git (i.e. whether there's a NUL byte near the start
+    /// of the contents).
+    pub fn is_binary(&self) -> bool {
+        self.inner.is_binary()
+    }
+}
+
+/// A linked worktree of a repository. See `Repo::get_worktrees`.
+pub struct Worktree {
+    inner: git2::Worktree,
+    name: String,
+}
+
+impl std::fmt::Debug for Worktree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Worktree name={:?}>", self.name)
+    }
+}
+
+impl Worktree {
+    /// Get the name of this worktree, which identifies it to commands like
+    /// `git worktree lock`.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the path to the working copy of this worktree. The directory may
+    /// not exist if the worktree was deleted without being pruned.
+    pub fn get_path(&self) -> &Path {
+        self.inner.path()
+    }
+
+    /// Whether this worktree is locked, which prevents it from being pruned,
+    /// moved, or removed.
+    #[instrument]
+    pub fn is_locked(&self) -> Result<bool> {
+        match self.inner.is_locked() {
+            Ok(git2::WorktreeLockStatus::Unlocked) => Ok(false),
+            Ok(git2::WorktreeLockStatus::Locked(_)) => Ok(true),
+            Err(err) => Err(Error::OpenWorktree {
+                source: err,
+                name: self.name.clone(),
+            }),
+        }
+    }
+
+    /// Open this worktree as a `Repo`.
+    #[instrument]
+    pub fn open(&self) -> Result<Repo> {
+        let repo = git2::Repository::open_from_worktree(&self.inner).map_err(|err| {
+            Error::OpenWorktree {
+                source: err,
+                name: self.name.clone(),
+            }
+        })?;
+        Ok(Repo::from_inner(repo))
+    }
+}
+
+/// Represents an annotated tag.
+pub struct Tag<'repo> {
+    inner: git2::Tag<'repo>,
+}
+
+impl std::fmt::Debug for Tag<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Tag oid={}>", self.inner.id())
+    }
+}
+
+impl<'repo> Tag<'repo> {
+    /// Get the OID of this tag object.
+    #[instrument]
+    pub fn get_oid(&self) -> NonZeroOid {
+        make_non_zero_oid(self.inner.id())
+    }
+
+    /// Get the name of this tag, if it's valid UTF-8.
+    #[instrument]
+    pub fn get_name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    /// Get the OID of the object which this tag points to. This is usually a
+    /// commit, but may be any kind of object, including another tag.
+    #[instrument]
+    pub fn get_target_oid(&self) -> NonZeroOid {
+        make_non_zero_oid(self.inner.target_id())
+    }
+
+    /// Get the tagger of this tag, if any. Some old tags were created without
+    /// a tagger.
+    #[instrument]
+    pub fn get_tagger(&self) -> Option<Signature> {
+        self.inner
+            .tagger()
+            .map(|tagger| Signature { inner: tagger })
+    }
+
+    /// Get the message of this tag, or an empty message if it has none.
+    #[instrument]
+    pub fn get_message(&self) -> BString {
+        BString::from(self.inner.message_bytes().unwrap_or_default())
+    }
 }
 
 /// The target of a reference.
@@ -1893,8 +4726,80 @@ impl AsRef<str> for ReferenceName {
     }
 }
 
+/// A batch of reference updates, which are applied together when the
+/// transaction is committed. Each reference must be locked with
+/// [`RefTransaction::lock_ref`] before it can be updated.
+///
+/// If the transaction is dropped without being committed, the locks are
+/// released and none of the updates are applied.
+pub struct RefTransaction<'repo> {
+    inner: git2::Transaction<'repo>,
+}
+
+impl std::fmt::Debug for RefTransaction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<RefTransaction>")
+    }
+}
+
+impl<'repo> RefTransaction<'repo> {
+    /// Lock the given reference so that it can be updated as part of this
+    /// transaction. Fails if the reference is already locked, such as by
+    /// another Git process.
+    #[instrument]
+    pub fn lock_ref(&mut self, name: &ReferenceName) -> Result<()> {
+        self.inner
+            .lock_ref(name.as_str())
+            .map_err(|err| Error::LockReference {
+                source: err,
+                name: name.clone(),
+            })
+    }
+
+    /// Set the given reference to point to `oid` when the transaction is
+    /// committed, creating it if it doesn't exist.
+    #[instrument]
+    pub fn set_target(
+        &mut self,
+        name: &ReferenceName,
+        oid: NonZeroOid,
+        log_message: &str,
+    ) -> Result<()> {
+        self.inner
+            .set_target(name.as_str(), oid.inner, None, log_message)
+            .map_err(|err| Error::UpdateReferenceInTransaction {
+                source: err,
+                name: name.clone(),
+            })
+    }
+
+    /// Delete the given reference when the transaction is committed.
+    #[instrument]
+    pub fn remove(&mut self, name: &ReferenceName) -> Result<()> {
+        self.inner
+            .remove(name.as_str())
+            .map_err(|err| Error::UpdateReferenceInTransaction {
+                source: err,
+                name: name.clone(),
+            })
+    }
+
+    /// Apply all of the updates in this transaction and release the locks.
+    ///
+    /// Note that `libgit2` may fail partway through applying the updates, in
+    /// which case some of them will have been applied. See
+    /// <https://github.com/libgit2/libgit2/issues/5918>.
+    #[instrument]
+    pub fn commit(self) -> Result<()> {
+        self.inner
+            .commit()
+            .map_err(Error::CommitReferenceTransaction)
+    }
+}
+
 /// Represents a reference to an object.
 pub struct Reference<'repo> {
+    repo: &'repo Repo,
     inner: git2::Reference<'repo>,
 }
 
@@ -1934,6 +4839,32 @@ impl<'repo> Reference<'repo> {
         }
     }
 
+    /// Get the entries of this reference's reflog, from newest to oldest.
+    /// Returns an empty list if the reference has no reflog.
+    #[instrument]
+    pub fn get_reflog(&self) -> Result<Vec<ReflogEntry>> {
+        let name = self.get_name()?;
+        let reflog = match self.repo.inner.reflog(name.as_str()) {
+            Ok(reflog) => reflog,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(Error::ReadReflog { source: err, name }),
+        };
+        let entries = reflog
+            .iter()
+            .map(|entry| ReflogEntry {
+                old_oid: entry.id_old().into(),
+                new_oid: entry.id_new().into(),
+                committer: Signature {
+                    inner: entry.committer().to_owned(),
+                },
+                message: entry
+                    .message_bytes()
+                    .map(|message| String::from_utf8_lossy(message).into_owned()),
+            })
+            .collect();
+        Ok(entries)
+    }
+
     /// Delete the reference.
     #[instrument]
     pub fn delete(&mut self) -> Result<()> {
@@ -1942,6 +4873,24 @@ impl<'repo> Reference<'repo> {
     }
 }
 
+/// An entry in the reflog of a reference. See `Reference::get_reflog`.
+#[derive(Debug)]
+pub struct ReflogEntry {
+    /// The OID which the reference pointed to before the update, or zero if
+    /// the reference was created by the update.
+    pub old_oid: MaybeZeroOid,
+
+    /// The OID which the reference pointed to after the update, or zero if
+    /// the reference was deleted by the update.
+    pub new_oid: MaybeZeroOid,
+
+    /// The identity which made the update, and when it was made.
+    pub committer: Signature<'static>,
+
+    /// The message describing the update, if any. Invalid UTF-8 is replaced.
+    pub message: Option<String>,
+}
+
 /// Determine what kind of branch a reference is, given its name. The returned
 /// `suffix` value is converted to a `String` to be rendered to the screen, so
 /// it may have lost some information if the reference name had unusual
@@ -2070,6 +5019,11 @@ impl Time {
     pub fn to_naive_date_time(&self) -> NaiveDateTime {
         NaiveDateTime::from_timestamp(self.inner.seconds(), 0)
     }
+
+    /// Get the timezone offset of this time, in minutes east of UTC.
+    pub fn get_offset_minutes(&self) -> i32 {
+        self.inner.offset_minutes()
+    }
 }
 
 /// Represents a Git branch.
@@ -2128,6 +5082,35 @@ impl<'repo> Branch<'repo> {
         }
     }
 
+    /// Get the full reference name of the upstream branch which this branch is
+    /// configured to track, such as `refs/remotes/origin/master`. Unlike
+    /// `get_upstream_branch`, this returns the name even if the upstream
+    /// branch no longer exists (i.e. it's "gone").
+    #[instrument]
+    pub fn get_upstream_branch_name(&self) -> Result<Option<ReferenceName>> {
+        let reference_name = self.inner.get().name().ok_or(Error::DecodeUtf8 {
+            item: "reference name",
+        })?;
+        match self.repo.inner.branch_upstream_name(reference_name) {
+            Ok(upstream_name) => {
+                let upstream_name = upstream_name.as_str().ok_or(Error::DecodeUtf8 {
+                    item: "upstream branch name",
+                })?;
+                Ok(Some(ReferenceName::from(upstream_name)))
+            }
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => {
+                let branch_name = self.inner.name_bytes().map_err(|_err| Error::DecodeUtf8 {
+                    item: "branch name",
+                })?;
+                Err(Error::FindUpstreamBranch {
+                    source: err,
+                    name: String::from_utf8_lossy(branch_name).into_owned(),
+                })
+            }
+        }
+    }
+
     /// Get the associated remote to push to for this branch. If there is no
     /// associated remote, returns `None`. Note that this never reads the value
     /// of `push.remoteDefault`.
@@ -2150,6 +5133,7 @@ impl<'repo> Branch<'repo> {
     /// Convert the branch into its underlying `Reference`.
     pub fn into_reference(self) -> Reference<'repo> {
         Reference {
+            repo: self.repo,
             inner: self.inner.into_reference(),
         }
     }
@@ -2157,257 +5141,3144 @@ impl<'repo> Branch<'repo> {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::core::config::get_commit_signing;
     use crate::git::status::FileMode;
-    use crate::git::FileStatus;
-    use crate::testing::make_git;
+    use crate::git::{FileStatus, SubmoduleChange};
+    use crate::testing::{
+        make_git, make_git_with_remote_repo, ExpectedHead, GitInitOptions, GitRunOptions,
+        GitWrapperWithRemoteRepo,
+    };
 
     use super::*;
 
     #[test]
-    fn test_parse_git_version_output() {
-        assert_eq!(
-            "git version 12.34.56".parse::<GitVersion>().unwrap(),
-            GitVersion(12, 34, 56)
-        );
-        assert_eq!(
-            "git version 12.34.56\n".parse::<GitVersion>().unwrap(),
-            GitVersion(12, 34, 56)
-        );
-        assert_eq!(
-            "git version 12.34.56.78.abcdef"
-                .parse::<GitVersion>()
-                .unwrap(),
-            GitVersion(12, 34, 56)
-        );
+    fn test_get_head_info() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo_with_options(&GitInitOptions {
+            make_initial_commit: false,
+            run_branchless_init: false,
+        })?;
+        git.assert_head(ExpectedHead::Unborn("master"))?;
 
-        // See https://github.com/arxanas/git-branchless/issues/69
-        assert_eq!(
-            "git version 2.33.0-rc0".parse::<GitVersion>().unwrap(),
-            GitVersion(2, 33, 0)
-        );
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.assert_head(ExpectedHead::Branch("master", test1_oid))?;
 
-        // See https://github.com/arxanas/git-branchless/issues/85
-        assert_eq!(
-            "git version 2.33.GIT".parse::<GitVersion>().unwrap(),
-            GitVersion(2, 33, 0)
-        );
+        git.detach_head()?;
+        git.assert_head(ExpectedHead::Detached(test1_oid))?;
+
+        git.run(&["checkout", "-b", "foo"])?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.assert_head(ExpectedHead::Branch("foo", test2_oid))?;
+
+        // `HEAD` may point to a branch which doesn't exist yet even when other
+        // branches do.
+        git.run(&["checkout", "--orphan", "bar"])?;
+        git.assert_head(ExpectedHead::Unborn("bar"))?;
+
+        Ok(())
     }
 
     #[test]
-    fn test_cherry_pick_fast() -> eyre::Result<()> {
+    fn test_find_reference_at_alias() -> eyre::Result<()> {
         let git = make_git()?;
         git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
 
-        git.run(&["checkout", "-b", "foo"])?;
+        let repo = git.get_repo()?;
+        let reference = repo.find_reference(&"@".into())?.unwrap();
+        assert_eq!(reference.get_name()?, ReferenceName::from("HEAD"));
+        let info = repo.resolve_reference(&reference)?;
+        assert_eq!(info.oid, Some(test1_oid));
+        assert_eq!(
+            info.reference_name,
+            Some(ReferenceName::from("refs/heads/master"))
+        );
+
+        // A reflog selector is not a valid reference name, even when it
+        // refers to the current value of `HEAD`; it has to go through
+        // `revparse_single_commit` instead.
+        let result = repo.find_reference(&"@{0}".into());
+        assert!(
+            matches!(result, Err(Error::FindReference { .. })),
+            "{result:?}"
+        );
+        assert_eq!(
+            repo.revparse_single_commit("@{0}")?
+                .map(|commit| commit.get_oid()),
+            Some(test1_oid)
+        );
+
+        git.run(&["checkout", "--detach"])?;
+        let reference = repo.find_reference(&"@".into())?.unwrap();
+        let info = repo.resolve_reference(&reference)?;
+        assert_eq!(info.oid, Some(test1_oid));
+        assert_eq!(info.reference_name, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_auto_comment_char() {
+        assert_eq!(select_auto_comment_char("foo\n\nbar\n"), Some('#'));
+        assert_eq!(select_auto_comment_char("fix issue #123\n"), Some('#'));
+        assert_eq!(
+            select_auto_comment_char("foo\n\n#include <stdio.h>\n"),
+            Some(';')
+        );
+        assert_eq!(
+            select_auto_comment_char("# heading\n; semicolon\n@ at\n"),
+            Some('!')
+        );
+        assert_eq!(
+            select_auto_comment_char(
+                &AUTO_COMMENT_CHAR_CANDIDATES
+                    .iter()
+                    .map(|c| format!("{c}\n"))
+                    .collect::<String>()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_writable_config() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let repo = git.get_repo()?;
+        std::fs::remove_dir_all(repo.get_path().join("branchless"))?;
+
+        {
+            let mut config = repo.get_writable_config()?;
+            config.set("branchless.test.string", "value")?;
+            config.set("branchless.test.bool", false)?;
+            config.set("branchless.test.int", 42)?;
+            config.set("branchless.test.removed", "value")?;
+            config.remove("branchless.test.removed")?;
+        }
+        assert!(repo.get_config_path().exists());
+
+        let config = repo.get_writable_config()?;
+        assert_eq!(
+            config.get("branchless.test.string")?,
+            Some("value".to_string())
+        );
+        assert_eq!(config.get("branchless.test.bool")?, Some(false));
+        assert_eq!(config.get("branchless.test.int")?, Some(42_i64));
+        assert_eq!(config.get::<String, _>("branchless.test.removed")?, None);
+
+        // The values are visible through the repository configuration, which
+        // includes the file.
+        assert_eq!(
+            repo.get_readonly_config()?.get("branchless.test.int")?,
+            Some(42_i64)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_readonly_config_worktree() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.run(&["config", "branchless.test.value", "local"])?;
+        git.run(&["worktree", "add", "--detach", "worktree"])?;
+
+        let repo = git.get_repo()?;
+        let worktree_repo = Repo::from_dir(&git.repo_path.join("worktree"))?;
+        let get_value = |repo: &Repo| -> eyre::Result<Option<String>> {
+            repo.get_readonly_config()?.get("branchless.test.value")
+        };
+        assert_eq!(get_value(&repo)?, Some("local".to_string()));
+        assert_eq!(get_value(&worktree_repo)?, Some("local".to_string()));
+
+        git.run(&["config", "extensions.worktreeConfig", "true"])?;
+        git.run(&["config", "--worktree", "core.sparseCheckout", "true"])?;
+        git.run(&[
+            "-C",
+            "worktree",
+            "config",
+            "--worktree",
+            "branchless.test.value",
+            "worktree",
+        ])?;
+        assert_eq!(get_value(&repo)?, Some("local".to_string()));
+        assert_eq!(get_value(&worktree_repo)?, Some("worktree".to_string()));
+        assert_eq!(
+            repo.get_readonly_config()?.get("core.sparseCheckout")?,
+            Some(true)
+        );
+        assert_eq!(
+            worktree_repo
+                .get_readonly_config()?
+                .get::<bool, _>("core.sparseCheckout")?,
+            None
+        );
+
+        // The per-worktree config is ignored unless the extension is enabled.
+        git.run(&["config", "extensions.worktreeConfig", "false"])?;
+        assert_eq!(get_value(&worktree_repo)?, Some("local".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_reference_checked_out_elsewhere() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["worktree", "add", "-b", "feature", "worktree1"])?;
+        git.run(&["worktree", "add", "--detach", "worktree2"])?;
+
+        let master: ReferenceName = "refs/heads/master".into();
+        let feature: ReferenceName = "refs/heads/feature".into();
+        let repo = git.get_repo()?;
+        let worktree1_repo = Repo::from_dir(&git.repo_path.join("worktree1"))?;
+        let worktree2_repo = Repo::from_dir(&git.repo_path.join("worktree2"))?;
+        assert!(!repo.is_reference_checked_out_elsewhere(&master)?);
+        assert!(repo.is_reference_checked_out_elsewhere(&feature)?);
+        assert!(worktree1_repo.is_reference_checked_out_elsewhere(&master)?);
+        assert!(!worktree1_repo.is_reference_checked_out_elsewhere(&feature)?);
+        assert!(worktree2_repo.is_reference_checked_out_elsewhere(&master)?);
+        assert!(worktree2_repo.is_reference_checked_out_elsewhere(&feature)?);
+
+        // Check out the same branch in two worktrees.
+        git.run(&[
+            "-C",
+            "worktree2",
+            "checkout",
+            "--ignore-other-worktrees",
+            "feature",
+        ])?;
+        assert!(worktree1_repo.is_reference_checked_out_elsewhere(&feature)?);
+        assert!(worktree2_repo.is_reference_checked_out_elsewhere(&feature)?);
+        assert!(!worktree2_repo.is_reference_checked_out_elsewhere(&"refs/heads/other".into())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_worktrees() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["worktree", "add", "-b", "feature", "worktree1"])?;
+        git.run(&["worktree", "add", "--detach", "worktree2"])?;
+        git.run(&["worktree", "lock", "worktree2"])?;
+
+        let repo = git.get_repo()?;
+        let describe_worktrees = |repo: &Repo| -> eyre::Result<Vec<(String, PathBuf, bool)>> {
+            let mut result = Vec::new();
+            for worktree in repo.get_worktrees()? {
+                result.push((
+                    worktree.get_name().to_owned(),
+                    std::fs::canonicalize(worktree.get_path())?,
+                    worktree.is_locked()?,
+                ));
+            }
+            result.sort();
+            Ok(result)
+        };
+        let repo_path = std::fs::canonicalize(&git.repo_path)?;
+        let expected = vec![
+            ("worktree1".to_owned(), repo_path.join("worktree1"), false),
+            ("worktree2".to_owned(), repo_path.join("worktree2"), true),
+        ];
+        assert_eq!(describe_worktrees(&repo)?, expected);
+
+        let worktree1_repo = repo.open_worktree("worktree1")?.unwrap();
+        assert_eq!(describe_worktrees(&worktree1_repo)?, expected);
+        assert_eq!(
+            worktree1_repo
+                .get_working_copy_path()
+                .map(std::fs::canonicalize)
+                .transpose()?,
+            Some(repo_path.join("worktree1"))
+        );
+        assert_eq!(
+            worktree1_repo.get_head_info()?.reference_name,
+            Some("refs/heads/feature".into())
+        );
+        assert_eq!(
+            std::fs::canonicalize(worktree1_repo.get_path())?,
+            std::fs::canonicalize(repo.get_path())?
+                .join("worktrees")
+                .join("worktree1")
+        );
+        assert_eq!(
+            worktree1_repo.get_rebase_state_dir_path(),
+            worktree1_repo.get_path().join("rebase-merge")
+        );
+        assert_ne!(
+            worktree1_repo.get_rebase_state_dir_path(),
+            repo.get_rebase_state_dir_path()
+        );
+
+        assert!(repo.open_worktree("nonexistent")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_reference_chain() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let repo = git.get_repo()?;
+        let describe_head_chain = || -> eyre::Result<String> {
+            let chain = repo.get_reference_chain(&"HEAD".into())?;
+            Ok(chain.iter().map(|name| name.as_str()).join(" -> "))
+        };
+        insta::assert_snapshot!(describe_head_chain()?, @"HEAD -> refs/heads/master");
+
+        git.run(&["symbolic-ref", "refs/heads/alias", "refs/heads/master"])?;
+        git.run(&["symbolic-ref", "HEAD", "refs/heads/alias"])?;
+        insta::assert_snapshot!(describe_head_chain()?, @"HEAD -> refs/heads/alias -> refs/heads/master");
+
+        git.run(&["checkout", "--orphan", "unborn"])?;
+        insta::assert_snapshot!(describe_head_chain()?, @"HEAD -> refs/heads/unborn");
+
+        git.run(&["checkout", "--detach", "master"])?;
+        insta::assert_snapshot!(describe_head_chain()?, @"HEAD");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_head_info_bare() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.run(&["init", "--bare"])?;
+        git.run(&["symbolic-ref", "HEAD", "refs/heads/master"])?;
+        assert!(git.get_repo()?.is_bare());
+        git.assert_head(ExpectedHead::Unborn("master"))?;
+
+        let other_git = make_git()?;
+        other_git.init_repo()?;
+        let test1_oid = other_git.commit_file("test1", 1)?;
+        other_git.run(&[
+            "push",
+            git.repo_path.to_str().unwrap(),
+            "master:refs/heads/master",
+        ])?;
+        git.assert_head(ExpectedHead::Branch("master", test1_oid))?;
+
+        git.run(&["update-ref", "--no-deref", "HEAD", &test1_oid.to_string()])?;
+        git.assert_head(ExpectedHead::Detached(test1_oid))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_git_version_output() {
+        assert_eq!(
+            "git version 12.34.56".parse::<GitVersion>().unwrap(),
+            GitVersion(12, 34, 56)
+        );
+        assert_eq!(
+            "git version 12.34.56\n".parse::<GitVersion>().unwrap(),
+            GitVersion(12, 34, 56)
+        );
+        assert_eq!(
+            "git version 12.34.56.78.abcdef"
+                .parse::<GitVersion>()
+                .unwrap(),
+            GitVersion(12, 34, 56)
+        );
+
+        // See https://github.com/arxanas/git-branchless/issues/69
+        assert_eq!(
+            "git version 2.33.0-rc0".parse::<GitVersion>().unwrap(),
+            GitVersion(2, 33, 0)
+        );
+
+        // See https://github.com/arxanas/git-branchless/issues/85
+        assert_eq!(
+            "git version 2.33.GIT".parse::<GitVersion>().unwrap(),
+            GitVersion(2, 33, 0)
+        );
+    }
+
+    #[test]
+    fn test_get_stashes() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let repo = git.get_repo()?;
+        assert_eq!(repo.get_stashes()?, Vec::new());
+
+        git.write_file("initial", "stash 1 contents")?;
+        git.run(&["stash", "push", "-m", "first stash"])?;
+        git.write_file("initial", "stash 2 contents")?;
+        git.run(&["stash", "push", "-m", "second stash"])?;
+
+        let stashes = repo.get_stashes()?;
+        insta::assert_debug_snapshot!(stashes.iter().map(|stash| (stash.index, stash.message.as_str())).collect_vec(), @r###"
+        [
+            (
+                0,
+                "On master: second stash",
+            ),
+            (
+                1,
+                "On master: first stash",
+            ),
+        ]
+        "###);
+
+        let stash_oid = repo
+            .revparse_single_commit("stash@{1}")?
+            .map(|commit| commit.get_oid());
+        assert_eq!(Some(stashes[1].commit_oid), stash_oid);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_would_check_out_overwrite_changes() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        assert!(!repo.would_check_out_overwrite_changes(&test1_commit)?);
+
+        // Changes to files which are the same in both commits are carried
+        // over by the checkout.
+        git.write_file("test1", "updated contents")?;
+        assert!(!repo.would_check_out_overwrite_changes(&test1_commit)?);
+
+        git.write_file("test2", "updated contents")?;
+        assert!(repo.would_check_out_overwrite_changes(&test1_commit)?);
+        git.run(&["add", "test2.txt"])?;
+        assert!(repo.would_check_out_overwrite_changes(&test1_commit)?);
+
+        // The working copy is left alone.
+        {
+            let (stdout, _stderr) = git.run(&["status", "--short"])?;
+            insta::assert_snapshot!(stdout, @r###"
+             M test1.txt
+            M  test2.txt
+            "###);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_change_id() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let change_id = make_change_id(b"foo")?;
+        insta::assert_snapshot!(change_id, @"I19102815663d23f8b75a47e7a01965dcdc96468c");
+
+        git.write_file("test1", "contents")?;
+        git.run(&["add", "."])?;
+        git.run(&[
+            "commit",
+            "-m",
+            &format!("create test1.txt\n\nChange-Id: {}", change_id),
+        ])?;
+        git.write_file("test2", "contents")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "create test2.txt\n\nMy-Change-Id: foo"])?;
+
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        assert_eq!(head_commit.get_change_id()?, None);
+        assert_eq!(
+            head_commit.get_change_id_from_trailer("My-Change-Id")?,
+            Some("foo".to_string())
+        );
+        let parent_commit = head_commit.get_only_parent().unwrap();
+        assert_eq!(parent_commit.get_change_id()?, Some(change_id));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_local_branches_sorted_by_recency() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        git.run(&["branch", "foo"])?;
+        git.commit_file("test1", 1)?;
+        git.run(&["branch", "bar"])?;
+        git.run(&["branch", "baz"])?;
+        git.commit_file("test2", 2)?;
+        git.run(&["symbolic-ref", "refs/heads/symbolic", "refs/heads/foo"])?;
+
+        let repo = git.get_repo()?;
+        let branches = repo.get_local_branches_sorted_by_recency()?;
+        let branch_names: Vec<&str> = branches
+            .iter()
+            .map(|branch| branch.get_name())
+            .try_collect()?;
+        insta::assert_debug_snapshot!(branch_names, @r###"
+        [
+            "master",
+            "bar",
+            "baz",
+            "foo",
+        ]
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_head_info_states() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let repo = git.get_repo()?;
+        let head_info = repo.get_head_info()?;
+        assert!(!head_info.is_detached());
+        assert!(!head_info.is_unborn());
+
+        git.detach_head()?;
+        let head_info = repo.get_head_info()?;
+        assert!(head_info.is_detached());
+        assert!(!head_info.is_unborn());
+        let head_reference = repo.find_reference(&"HEAD".into())?.unwrap();
+        assert_eq!(head_info, repo.resolve_reference(&head_reference)?);
+
+        git.run(&["checkout", "--orphan", "new-branch"])?;
+        let head_info = repo.get_head_info()?;
+        assert!(!head_info.is_detached());
+        assert!(head_info.is_unborn());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_orig_head() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let repo = git.get_repo()?;
+        assert_eq!(repo.get_orig_head()?, None);
+
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&["reset", "--hard", "HEAD^"])?;
+        assert_eq!(repo.get_head_info()?.oid, Some(test1_oid));
+        assert_eq!(repo.get_orig_head()?, Some(test2_oid));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_signed_commit_unsigned() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let oid = repo.create_signed_commit(
+            get_commit_signing(&repo)?.as_ref(),
+            &test1_commit.get_author(),
+            &test1_commit.get_committer(),
+            "unsigned\n",
+            &test1_commit.get_tree()?,
+            vec![&test1_commit],
+        )?;
+        assert!(repo.inner.extract_signature(&oid.into(), None).is_err());
+
+        // Signing failures are reported rather than silently producing an
+        // unsigned commit.
+        git.run(&["config", "commit.gpgSign", "true"])?;
+        git.run(&["config", "gpg.program", "false"])?;
+        let result = repo.create_signed_commit(
+            get_commit_signing(&repo)?.as_ref(),
+            &test1_commit.get_author(),
+            &test1_commit.get_committer(),
+            "signed\n",
+            &test1_commit.get_tree()?,
+            vec![&test1_commit],
+        );
+        assert!(
+            matches!(result, Err(Error::SignCommit { ref program, .. }) if program == "false"),
+            "{result:?}"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_create_signed_commit_ssh() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let key_dir = tempfile::tempdir()?;
+        let key_path = key_dir.path().join("id_ed25519");
+        match std::process::Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+            .arg(&key_path)
+            .status()
+        {
+            Ok(status) => assert!(status.success()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("Skipping test because `ssh-keygen` is not installed");
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        }
+        git.run(&["config", "commit.gpgSign", "true"])?;
+        git.run(&["config", "gpg.format", "ssh"])?;
+        git.run(&["config", "user.signingKey", key_path.to_str().unwrap()])?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let oid = repo.create_signed_commit(
+            get_commit_signing(&repo)?.as_ref(),
+            &test1_commit.get_author(),
+            &test1_commit.get_committer(),
+            "signed\n",
+            &test1_commit.get_tree()?,
+            vec![&test1_commit],
+        )?;
+        let (signature, signed_data) = repo.inner.extract_signature(&oid.into(), None)?;
+        assert!(signature
+            .as_str()
+            .unwrap()
+            .starts_with("-----BEGIN SSH SIGNATURE-----"));
+
+        let commit = repo.find_commit_or_fail(oid)?;
+        assert_eq!(commit.get_message_raw()?, "signed\n");
+        assert_eq!(commit.get_parent_oids(), vec![test1_oid]);
+        assert!(signed_data.as_str().unwrap().ends_with("\nsigned\n"));
+
+        // Amended commits are signed as well.
+        let amended_oid = repo.amend_fast_commit(
+            &commit,
+            &AmendFastOptions::FromIndex { paths: vec![] },
+            Some("amended\n"),
+            None,
+            get_commit_signing(&repo)?.as_ref(),
+            true,
+            SystemTime::now(),
+        )?;
+        let (_signature, signed_data) = repo.inner.extract_signature(&amended_oid.into(), None)?;
+        assert!(signed_data.as_str().unwrap().ends_with("\namended\n"));
+        let amended_commit = repo.find_commit_or_fail(amended_oid)?;
+        assert_eq!(amended_commit.get_parent_oids(), vec![test1_oid]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_commit_with_headers() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let oid = repo.create_commit_with_headers(
+            &head_commit.get_author(),
+            &head_commit.get_committer(),
+            "new commit\n",
+            &head_commit.get_tree()?,
+            vec![&head_commit],
+            &[
+                ("change-id".to_string(), "abc123".to_string()),
+                ("x-multi".to_string(), "line 1\nline 2".to_string()),
+            ],
+        )?;
+        let (stdout, _stderr) = git.run(&["cat-file", "-p", &oid.to_string()])?;
+        insta::assert_snapshot!(stdout, @r###"
+        tree 8108c01b1930423879f106c1ebf725fcbfedccda
+        parent 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        author Testy McTestface <test@example.com> 1603978496 -0100
+        committer Testy McTestface <test@example.com> 1603978496 -0100
+        change-id abc123
+        x-multi line 1
+         line 2
+
+        new commit
+        "###);
+
+        let result = repo.create_commit_with_headers(
+            &head_commit.get_author(),
+            &head_commit.get_committer(),
+            "new commit\n",
+            &head_commit.get_tree()?,
+            vec![&head_commit],
+            &[("parent".to_string(), oid.to_string())],
+        );
+        assert!(matches!(result, Err(Error::InvalidCommitHeaderName(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_and_write_note() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        let notes_ref = "refs/notes/review";
+        assert_eq!(repo.read_note(notes_ref, test1_oid)?, None);
+
+        let signature = Signature::automated()?;
+        repo.write_note(
+            notes_ref,
+            test1_oid,
+            "Reviewed-by: foo\n",
+            &signature,
+            &signature,
+        )?;
+        assert_eq!(
+            repo.read_note(notes_ref, test1_oid)?,
+            Some(BString::from("Reviewed-by: foo\n"))
+        );
+        assert_eq!(repo.read_note(notes_ref, test2_oid)?, None);
+        assert_eq!(repo.read_note("refs/notes/commits", test1_oid)?, None);
+
+        // Existing notes are overwritten.
+        repo.write_note(
+            notes_ref,
+            test1_oid,
+            "Reviewed-by: bar\n",
+            &signature,
+            &signature,
+        )?;
+        assert_eq!(
+            repo.read_note(notes_ref, test1_oid)?,
+            Some(BString::from("Reviewed-by: bar\n"))
+        );
+
+        let (stdout, _stderr) =
+            git.run(&["notes", "--ref", notes_ref, "show", &test1_oid.to_string()])?;
+        insta::assert_snapshot!(stdout, @"Reviewed-by: bar");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shallow_and_partial_clones() -> eyre::Result<()> {
+        let GitWrapperWithRemoteRepo {
+            temp_dir: _guard,
+            original_repo,
+            cloned_repo,
+        } = make_git_with_remote_repo()?;
+        original_repo.init_repo()?;
+        original_repo.commit_file("test1", 1)?;
+        let test2_oid = original_repo.commit_file("test2", 2)?;
+
+        {
+            let repo = original_repo.get_repo()?;
+            assert!(!repo.is_shallow());
+            assert!(repo.get_shallow_boundary()?.is_empty());
+            assert!(!repo.is_partial()?);
+        }
+
+        original_repo.clone_repo_into(&cloned_repo, &["--depth", "1"])?;
+        {
+            let repo = cloned_repo.get_repo()?;
+            assert!(repo.is_shallow());
+            assert_eq!(repo.get_shallow_boundary()?, HashSet::from([test2_oid]));
+            assert!(!repo.is_partial()?);
+        }
+
+        cloned_repo.run(&["config", "remote.origin.promisor", "true"])?;
+        {
+            let repo = cloned_repo.get_repo()?;
+            assert!(repo.is_partial()?);
+        }
+
+        cloned_repo.run(&["config", "remote.origin.promisor", "false"])?;
+        cloned_repo.run(&["config", "remote.origin.partialclonefilter", "blob:none"])?;
+        {
+            let repo = cloned_repo.get_repo()?;
+            assert!(repo.is_partial()?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_upstream_branch_name() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.run(&["remote", "add", "origin", "file:///nonexistent"])?;
+        git.run(&["branch", "foo"])?;
+        git.run(&["branch", "bar"])?;
+        git.run(&["config", "branch.foo.remote", "origin"])?;
+        git.run(&["config", "branch.foo.merge", "refs/heads/master"])?;
+
+        let repo = git.get_repo()?;
+        let foo = repo.find_branch("foo", BranchType::Local)?.unwrap();
+        assert!(foo.get_upstream_branch()?.is_none());
+        assert_eq!(
+            foo.get_upstream_branch_name()?,
+            Some(ReferenceName::from("refs/remotes/origin/master"))
+        );
+
+        let bar = repo.find_branch("bar", BranchType::Local)?.unwrap();
+        assert_eq!(bar.get_upstream_branch_name()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_blob_from_stream() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let repo = git.get_repo()?;
+
+        let contents = b"hello, world\n".repeat(10_000);
+        let oid = repo.create_blob_from_stream(contents.as_slice())?;
+        assert_eq!(oid, repo.create_blob_from_contents(&contents)?);
+        let blob = repo.find_blob_or_fail(oid)?;
+        assert_eq!(blob.size(), contents.len());
+        assert!(!blob.is_binary());
+        assert_eq!(blob.get_content(), contents.as_slice());
+
+        let oid = repo.create_blob_from_stream(&b"foo\0bar"[..])?;
+        let blob = repo.find_blob_or_fail(oid)?;
+        assert_eq!(blob.size(), 7);
+        assert!(blob.is_binary());
+
+        let oid = repo.create_blob_from_stream(std::io::empty())?;
+        let blob = repo.find_blob_or_fail(oid)?;
+        assert_eq!(blob.size(), 0);
+        assert!(!blob.is_binary());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_entries_and_read_file_at_commit() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.write_file("foo/bar/baz", "nested contents\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "add nested file"])?;
+        git.run(&[
+            "update-index",
+            "--add",
+            "--cacheinfo",
+            "160000,1111111111111111111111111111111111111111,submodule",
+        ])?;
+        git.run(&["commit", "-m", "add submodule entry"])?;
+
+        let repo = git.get_repo()?;
+        let commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let tree = commit.get_tree()?;
+        let entries = tree
+            .entries()
+            .map(|(name, _oid, file_mode)| (name, file_mode))
+            .collect_vec();
+        assert_eq!(
+            entries,
+            vec![
+                (PathBuf::from("foo"), FileMode::Tree),
+                (PathBuf::from("initial.txt"), FileMode::Blob),
+                (PathBuf::from("submodule"), FileMode::Commit),
+            ]
+        );
+
+        let (_, foo_oid, _) = tree.entries().next().unwrap();
+        let foo_tree = repo.find_tree_or_fail(foo_oid)?;
+        let foo_entries = foo_tree
+            .entries()
+            .map(|(name, _oid, file_mode)| (name, file_mode))
+            .collect_vec();
+        assert_eq!(foo_entries, vec![(PathBuf::from("bar"), FileMode::Tree)]);
+
+        assert_eq!(
+            repo.read_file_at_commit(&commit, Path::new("foo/bar/baz.txt"))?,
+            Some(b"nested contents\n".to_vec())
+        );
+        assert_eq!(
+            repo.read_file_at_commit(&commit, Path::new("initial.txt"))?,
+            Some(b"initial contents\n".to_vec())
+        );
+        assert_eq!(
+            repo.read_file_at_commit(&commit, Path::new("foo/bar"))?,
+            None
+        );
+        assert_eq!(
+            repo.read_file_at_commit(&commit, Path::new("submodule"))?,
+            None
+        );
+        assert_eq!(
+            repo.read_file_at_commit(&commit, Path::new("foo/nonexistent.txt"))?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_commit_by_prefix() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let repo = git.get_repo()?;
+        let test1_hex = test1_oid.to_string();
+        for len in [4, 7, 8, test1_hex.len()] {
+            let commit = repo.find_commit_by_prefix(&test1_hex[..len])?;
+            assert_eq!(commit.map(|commit| commit.get_oid()), Some(test1_oid));
+        }
+        let commit = repo.find_commit_by_prefix(&test1_hex[..7].to_uppercase())?;
+        assert_eq!(commit.map(|commit| commit.get_oid()), Some(test1_oid));
+
+        // Too short, not hex, or not present.
+        assert!(repo.find_commit_by_prefix(&test1_hex[..3])?.is_none());
+        assert!(repo.find_commit_by_prefix("master")?.is_none());
+        assert!(repo.find_commit_by_prefix("HEAD")?.is_none());
+        let missing_prefix = if test1_hex.starts_with("0000") {
+            "ffff"
+        } else {
+            "0000"
+        };
+        assert!(repo.find_commit_by_prefix(missing_prefix)?.is_none());
+
+        // Matches a tree rather than a commit.
+        let tree_hex = repo
+            .find_commit_or_fail(test1_oid)?
+            .get_tree()?
+            .get_oid()
+            .to_string();
+        assert!(repo.find_commit_by_prefix(&tree_hex[..8])?.is_none());
+
+        // Create enough blobs that some pair of them shares a four-character
+        // prefix.
+        let mut blob_oids: HashMap<String, NonZeroOid> = HashMap::new();
+        let (oid1, oid2) = (0..)
+            .find_map(|i| {
+                let oid = repo
+                    .create_blob_from_contents(format!("blob {i}\n").as_bytes())
+                    .unwrap();
+                let key = oid.to_string()[..4].to_owned();
+                blob_oids.insert(key, oid).map(|other_oid| (other_oid, oid))
+            })
+            .unwrap();
+        let prefix = &oid1.to_string()[..4];
+        match repo.find_commit_by_prefix(prefix) {
+            Err(Error::AmbiguousPrefix {
+                prefix: actual_prefix,
+                candidates,
+            }) => {
+                assert_eq!(actual_prefix, prefix);
+                assert!(candidates.contains(&oid1));
+                assert!(candidates.contains(&oid2));
+            }
+            result => panic!("expected ambiguous prefix error, got: {result:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_commit_bytes() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        assert_eq!(head_commit.get_message_encoding(), None);
+
+        // "café" in Latin-1.
+        let message = b"caf\xe9\n";
+        let oid = repo.create_commit_bytes(
+            &head_commit.get_author(),
+            &head_commit.get_committer(),
+            message,
+            Some("ISO-8859-1"),
+            &head_commit.get_tree()?,
+            vec![&head_commit],
+        )?;
+        let commit = repo.find_commit_or_fail(oid)?;
+        assert_eq!(commit.get_message_raw()?, &message[..]);
+        assert_eq!(
+            commit.get_message_encoding(),
+            Some("ISO-8859-1".to_string())
+        );
+        assert_eq!(commit.get_parent_oids(), vec![head_commit.get_oid()]);
+        assert_eq!(
+            commit.get_tree()?.get_oid(),
+            head_commit.get_tree()?.get_oid()
+        );
+
+        // Git re-encodes the message for display.
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%s", &oid.to_string()])?;
+        assert_eq!(stdout, "café\n");
+
+        let oid = repo.create_commit_bytes(
+            &head_commit.get_author(),
+            &head_commit.get_committer(),
+            b"plain message\n",
+            None,
+            &head_commit.get_tree()?,
+            vec![&head_commit],
+        )?;
+        let commit = repo.find_commit_or_fail(oid)?;
+        assert_eq!(commit.get_message_raw()?, "plain message\n");
+        assert_eq!(commit.get_message_encoding(), None);
+
+        let result = repo.create_commit_bytes(
+            &head_commit.get_author(),
+            &head_commit.get_committer(),
+            b"message\n",
+            Some("bad\nencoding"),
+            &head_commit.get_tree()?,
+            vec![&head_commit],
+        );
+        assert!(matches!(result, Err(Error::InvalidCommitEncoding(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_to_unified_bytes() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.write_file("initial", "updated contents\n")?;
+        git.run(&["commit", "-a", "-m", "update initial"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let diff = repo
+            .get_patch_for_commit(&effects, &head_commit, &DiffOptions::default())?
+            .unwrap();
+        let patch = String::from_utf8(diff.to_unified_bytes()?)?;
+        insta::assert_snapshot!(patch, @r###"
+        diff --git a/initial.txt b/initial.txt
+        index 63af228..27e2fc9 100644
+        --- a/initial.txt
+        +++ b/initial.txt
+        @@ -1 +1 @@
+        -initial contents
+        +updated contents
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_diff_stats_for_commit() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.write_file("initial", "line 1\nline 2\nline 3\n")?;
+        git.write_file("test1", "")?;
+        git.write_file("test2", "line 1\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "update files"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        assert_eq!(
+            repo.get_diff_stats_for_commit(&effects, &commit, &DiffOptions::default())?,
+            Some(DiffStats {
+                files_changed: 3,
+                insertions: 4,
+                deletions: 2,
+            })
+        );
+
+        git.run(&["commit", "--allow-empty", "-m", "empty"])?;
+        let commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        assert_eq!(
+            repo.get_diff_stats_for_commit(&effects, &commit, &DiffOptions::default())?,
+            Some(DiffStats::default())
+        );
+
+        git.run(&["checkout", "-b", "foo", "HEAD^"])?;
+        git.commit_file("test3", 3)?;
+        git.run(&["checkout", "master"])?;
+        git.run(&["merge", "--no-ff", "-m", "merge foo", "foo"])?;
+        let merge_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        assert_eq!(
+            repo.get_diff_stats_for_commit(&effects, &merge_commit, &DiffOptions::default())?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_patch_for_merge_commit() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["checkout", "-b", "foo"])?;
+        git.commit_file("test2", 2)?;
+        git.run(&["checkout", "master"])?;
+        git.commit_file("test3", 3)?;
+        git.run(&["merge", "--no-ff", "-m", "merge foo", "foo"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let merge_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        assert!(repo
+            .get_patch_for_commit(&effects, &merge_commit, &DiffOptions::default())?
+            .is_none());
+
+        let get_changed_paths = |mainline: u32| -> eyre::Result<Option<Vec<PathBuf>>> {
+            let diff = match repo.get_patch_for_merge_commit(
+                &effects,
+                &merge_commit,
+                mainline,
+                &DiffOptions::default(),
+            )? {
+                Some(diff) => diff,
+                None => return Ok(None),
+            };
+            let paths = diff
+                .inner
+                .deltas()
+                .filter_map(|delta| delta.new_file().path().map(|path| path.to_owned()))
+                .collect();
+            Ok(Some(paths))
+        };
+        assert_eq!(
+            get_changed_paths(1)?,
+            Some(vec![PathBuf::from("test2.txt")])
+        );
+        assert_eq!(
+            get_changed_paths(2)?,
+            Some(vec![PathBuf::from("test3.txt")])
+        );
+        assert_eq!(get_changed_paths(0)?, None);
+        assert_eq!(get_changed_paths(3)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_submodule_pointer_change() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&[
+            "update-index",
+            "--add",
+            "--cacheinfo",
+            &format!("160000,{test1_oid},submodule"),
+        ])?;
+        git.run(&["commit", "-m", "add submodule"])?;
+        git.run(&[
+            "update-index",
+            "--cacheinfo",
+            &format!("160000,{test2_oid},submodule"),
+        ])?;
+        git.run(&["commit", "-m", "bump submodule"])?;
+        git.run(&["config", "diff.ignoreSubmodules", "all"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let diff = repo
+            .get_patch_for_commit(&effects, &head_commit, &DiffOptions::default())?
+            .unwrap();
+        let patch = String::from_utf8(diff.to_unified_bytes()?)?;
+        insta::assert_snapshot!(patch, @r###"
+        diff --git a/submodule b/submodule
+        index 62fc20d..96d1c37 160000
+        --- a/submodule
+        +++ b/submodule
+        @@ -1 +1 @@
+        -Subproject commit 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        +Subproject commit 96d1c37a3d4363611c49f7e52186e189a04c531f
+        "###);
+        assert_eq!(
+            diff.get_submodule_changes(),
+            vec![SubmoduleChange {
+                path: PathBuf::from("submodule"),
+                old_oid: MaybeZeroOid::NonZero(test1_oid),
+                new_oid: MaybeZeroOid::NonZero(test2_oid),
+            }]
+        );
+
+        let parent_commit = head_commit.get_only_parent().unwrap();
+        let diff = repo
+            .get_patch_for_commit(&effects, &parent_commit, &DiffOptions::default())?
+            .unwrap();
+        assert_eq!(
+            diff.get_submodule_changes(),
+            vec![SubmoduleChange {
+                path: PathBuf::from("submodule"),
+                old_oid: MaybeZeroOid::Zero,
+                new_oid: MaybeZeroOid::NonZero(test1_oid),
+            }]
+        );
+
+        let head_patch_id = repo.get_patch_id(&effects, &head_commit, &DiffOptions::default())?;
+        let parent_patch_id =
+            repo.get_patch_id(&effects, &parent_commit, &DiffOptions::default())?;
+        assert!(head_patch_id.is_some());
+        assert_ne!(head_patch_id, parent_patch_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_to_word_diff_bytes() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.write_file(
+            "test1",
+            "line one\nfoo bar qux\nline three\nremove me\nline five\n",
+        )?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "create test1.txt"])?;
+        git.write_file(
+            "test1",
+            "line one\nfoo baz qux\nline three\nadded line\nline five\nnew line\n",
+        )?;
+        git.run(&["commit", "-a", "-m", "update test1.txt"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let diff = repo
+            .get_patch_for_commit(&effects, &head_commit, &DiffOptions::default())?
+            .unwrap();
+        let patch = String::from_utf8(diff.to_word_diff_bytes()?)?;
+        insta::assert_snapshot!(patch, @r###"
+        diff --git a/test1.txt b/test1.txt
+        index 45929a6..505d2bb 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1,5 +1,6 @@
+        line one
+        foo [-bar-]{+baz+} qux
+        line three
+        [-remove me-]{+added line+}
+        line five
+        {+new line+}
+        "###);
+
+        let (stdout, _stderr) = git.run(&["show", "--word-diff", "--format=", "HEAD"])?;
+        assert_eq!(patch, stdout);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_diff() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.write_file("initial", "updated contents\n")?;
+        git.run(&["commit", "-a", "-m", "update initial"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let diff = repo
+            .get_patch_for_commit(&effects, &head_commit, &DiffOptions::default())?
+            .unwrap();
+
+        git.run(&["reset", "--hard", "HEAD^"])?;
+        repo.apply_diff(&diff, ApplyLocation::Both)?;
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"M  initial.txt
+");
+
+        git.run(&["reset", "--hard", "HEAD"])?;
+        git.write_file("initial", "conflicting contents\n")?;
+        let result = repo.apply_diff(&diff, ApplyLocation::WorkDir);
+        insta::assert_snapshot!(result.unwrap_err(), @"could not apply hunk at initial.txt:1 to WorkDir: hunk at line 1 did not apply; class=Patch (31); code=ApplyFail (-35)");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_reference_targets() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.run(&["branch", "foo", "HEAD^"])?;
+        git.run(&["tag", "-a", "-m", "annotated tag", "bar"])?;
+        git.run(&["tag", "tree-tag", "HEAD^{tree}"])?;
+        git.run(&[
+            "symbolic-ref",
+            "refs/heads/dangling",
+            "refs/heads/nonexistent",
+        ])?;
+
+        let repo = git.get_repo()?;
+        let initial_oid = repo.revparse_single_commit("HEAD^")?.unwrap().get_oid();
+        let reference_targets: BTreeMap<String, NonZeroOid> = repo
+            .get_reference_targets()?
+            .into_iter()
+            .map(|(name, oid)| (name.as_str().to_owned(), oid))
+            .filter(|(name, _oid)| !name.starts_with("refs/branchless/"))
+            .collect();
+        assert_eq!(
+            reference_targets,
+            BTreeMap::from([
+                ("refs/heads/foo".to_owned(), initial_oid),
+                ("refs/heads/master".to_owned(), test1_oid),
+                ("refs/tags/bar".to_owned(), test1_oid),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_populate_refs() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.populate_refs(100)?;
+
+        let repo = git.get_repo()?;
+        let head_oid = repo.get_head_info()?.oid.unwrap();
+        let reference_targets = repo.get_reference_targets()?;
+        let populated_oids = (0..100)
+            .map(|i| reference_targets.get(&ReferenceName::from(format!("refs/heads/ref-{i}"))))
+            .collect_vec();
+        assert!(populated_oids.iter().all(|oid| oid == &Some(&head_oid)));
+        assert_eq!(repo.get_all_references()?.len(), reference_targets.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_each_reference() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.run(&["branch", "foo"])?;
+        git.run(&["tag", "bar"])?;
+
+        let repo = git.get_repo()?;
+        let mut reference_names = Vec::new();
+        repo.for_each_reference(|reference| {
+            let reference_name = reference.get_name()?;
+            if !reference_name.as_str().starts_with("refs/branchless/") {
+                reference_names.push(reference_name);
+            }
+            Ok(())
+        })?;
+        reference_names.sort();
+        insta::assert_debug_snapshot!(reference_names, @r###"
+        [
+            ReferenceName(
+                "refs/heads/foo",
+            ),
+            ReferenceName(
+                "refs/heads/master",
+            ),
+            ReferenceName(
+                "refs/tags/bar",
+            ),
+        ]
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_contains_touched_path_matching() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.write_file("src/foo", "foo\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "add src/foo"])?;
+
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let matches = |pattern: &str| -> eyre::Result<Option<bool>> {
+            let pattern = glob::Pattern::new(pattern)?;
+            Ok(repo.contains_touched_path_matching(&head_commit, &pattern)?)
+        };
+        assert_eq!(matches("src/**")?, Some(true));
+        assert_eq!(matches("*.txt")?, Some(true));
+        assert_eq!(matches("initial.txt")?, Some(false));
+        assert_eq!(matches("test/**")?, Some(false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_prompt_info() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.run(&["branch", "upstream"])?;
+        git.run(&["branch", "--set-upstream-to", "upstream"])?;
+        git.commit_file("test1", 1)?;
+        git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        insta::assert_snapshot!(repo.render_prompt_info()?, @"master +2");
+
+        git.run(&["checkout", "upstream"])?;
+        git.commit_file("test3", 3)?;
+        git.run(&["checkout", "master"])?;
+        git.write_file("test1", "modified contents\n")?;
+        insta::assert_snapshot!(repo.render_prompt_info()?, @"master +2 -1 *");
+
+        git.run(&["checkout", "--detach", "--force"])?;
+        insta::assert_debug_snapshot!(repo.render_prompt_info()?, @r###"
+        PromptInfo {
+            branch_name: None,
+            short_oid: Some(
+                "96d1c37",
+            ),
+            ahead_behind: None,
+            is_dirty: false,
+            operation_type: None,
+        }
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_diff_between_trees_max_file_size() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.write_file("test1", "updated test1 contents\n")?;
+        git.write_file("test2", "small\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "update files"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let parent_commit = head_commit.get_only_parent().unwrap();
+        let old_tree = parent_commit.get_tree()?;
+        let new_tree = head_commit.get_tree()?;
+        let diff = repo.get_diff_between_trees(
+            &effects,
+            Some(&old_tree),
+            &new_tree,
+            &DiffOptions {
+                num_context_lines: 0,
+                max_file_size: Some(10),
+                ..Default::default()
+            },
+        )?;
+        let patch = String::from_utf8(diff.to_unified_bytes()?)?;
+        insta::assert_snapshot!(patch, @r###"
+        diff --git a/test1.txt b/test1.txt
+        index 7432a8f..f5ca166 100644
+        Binary files a/test1.txt and b/test1.txt differ
+        diff --git a/test2.txt b/test2.txt
+        new file mode 100644
+        index 0000000..ac79041
+        --- /dev/null
+        +++ b/test2.txt
+        @@ -0,0 +1 @@
+        +small
+        "###);
+
+        // The limit also applies when computing patches for commits.
+        git.run(&["config", "branchless.diff.maxFileSize", "10"])?;
+        let diff_options = DiffOptions {
+            num_context_lines: 0,
+            ..DiffOptions::load(&repo)?
+        };
+        let diff = repo
+            .get_patch_for_commit(&effects, &head_commit, &diff_options)?
+            .unwrap();
+        let commit_patch = String::from_utf8(diff.to_unified_bytes()?)?;
+        assert_eq!(commit_patch, patch);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_diff_between_trees_pathspec() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let old_oid = git.commit_file("test1", 1)?;
+        git.write_file("test1", "updated test1\n")?;
+        git.write_file("dir/test2", "test2\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "update files"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let old_commit = repo.find_commit_or_fail(old_oid)?;
+        let new_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        let old_tree = old_commit.get_tree()?;
+        let new_tree = new_commit.get_tree()?;
+        let diff = repo.get_diff_between_trees(
+            &effects,
+            Some(&old_tree),
+            &new_tree,
+            &DiffOptions {
+                num_context_lines: 0,
+                pathspec: vec![PathBuf::from("dir")],
+                ..Default::default()
+            },
+        )?;
+        let patch = String::from_utf8(diff.to_unified_bytes()?)?;
+        insta::assert_snapshot!(patch, @r###"
+        diff --git a/dir/test2.txt b/dir/test2.txt
+        new file mode 100644
+        index 0000000..180cf83
+        --- /dev/null
+        +++ b/dir/test2.txt
+        @@ -0,0 +1 @@
+        +test2
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_bare() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let repo = git.get_repo()?;
+        assert!(!repo.is_bare());
+
+        git.run(&["config", "core.bare", "true"])?;
+        let repo = git.get_repo()?;
+        assert!(repo.is_bare());
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let git_run_info = git.get_git_run_info();
+        let index = repo.get_index()?;
+        let head_info = repo.get_head_info()?;
+        let result = repo.get_status(&effects, &git_run_info, &index, &head_info, None);
+        assert!(matches!(result, Err(Error::NoWorkingCopyPath)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_merge_base_cached() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.run(&["checkout", "-b", "foo"])?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&["checkout", "master"])?;
+        let test3_oid = git.commit_file("test3", 3)?;
+
+        let repo = git.get_repo()?;
+        assert_eq!(repo.find_merge_base(test2_oid, test3_oid)?, Some(test1_oid));
+        assert_eq!(repo.merge_base_cache.borrow().len(), 1);
+        assert_eq!(repo.find_merge_base(test3_oid, test2_oid)?, Some(test1_oid));
+        assert_eq!(repo.merge_base_cache.borrow().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_and_find_tag() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let tagger = test1_commit.get_committer();
+        let tag_oid = repo.create_tag("v1.0", test1_oid, &tagger, "release 1.0\n", false)?;
+
+        let tag = repo.find_tag(tag_oid)?.unwrap();
+        assert_eq!(tag.get_oid(), tag_oid);
+        assert_eq!(tag.get_name(), Some("v1.0"));
+        assert_eq!(tag.get_target_oid(), test1_oid);
+        assert_eq!(
+            tag.get_tagger()
+                .and_then(|tagger| tagger.friendly_describe()),
+            Some("Testy McTestface <test@example.com>".to_string())
+        );
+        assert_eq!(tag.get_message(), "release 1.0\n");
+
+        let reference = repo
+            .find_reference(&ReferenceName::from("refs/tags/v1.0"))?
+            .unwrap();
+        assert_eq!(
+            reference.peel_to_commit()?.map(|commit| commit.get_oid()),
+            Some(test1_oid)
+        );
+
+        assert!(repo
+            .create_tag("v1.0", test2_oid, &tagger, "release 1.0\n", false)
+            .is_err());
+        let tag_oid = repo.create_tag("v1.0", test2_oid, &tagger, "release 1.0\n", true)?;
+        assert_eq!(repo.find_tag(tag_oid)?.unwrap().get_target_oid(), test2_oid);
+
+        assert!(repo
+            .find_tag(NonZeroOid::from_str(
+                "1111111111111111111111111111111111111111"
+            )?)?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reference_get_reflog() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        let foo: ReferenceName = "refs/heads/foo".into();
+        repo.create_reference(&foo, test1_oid, false, "create foo")?;
+        let reference = repo.create_reference(&foo, test2_oid, true, "move foo")?;
+        let reflog = reference.get_reflog()?;
+        assert_eq!(
+            reflog
+                .iter()
+                .map(|entry| (entry.old_oid, entry.new_oid, entry.message.as_deref()))
+                .collect_vec(),
+            vec![
+                (test1_oid.into(), test2_oid.into(), Some("move foo")),
+                (MaybeZeroOid::Zero, test1_oid.into(), Some("create foo")),
+            ]
+        );
+        assert_eq!(
+            reflog[0].committer.friendly_describe(),
+            Some("Testy McTestface <test@example.com>".to_string())
+        );
+
+        // References outside of `refs/heads` don't have reflogs by default.
+        let bar: ReferenceName = "refs/custom/bar".into();
+        let reference = repo.create_reference(&bar, test1_oid, false, "create bar")?;
+        assert!(!repo.get_path().join("logs/refs/custom/bar").exists());
+        assert!(reference.get_reflog()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ahead_behind_first_parent() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let initial_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+        git.commit_file("test1", 1)?;
+        git.run(&["checkout", "-b", "feature", "HEAD^"])?;
+        git.commit_file("test2", 2)?;
+        git.commit_file("test3", 3)?;
+        git.run(&["checkout", "master"])?;
+        git.run(&["merge", "--no-ff", "-m", "merge feature", "feature"])?;
+
+        let repo = git.get_repo()?;
+        let merge_oid = repo.get_head_info()?.oid.unwrap();
+        assert_eq!(repo.ahead_behind(merge_oid, initial_oid, false)?, (4, 0));
+        assert_eq!(repo.ahead_behind(merge_oid, initial_oid, true)?, (2, 0));
+        assert_eq!(repo.ahead_behind(initial_oid, merge_oid, true)?, (0, 2));
+
+        let (stdout, _stderr) = git.run(&[
+            "rev-list",
+            "--first-parent",
+            "--count",
+            &format!("{}..{}", initial_oid, merge_oid),
+        ])?;
+        assert_eq!(stdout.trim(), "2");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_reference_with_signature() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        let name = ReferenceName::from("refs/heads/foo");
+        let signature = Signature::automated()?;
+        repo.create_reference_with_signature(
+            &name,
+            test1_oid,
+            false,
+            "branchless: create foo",
+            &signature,
+        )?;
+        assert!(repo
+            .create_reference_with_signature(
+                &name,
+                test2_oid,
+                false,
+                "branchless: move foo",
+                &signature,
+            )
+            .is_err());
+        repo.create_reference_with_signature(
+            &name,
+            test2_oid,
+            true,
+            "branchless: move foo",
+            &signature,
+        )?;
+        assert_eq!(
+            repo.find_reference(&name)?
+                .unwrap()
+                .peel_to_commit()?
+                .unwrap()
+                .get_oid(),
+            test2_oid
+        );
+
+        let reflog = std::fs::read_to_string(repo.get_path().join("logs/refs/heads/foo"))?;
+        insta::assert_snapshot!(reflog, @r###"
+        0000000000000000000000000000000000000000 62fc20d2a290daea0d52bdc2ed2ad4be6491010e git-branchless <git-branchless@example.com> 0 +0000	branchless: create foo
+        62fc20d2a290daea0d52bdc2ed2ad4be6491010e 96d1c37a3d4363611c49f7e52186e189a04c531f git-branchless <git-branchless@example.com> 0 +0000	branchless: move foo
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reference_transaction() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&["branch", "foo", &test1_oid.to_string()])?;
+
+        let repo = git.get_repo()?;
+        let foo = ReferenceName::from("refs/heads/foo");
+        let bar = ReferenceName::from("refs/heads/bar");
+        let get_oid = |name: &ReferenceName| -> eyre::Result<Option<NonZeroOid>> {
+            Ok(match repo.find_reference(name)? {
+                Some(reference) => reference.peel_to_commit()?.map(|commit| commit.get_oid()),
+                None => None,
+            })
+        };
+
+        {
+            let mut transaction = repo.reference_transaction()?;
+            transaction.lock_ref(&foo)?;
+            transaction.set_target(&foo, test2_oid, "move foo")?;
+            transaction.lock_ref(&bar)?;
+            transaction.set_target(&bar, test1_oid, "create bar")?;
+            // Dropping the transaction without committing discards it.
+        }
+        assert_eq!(get_oid(&foo)?, Some(test1_oid));
+        assert_eq!(get_oid(&bar)?, None);
+
+        let mut transaction = repo.reference_transaction()?;
+        transaction.lock_ref(&foo)?;
+        transaction.set_target(&foo, test2_oid, "move foo")?;
+        transaction.lock_ref(&bar)?;
+        transaction.set_target(&bar, test1_oid, "create bar")?;
+        transaction.commit()?;
+        assert_eq!(get_oid(&foo)?, Some(test2_oid));
+        assert_eq!(get_oid(&bar)?, Some(test1_oid));
+
+        let mut transaction = repo.reference_transaction()?;
+        transaction.lock_ref(&foo)?;
+        transaction.remove(&foo)?;
+        transaction.commit()?;
+        assert_eq!(get_oid(&foo)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_readonly_config_includes() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let included_path = git.repo_path.join("included.gitconfig");
+        std::fs::write(
+            &included_path,
+            "[branch \"master\"]\n\tpushRemote = included-remote\n",
+        )?;
+        git.run(&["config", "include.path", included_path.to_str().unwrap()])?;
+
+        let conditional_path = git.repo_path.join("conditional.gitconfig");
+        std::fs::write(
+            &conditional_path,
+            "[branchless \"core\"]\n\tmainBranch = conditional-main\n",
+        )?;
+        git.run(&[
+            "config",
+            &format!("includeIf.gitdir:{}/.path", git.repo_path.display()),
+            conditional_path.to_str().unwrap(),
+        ])?;
+
+        let repo = git.get_repo()?;
+        let branch = repo.find_branch("master", BranchType::Local)?.unwrap();
+        assert_eq!(
+            branch.get_push_remote_name()?,
+            Some("included-remote".to_string())
+        );
+        assert_eq!(
+            crate::core::config::get_main_branch_name(&repo)?,
+            "conditional-main"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_mailmap() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let author = test1_commit.get_author();
+        assert_eq!(
+            repo.resolve_mailmap(&author)?.friendly_describe(),
+            Some("Testy McTestface <test@example.com>".to_string())
+        );
+
+        std::fs::write(
+            git.repo_path.join(".mailmap"),
+            "Canonical Name <canonical@example.com> Testy McTestface <test@example.com>\n",
+        )?;
+        assert_eq!(
+            repo.resolve_mailmap(&author)?.friendly_describe(),
+            Some("Canonical Name <canonical@example.com>".to_string())
+        );
+
+        let mailmap = repo.get_mailmap()?;
+        let committer = test1_commit.get_committer();
+        assert_eq!(
+            mailmap.resolve_signature(&committer)?.friendly_describe(),
+            Some("Canonical Name <canonical@example.com>".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_signature_update_timestamp_offset() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let original_offset = test1_commit.get_author().get_time().get_offset_minutes();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+        let signature = test1_commit.get_author().update_timestamp(now)?;
+        assert_eq!(signature.get_time().to_system_time()?, now);
+        assert_eq!(signature.get_time().get_offset_minutes(), original_offset);
+
+        let signature = test1_commit
+            .get_author()
+            .update_timestamp_with_offset(now, 120)?;
+        assert_eq!(signature.get_time().to_system_time()?, now);
+        assert_eq!(signature.get_time().get_offset_minutes(), 120);
+        assert_eq!(
+            signature.friendly_describe(),
+            Some("Testy McTestface <test@example.com>".to_string())
+        );
+
+        let signature = signature.with_offset(-300)?;
+        assert_eq!(signature.get_time().to_system_time()?, now);
+        assert_eq!(signature.get_time().get_offset_minutes(), -300);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_paths_touched_by_commit_cached() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let expected = Some(HashSet::from([PathBuf::from("test1.txt")]));
+        assert_eq!(
+            repo.get_paths_touched_by_commit_cached(&test1_commit)?,
+            expected
+        );
+        assert_eq!(repo.touched_paths_cache.borrow().len(), 1);
+        assert_eq!(
+            repo.get_paths_touched_by_commit_cached(&test1_commit)?,
+            expected
+        );
+        assert_eq!(repo.touched_paths_cache.borrow().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_commit_deltas() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.write_file(
+            "to_rename",
+            "contents which are long enough to be detected as a rename\n",
+        )?;
+        git.commit_file("test1", 1)?;
+        git.commit_file("test2", 2)?;
+
+        git.write_file("new_file", "new contents\n")?;
+        git.write_file("test1", "updated contents\n")?;
+        git.delete_file("test2")?;
+        git.run(&["mv", "to_rename.txt", "renamed.txt"])?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "update files"])?;
+
+        let repo = git.get_repo()?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        assert_eq!(
+            repo.get_commit_deltas(&head_commit)?,
+            Some(vec![
+                FileDelta {
+                    status: FileStatus::Added,
+                    old_path: None,
+                    new_path: Some("new_file.txt".into()),
+                    old_mode: None,
+                    new_mode: Some(FileMode::Blob),
+                },
+                FileDelta {
+                    status: FileStatus::Renamed,
+                    old_path: Some("to_rename.txt".into()),
+                    new_path: Some("renamed.txt".into()),
+                    old_mode: Some(FileMode::Blob),
+                    new_mode: Some(FileMode::Blob),
+                },
+                FileDelta {
+                    status: FileStatus::Modified,
+                    old_path: Some("test1.txt".into()),
+                    new_path: Some("test1.txt".into()),
+                    old_mode: Some(FileMode::Blob),
+                    new_mode: Some(FileMode::Blob),
+                },
+                FileDelta {
+                    status: FileStatus::Deleted,
+                    old_path: Some("test2.txt".into()),
+                    new_path: None,
+                    old_mode: Some(FileMode::Blob),
+                    new_mode: None,
+                },
+            ])
+        );
+
+        git.run(&["commit", "--allow-empty", "-m", "empty"])?;
+        let empty_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        assert_eq!(repo.get_commit_deltas(&empty_commit)?, Some(vec![]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cherry_pick_fast() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        git.run(&["checkout", "-b", "foo"])?;
+        let test1_oid = git.commit_file_with_contents("test1", 1, "test1 contents")?;
+        git.run(&["checkout", "master"])?;
+        let initial2_oid =
+            git.commit_file_with_contents("initial", 2, "updated initial contents")?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let initial2_commit = repo.find_commit_or_fail(initial2_oid)?;
+        let CherryPickFastResult { tree, became_empty } = repo.cherry_pick_fast(
+            &test1_commit,
+            &initial2_commit,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: false,
+                mainline: None,
+                max_dehydrated_paths: None,
+            },
+        )?;
+        assert!(!became_empty);
+
+        insta::assert_debug_snapshot!(tree, @r###"
+        Tree {
+            inner: Tree {
+                id: 367f91ddd5df2d1c18742ce3f09b4944944cac3a,
+            },
+        }
+        "###);
+
+        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| entry.name().unwrap().to_string()).collect_vec(), @r###"
+        [
+            "initial.txt",
+            "test1.txt",
+        ]
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cherry_pick_fast_became_empty() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        git.run(&["checkout", "-b", "foo"])?;
         let test1_oid = git.commit_file_with_contents("test1", 1, "test1 contents")?;
         git.run(&["checkout", "master"])?;
-        let initial2_oid =
-            git.commit_file_with_contents("initial", 2, "updated initial contents")?;
+        git.commit_file_with_contents("initial", 2, "updated initial contents")?;
+        git.run(&["cherry-pick", "foo"])?;
+        git.run(&["commit", "--allow-empty", "-m", "empty commit"])?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let target_commit = repo.revparse_single_commit("HEAD^")?.unwrap();
+        let CherryPickFastResult { tree, became_empty } = repo.cherry_pick_fast(
+            &test1_commit,
+            &target_commit,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: false,
+                mainline: None,
+                max_dehydrated_paths: None,
+            },
+        )?;
+        assert_eq!(tree.get_oid(), target_commit.get_tree()?.get_oid());
+        assert!(became_empty);
+
+        // A commit which was already empty doesn't become empty.
+        let empty_commit = repo.revparse_single_commit("HEAD")?.unwrap();
+        let CherryPickFastResult { tree, became_empty } = repo.cherry_pick_fast(
+            &empty_commit,
+            &target_commit,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: false,
+                mainline: None,
+                max_dehydrated_paths: None,
+            },
+        )?;
+        assert_eq!(tree.get_oid(), target_commit.get_tree()?.get_oid());
+        assert!(!became_empty);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cherry_pick_fast_mainline() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        git.run(&["checkout", "-b", "foo"])?;
+        git.commit_file("test1", 1)?;
+        git.run(&["checkout", "master"])?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&["merge", "--no-edit", "foo"])?;
+        git.run(&["checkout", "-b", "bar", "HEAD^^"])?;
+        let test3_oid = git.commit_file("test3", 3)?;
+
+        let repo = git.get_repo()?;
+        let merge_commit = repo.revparse_single_commit("master")?.unwrap();
+        let test3_commit = repo.find_commit_or_fail(test3_oid)?;
+        let get_entry_names = |tree: &Tree| {
+            tree.inner
+                .iter()
+                .map(|entry| entry.name().unwrap().to_string())
+                .collect_vec()
+        };
+
+        // Relative to the first parent, the merge introduced `test1.txt`.
+        let CherryPickFastResult { tree, became_empty } = repo.cherry_pick_fast(
+            &merge_commit,
+            &test3_commit,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: false,
+                mainline: Some(1),
+                max_dehydrated_paths: None,
+            },
+        )?;
+        assert!(!became_empty);
+        insta::assert_debug_snapshot!(get_entry_names(&tree), @r###"
+        [
+            "initial.txt",
+            "test1.txt",
+            "test3.txt",
+        ]
+        "###);
+
+        // Relative to the second parent, the merge introduced `test2.txt`.
+        let CherryPickFastResult { tree, became_empty } = repo.cherry_pick_fast(
+            &merge_commit,
+            &test3_commit,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: false,
+                mainline: Some(2),
+                max_dehydrated_paths: None,
+            },
+        )?;
+        assert!(!became_empty);
+        insta::assert_debug_snapshot!(get_entry_names(&tree), @r###"
+        [
+            "initial.txt",
+            "test2.txt",
+            "test3.txt",
+        ]
+        "###);
+
+        assert!(matches!(
+            repo.cherry_pick_fast(
+                &merge_commit,
+                &test3_commit,
+                &CherryPickFastOptions {
+                    reuse_parent_tree_if_possible: false,
+                    mainline: None,
+                    max_dehydrated_paths: None,
+                },
+            ),
+            Err(CherryPickFastError::GetPatch { .. })
+        ));
+        assert!(matches!(
+            repo.cherry_pick_fast(
+                &merge_commit,
+                &test3_commit,
+                &CherryPickFastOptions {
+                    reuse_parent_tree_if_possible: false,
+                    mainline: Some(3),
+                    max_dehydrated_paths: None,
+                },
+            ),
+            Err(CherryPickFastError::InvalidMainline { .. })
+        ));
+        let test2_commit = repo.find_commit_or_fail(test2_oid)?;
+        assert!(matches!(
+            repo.cherry_pick_fast(
+                &test2_commit,
+                &test3_commit,
+                &CherryPickFastOptions {
+                    reuse_parent_tree_if_possible: false,
+                    mainline: Some(1),
+                    max_dehydrated_paths: None,
+                },
+            ),
+            Err(CherryPickFastError::InvalidMainline { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_would_be_empty() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&["checkout", "-b", "other", &test1_oid.to_string()])?;
+        git.run(&["cherry-pick", &test2_oid.to_string()])?;
+        let test3_oid = git.commit_file("test3", 3)?;
+        git.run(&["checkout", "-b", "conflict", &test1_oid.to_string()])?;
+        let conflict_oid = git.commit_file_with_contents("test2", 4, "conflicting contents\n")?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let test2_commit = repo.find_commit_or_fail(test2_oid)?;
+        let test3_commit = repo.find_commit_or_fail(test3_oid)?;
+        let conflict_commit = repo.find_commit_or_fail(conflict_oid)?;
+        assert!(!repo.would_be_empty(&test2_commit, &test1_commit)?);
+        assert!(repo.would_be_empty(&test2_commit, &test3_commit)?);
+        assert!(!repo.would_be_empty(&test3_commit, &test2_commit)?);
+        assert!(!repo.would_be_empty(&conflict_commit, &test3_commit)?);
+
+        git.run(&["commit", "--allow-empty", "-m", "empty commit"])?;
+        let empty_commit = repo.revparse_single_commit("HEAD")?.unwrap();
+        assert!(repo.would_be_empty(&empty_commit, &conflict_commit)?);
+        assert!(repo.would_be_empty(&empty_commit, &test3_commit)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cherry_pick_fast_full_index() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.write_file("test2", "test2 contents\n")?;
+        git.write_file("test3", "test3 contents\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "create test2.txt and test3.txt"])?;
+        git.write_file("test1", "updated test1 contents\n")?;
+        git.write_file("test5", "test5 contents\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "update test1.txt and create test5.txt"])?;
+        git.run(&["checkout", "-b", "other", &test1_oid.to_string()])?;
+        git.write_file("test1", "conflicting test1 contents\n")?;
+        git.write_file("test4", "test4 contents\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "update test1.txt and create test4.txt"])?;
+
+        let cherry_pick =
+            |patch_spec: &str, max_dehydrated_paths: Option<usize>| -> eyre::Result<_> {
+                let repo = git.get_repo()?;
+                let patch_commit = repo.revparse_single_commit(patch_spec)?.unwrap();
+                let target_commit = repo.revparse_single_commit("other")?.unwrap();
+                let result = match repo.cherry_pick_fast(
+                    &patch_commit,
+                    &target_commit,
+                    &CherryPickFastOptions {
+                        reuse_parent_tree_if_possible: false,
+                        mainline: None,
+                        max_dehydrated_paths,
+                    },
+                ) {
+                    Ok(CherryPickFastResult { tree, became_empty }) => {
+                        Ok((tree.get_oid(), became_empty))
+                    }
+                    Err(CherryPickFastError::MergeConflict { conflicting_paths }) => {
+                        Err(conflicting_paths)
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+                Ok(result)
+            };
+
+        let dehydrated_result = cherry_pick("master^", None)?;
+        let dehydrated_conflict = cherry_pick("master", None)?;
+        assert!(dehydrated_result.is_ok());
+        assert_eq!(
+            dehydrated_conflict,
+            Err([PathBuf::from("test1.txt")].into_iter().collect())
+        );
+
+        // Both commits touch more paths than the limit, so the full index is
+        // used for them, with the same results.
+        assert_eq!(cherry_pick("master^", Some(1))?, dehydrated_result);
+        assert_eq!(cherry_pick("master", Some(1))?, dehydrated_conflict);
+
+        // Measure both paths on a commit touching many files. The timings are
+        // only reported, since they're too noisy to assert on.
+        git.run(&["checkout", "master"])?;
+        for i in 0..200 {
+            git.write_file(&format!("many{i}"), &format!("many{i} contents\n"))?;
+        }
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "create many files"])?;
+        let time_cherry_pick = |max_dehydrated_paths: Option<usize>| -> eyre::Result<_> {
+            let start = std::time::Instant::now();
+            let result = cherry_pick("master", max_dehydrated_paths)?;
+            Ok((result, start.elapsed()))
+        };
+        let (dehydrated_result, dehydrated_duration) = time_cherry_pick(None)?;
+        let (full_index_result, full_index_duration) = time_cherry_pick(Some(0))?;
+        println!(
+            "cherry-picking 200 paths: dehydrated {dehydrated_duration:?}, full index {full_index_duration:?}"
+        );
+        assert!(dehydrated_result.is_ok());
+        assert_eq!(full_index_result, dehydrated_result);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cherry_pick_fast_type_change() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.delete_file("test1")?;
+        std::os::unix::fs::symlink("initial.txt", git.repo_path.join("test1.txt"))?;
+        git.run(&["add", "test1.txt"])?;
+        git.run(&["commit", "-m", "convert test1.txt to symlink"])?;
+        git.run(&["checkout", "-b", "other", &test1_oid.to_string()])?;
+        let test2_oid = git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        let symlink_commit = repo.revparse_single_commit("master")?.unwrap();
+        let touched_paths = repo.get_paths_touched_by_commit(&symlink_commit)?;
+        assert_eq!(
+            touched_paths,
+            Some([PathBuf::from("test1.txt")].into_iter().collect())
+        );
+
+        let test2_commit = repo.find_commit_or_fail(test2_oid)?;
+        let CherryPickFastResult { tree, became_empty } = repo.cherry_pick_fast(
+            &symlink_commit,
+            &test2_commit,
+            &CherryPickFastOptions {
+                reuse_parent_tree_if_possible: false,
+                mainline: None,
+                max_dehydrated_paths: None,
+            },
+        )?;
+        assert!(!became_empty);
+        let entry = tree.get_path(Path::new("test1.txt"))?.unwrap();
+        assert_eq!(entry.get_filemode(), FileMode::Link);
+        let blob = repo.find_blob_or_fail(entry.get_oid())?;
+        assert_eq!(blob.get_content(), b"initial.txt");
+        assert!(tree.get_path(Path::new("test2.txt"))?.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_amend_fast_from_index() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        git.run(&["checkout", "master"])?;
+        let initial_oid = git.commit_file_with_contents("initial", 2, "initial contents")?;
+        git.write_file("initial", "updated contents")?;
+
+        let repo = git.get_repo()?;
+        let initial_commit = repo.find_commit_or_fail(initial_oid)?;
+
+        let tree = initial_commit.get_tree()?;
+        insta::assert_debug_snapshot!(tree, @r###"
+        Tree {
+            inner: Tree {
+                id: 01deb7745d411223bbf6b9cb1abaeed451bb25a0,
+            },
+        }
+        "###);
+        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
+        [
+            (
+                "initial.txt",
+                "5c41c3d7e736911dbbd53d62c10292b9bc78f838",
+            ),
+        ]
+        "###);
+
+        let tree = repo.amend_fast(
+            &initial_commit,
+            &AmendFastOptions::FromIndex {
+                paths: vec!["initial.txt".into()],
+            },
+        )?;
+
+        insta::assert_debug_snapshot!(tree, @r###"
+        Tree {
+            inner: Tree {
+                id: 01deb7745d411223bbf6b9cb1abaeed451bb25a0,
+            },
+        }
+        "###);
+        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
+        [
+            (
+                "initial.txt",
+                "5c41c3d7e736911dbbd53d62c10292b9bc78f838",
+            ),
+        ]
+        "###);
+
+        git.run(&["add", "initial.txt"])?;
+        let tree = repo.amend_fast(
+            &initial_commit,
+            &AmendFastOptions::FromIndex {
+                paths: vec!["initial.txt".into()],
+            },
+        )?;
+
+        insta::assert_debug_snapshot!(tree, @r###"
+        Tree {
+            inner: Tree {
+                id: 1c15b79a72c3285df172fcfdaceedb7259283eb5,
+            },
+        }
+        "###);
+        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
+        [
+            (
+                "initial.txt",
+                "53cd9398c8a2d92f18d279c6cad3f5dde67235e7",
+            ),
+        ]
+        "###);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_amend_fast_commit() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.write_file("test2", "updated contents")?;
+        git.run(&["add", "test2.txt"])?;
+
+        let repo = git.get_repo()?;
+        let test2_commit = repo.find_commit_or_fail(test2_oid)?;
+        let opts = AmendFastOptions::FromIndex {
+            paths: vec!["test2.txt".into()],
+        };
+        let expected_tree = repo.amend_fast(&test2_commit, &opts)?;
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let amended_oid =
+            repo.amend_fast_commit(&test2_commit, &opts, None, None, None, false, now)?;
+        let amended_commit = repo.find_commit_or_fail(amended_oid)?;
+        assert_eq!(
+            amended_commit.get_tree()?.get_oid(),
+            expected_tree.get_oid()
+        );
+        assert_eq!(
+            amended_commit.get_parent_oids(),
+            test2_commit.get_parent_oids()
+        );
+        assert_eq!(
+            amended_commit.get_message_raw()?,
+            test2_commit.get_message_raw()?
+        );
+        let (author, original_author) = (amended_commit.get_author(), test2_commit.get_author());
+        assert_eq!(author.inner.to_string(), original_author.inner.to_string());
+        assert_eq!(author.inner.when(), original_author.inner.when());
+        assert_eq!(
+            amended_commit.get_committer().inner.when().seconds(),
+            1_000_000_000
+        );
+        // References are only updated if requested.
+        assert_eq!(repo.get_head_info()?.oid, Some(test2_oid));
+
+        let reworded_oid = repo.amend_fast_commit(
+            &test2_commit,
+            &opts,
+            Some("reworded message\n"),
+            Some("HEAD"),
+            None,
+            true,
+            now,
+        )?;
+        let reworded_commit = repo.find_commit_or_fail(reworded_oid)?;
+        assert_eq!(
+            reworded_commit.get_tree()?.get_oid(),
+            expected_tree.get_oid()
+        );
+        assert_eq!(reworded_commit.get_message_raw()?, "reworded message\n");
+        assert_eq!(
+            reworded_commit.get_committer().inner.when(),
+            test2_commit.get_committer().inner.when()
+        );
+        assert_eq!(repo.get_head_info()?.oid, Some(reworded_oid));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_amend_fast_from_working_tree() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        git.run(&["checkout", "master"])?;
+        let initial_oid = git.commit_file_with_contents("initial", 2, "initial contents")?;
+        git.write_file("initial", "updated contents")?;
+
+        let repo = git.get_repo()?;
+        let initial_commit = repo.find_commit_or_fail(initial_oid)?;
+        let tree = repo.amend_fast(
+            &initial_commit,
+            &AmendFastOptions::FromWorkingCopy {
+                status_entries: vec![StatusEntry {
+                    index_status: FileStatus::Renamed,
+                    working_copy_status: FileStatus::Unmodified,
+                    working_copy_file_mode: FileMode::Blob,
+                    path: "initial.txt".into(),
+                    orig_path: None,
+                }],
+            },
+        )?;
+
+        insta::assert_debug_snapshot!(tree, @r###"
+        Tree {
+            inner: Tree {
+                id: 1c15b79a72c3285df172fcfdaceedb7259283eb5,
+            },
+        }
+        "###);
+        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
+        [
+            (
+                "initial.txt",
+                "53cd9398c8a2d92f18d279c6cad3f5dde67235e7",
+            ),
+        ]
+        "###);
+
+        git.write_file("file2", "another file")?;
+        git.write_file("initial", "updated contents again")?;
+        let tree = repo.amend_fast(
+            &initial_commit,
+            &AmendFastOptions::FromWorkingCopy {
+                status_entries: vec![StatusEntry {
+                    index_status: FileStatus::Unmodified,
+                    working_copy_status: FileStatus::Added,
+                    working_copy_file_mode: FileMode::Blob,
+                    path: "file2.txt".into(),
+                    orig_path: None,
+                }],
+            },
+        )?;
+        insta::assert_debug_snapshot!(tree, @r###"
+        Tree {
+            inner: Tree {
+                id: 1a9fbbecd825881c3e79f0fb194a1c1e1104fe0f,
+            },
+        }
+        "###);
+        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
+        [
+            (
+                "file2.txt",
+                "cdcb28483da7783a8b505a074c50632a5481a69b",
+            ),
+            (
+                "initial.txt",
+                "5c41c3d7e736911dbbd53d62c10292b9bc78f838",
+            ),
+        ]
+        "###);
+
+        git.delete_file("initial")?;
+        let tree = repo.amend_fast(
+            &initial_commit,
+            &AmendFastOptions::FromWorkingCopy {
+                status_entries: vec![StatusEntry {
+                    index_status: FileStatus::Unmodified,
+                    working_copy_status: FileStatus::Deleted,
+                    working_copy_file_mode: FileMode::Blob,
+                    path: "initial.txt".into(),
+                    orig_path: None,
+                }],
+            },
+        )?;
+        insta::assert_debug_snapshot!(tree, @r###"
+        Tree {
+            inner: Tree {
+                id: 4b825dc642cb6eb9a060e54bf8d69288fbee4904,
+            },
+        }
+        "###);
+        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @"[]");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_amend_fast_mode_change() -> eyre::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let file_path = git.repo_path.join("test1.txt");
+        std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o755))?;
+
+        let get_file_mode = |tree: &Tree| -> eyre::Result<FileMode> {
+            let entry = tree.get_path(Path::new("test1.txt"))?.unwrap();
+            Ok(entry.get_filemode())
+        };
+        assert_eq!(get_file_mode(&test1_commit.get_tree()?)?, FileMode::Blob);
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let git_run_info = git.get_git_run_info();
+        let (_snapshot, status) = repo.get_status(
+            &effects,
+            &git_run_info,
+            &repo.get_index()?,
+            &repo.get_head_info()?,
+            None,
+        )?;
+        let tree = repo.amend_fast(
+            &test1_commit,
+            &AmendFastOptions::FromWorkingCopy {
+                status_entries: status,
+            },
+        )?;
+        assert_eq!(get_file_mode(&tree)?, FileMode::BlobExecutable);
+        assert_eq!(
+            tree.get_oid_for_path(Path::new("test1.txt"))?,
+            test1_commit
+                .get_tree()?
+                .get_oid_for_path(Path::new("test1.txt"))?
+        );
+
+        git.run(&["add", "test1.txt"])?;
+        let tree = repo.amend_fast(
+            &test1_commit,
+            &AmendFastOptions::FromIndex {
+                paths: vec!["test1.txt".into()],
+            },
+        )?;
+        assert_eq!(get_file_mode(&tree)?, FileMode::BlobExecutable);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_amend_fast_type_change() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.delete_file("test1")?;
+        std::os::unix::fs::symlink("initial.txt", git.repo_path.join("test1.txt"))?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let git_run_info = git.get_git_run_info();
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let (_snapshot, status) = repo.get_status(
+            &effects,
+            &git_run_info,
+            &repo.get_index()?,
+            &repo.get_head_info()?,
+            None,
+        )?;
+        assert_eq!(
+            status
+                .iter()
+                .map(|entry| entry.working_copy_status)
+                .collect_vec(),
+            vec![FileStatus::TypeChanged]
+        );
+
+        let tree = repo.amend_fast(
+            &test1_commit,
+            &AmendFastOptions::FromWorkingCopy {
+                status_entries: status,
+            },
+        )?;
+        let entry = tree.get_path(Path::new("test1.txt"))?.unwrap();
+        assert_eq!(entry.get_filemode(), FileMode::Link);
+        let blob = repo.find_blob_or_fail(entry.get_oid())?;
+        assert_eq!(blob.get_content(), b"initial.txt");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_add_remove_path() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.write_file("test1", "updated contents\n")?;
+        git.write_file("test2", "test2 contents\n")?;
+
+        let repo = git.get_repo()?;
+        let mut index = repo.get_index()?;
+        index.add_path(Path::new("test1.txt"))?;
+        index.add_path(Path::new("test2.txt"))?;
+        index.remove_path(Path::new("initial.txt"))?;
+        assert!(matches!(
+            index.add_path(Path::new("nonexistent.txt")),
+            Err(Error::AddIndexPath { path, .. }) if path == Path::new("nonexistent.txt")
+        ));
+        index.write()?;
+
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        D  initial.txt
+        M  test1.txt
+        A  test2.txt
+        ?? initial.txt
+        "###);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_amend_fast_symlink() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        std::os::unix::fs::symlink("test1.txt", git.repo_path.join("link.txt"))?;
+        git.run(&["add", "link.txt"])?;
+        git.run(&["commit", "-m", "add link"])?;
+
+        let repo = git.get_repo()?;
+        let link_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+        std::fs::remove_file(git.repo_path.join("link.txt"))?;
+        std::os::unix::fs::symlink("initial.txt", git.repo_path.join("link.txt"))?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let git_run_info = git.get_git_run_info();
+        let (_snapshot, status) = repo.get_status(
+            &effects,
+            &git_run_info,
+            &repo.get_index()?,
+            &repo.get_head_info()?,
+            None,
+        )?;
+        let tree = repo.amend_fast(
+            &link_commit,
+            &AmendFastOptions::FromWorkingCopy {
+                status_entries: status,
+            },
+        )?;
+        let entry = tree.get_path(Path::new("link.txt"))?.unwrap();
+        assert_eq!(entry.get_filemode(), FileMode::Link);
+        let blob = repo.find_blob_or_fail(entry.get_oid())?;
+        insta::assert_snapshot!(String::from_utf8_lossy(blob.get_content()), @"initial.txt");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_patch_ids() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.run(&["checkout", "-b", "other", "HEAD^"])?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&["checkout", "master"])?;
+        git.run(&["merge", "--no-ff", "-m", "merge", "other"])?;
+        git.run(&["checkout", "other"])?;
+        git.run(&["cherry-pick", "master^1"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let test2_commit = repo.find_commit_or_fail(test2_oid)?;
+        let merge_commit = repo.revparse_single_commit("master")?.unwrap();
+        let cherry_picked_commit = repo.revparse_single_commit("other")?.unwrap();
+        assert_ne!(cherry_picked_commit.get_oid(), test1_oid);
+
+        let patch_ids = repo.get_patch_ids(
+            &effects,
+            &[
+                &test1_commit,
+                &test2_commit,
+                &merge_commit,
+                &cherry_picked_commit,
+            ],
+            &DiffOptions::default(),
+        )?;
+        assert_eq!(patch_ids.len(), 3);
+        assert!(!patch_ids.contains_key(&merge_commit.get_oid()));
+        assert_eq!(
+            patch_ids.get(&test1_oid),
+            patch_ids.get(&cherry_picked_commit.get_oid())
+        );
+        assert_ne!(patch_ids.get(&test1_oid), patch_ids.get(&test2_oid));
+        assert_eq!(
+            patch_ids.get(&test2_oid).copied(),
+            repo.get_patch_id(&effects, &test2_commit, &DiffOptions::default())?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_patch_id_normalize_line_endings() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.run(&["config", "core.autocrlf", "false"])?;
+        let lf_oid = git.commit_file_with_contents("test1", 1, "foo\nbar\n")?;
+        git.run(&["checkout", "-b", "crlf", "HEAD^"])?;
+        let crlf_oid = git.commit_file_with_contents("test1", 2, "foo\r\nbar\r\n")?;
+        git.write_file("test1", "foo\nbar\n")?;
+        git.run(&["commit", "-a", "-m", "convert test1.txt to LF"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let get_patch_ids = || -> eyre::Result<_> {
+            let repo = git.get_repo()?;
+            let diff_options = DiffOptions::load(&repo)?;
+            let lf_commit = repo.find_commit_or_fail(lf_oid)?;
+            let crlf_commit = repo.find_commit_or_fail(crlf_oid)?;
+            let convert_commit = repo.revparse_single_commit("crlf")?.unwrap();
+            let convert_patch = repo
+                .get_patch_for_commit(&effects, &convert_commit, &diff_options)?
+                .unwrap();
+            let convert_patch = String::from_utf8(convert_patch.to_unified_bytes()?)?;
+            Ok((
+                repo.get_patch_id(&effects, &lf_commit, &diff_options)?
+                    .unwrap(),
+                repo.get_patch_id(&effects, &crlf_commit, &diff_options)?
+                    .unwrap(),
+                convert_patch.contains("@@"),
+            ))
+        };
+
+        // Patch IDs ignore whitespace within changed lines regardless, but a
+        // commit which only changes line endings still has a patch.
+        let (lf_patch_id, crlf_patch_id, convert_has_hunks) = get_patch_ids()?;
+        assert_eq!(lf_patch_id, crlf_patch_id);
+        assert!(convert_has_hunks);
+
+        git.run(&["config", "branchless.diff.normalizeLineEndings", "true"])?;
+        let (lf_patch_id, crlf_patch_id, convert_has_hunks) = get_patch_ids()?;
+        assert_eq!(lf_patch_id, crlf_patch_id);
+        assert!(!convert_has_hunks);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_patch_id_empty_patch() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.run(&["config", "core.autocrlf", "false"])?;
+        git.commit_file_with_contents("test1", 1, "foo  \r\nbar\r\n")?;
+        git.write_file("test1", "foo\r\nbar\r\n")?;
+        git.run(&["commit", "-a", "-m", "remove trailing whitespace"])?;
+        let whitespace_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+        git.write_file("test1", "foo\nbar\n")?;
+        git.run(&["commit", "-a", "-m", "convert test1.txt to LF"])?;
+        let convert_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+        git.run(&["commit", "--allow-empty", "-m", "empty commit"])?;
+        let empty_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let whitespace_commit = repo.find_commit_or_fail(whitespace_oid)?;
+        let convert_commit = repo.find_commit_or_fail(convert_oid)?;
+        let empty_commit = repo.find_commit_or_fail(empty_oid)?;
+
+        for diff_options in [
+            DiffOptions::default(),
+            DiffOptions {
+                normalize_line_endings: true,
+                ..Default::default()
+            },
+        ] {
+            // Removing trailing whitespace is a content change, even when
+            // line endings are normalized.
+            let whitespace_patch = repo
+                .get_patch_for_commit(&effects, &whitespace_commit, &diff_options)?
+                .unwrap();
+            assert!(!whitespace_patch.is_empty()?);
+            assert!(repo
+                .get_patch_id(&effects, &whitespace_commit, &diff_options)?
+                .is_some());
+
+            // Commits which don't change anything still have a patch ID, so
+            // that they're considered duplicates of each other.
+            assert!(repo
+                .get_patch_id(&effects, &empty_commit, &diff_options)?
+                .is_some());
+        }
+
+        assert!(repo
+            .get_patch_id(&effects, &convert_commit, &DiffOptions::default())?
+            .is_some());
+
+        // The commit which only converts line endings has an empty patch when
+        // line endings are normalized, so it shouldn't be considered a
+        // duplicate of any commit which doesn't change anything.
+        let diff_options = DiffOptions {
+            normalize_line_endings: true,
+            ..Default::default()
+        };
+        let convert_patch = repo
+            .get_patch_for_commit(&effects, &convert_commit, &diff_options)?
+            .unwrap();
+        assert!(convert_patch.is_empty()?);
+        assert_eq!(
+            repo.get_patch_id(&effects, &convert_commit, &diff_options)?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_patch_id_ignores_timestamps() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.run_with_options(
+            &[
+                "commit",
+                "--amend",
+                "--no-edit",
+                "--date",
+                "2001-02-03T04:05:06+0900",
+            ],
+            &GitRunOptions {
+                env: [(
+                    "GIT_COMMITTER_DATE".to_string(),
+                    "2002-03-04T05:06:07-0430".to_string(),
+                )]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            },
+        )?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let amended_commit = repo.revparse_single_commit("HEAD")?.unwrap();
+        assert_ne!(amended_commit.get_oid(), test1_oid);
+        assert_ne!(
+            amended_commit.get_author().get_time(),
+            test1_commit.get_author().get_time()
+        );
+        assert_ne!(
+            amended_commit.get_committer().get_time(),
+            test1_commit.get_committer().get_time()
+        );
+
+        let test1_patch_id = repo.get_patch_id(&effects, &test1_commit, &DiffOptions::default())?;
+        assert!(test1_patch_id.is_some());
+        assert_eq!(
+            repo.get_patch_id(&effects, &amended_commit, &DiffOptions::default())?,
+            test1_patch_id
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_dir_with_ceiling() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        let repo_path = git.repo_path.canonicalize()?;
+        let subdir_path = repo_path.join("foo").join("bar");
+        std::fs::create_dir_all(&subdir_path)?;
+
+        let repo = Repo::from_dir_with_ceiling(&subdir_path, &[])?;
+        assert_eq!(
+            repo.get_working_copy_path(),
+            Some(repo_path.join("").as_path())
+        );
+
+        // The ceiling directory itself is not searched.
+        let result = Repo::from_dir_with_ceiling(&subdir_path, std::slice::from_ref(&repo_path));
+        assert!(matches!(result, Err(Error::OpenRepo(_))));
+        let result = Repo::from_dir_with_ceiling(&subdir_path, &[repo_path.join("foo")]);
+        assert!(matches!(result, Err(Error::OpenRepo(_))));
+
+        // Ceiling directories don't apply if the starting directory is itself
+        // the root of the repository.
+        let repo = Repo::from_dir_with_ceiling(&repo_path, std::slice::from_ref(&repo_path))?;
+        assert_eq!(
+            repo.get_working_copy_path(),
+            Some(repo_path.join("").as_path())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_quick_status() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
 
         let repo = git.get_repo()?;
-        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
-        let initial2_commit = repo.find_commit_or_fail(initial2_oid)?;
-        let tree = repo.cherry_pick_fast(
-            &test1_commit,
-            &initial2_commit,
-            &CherryPickFastOptions {
-                reuse_parent_tree_if_possible: false,
-            },
-        )?;
+        assert_eq!(
+            repo.quick_status()?,
+            QuickStatus {
+                branch_name: Some("master".to_string()),
+                head_oid: Some(test1_oid),
+                is_dirty: false,
+            }
+        );
 
-        insta::assert_debug_snapshot!(tree, @r###"
-        Tree {
-            inner: Tree {
-                id: 367f91ddd5df2d1c18742ce3f09b4944944cac3a,
-            },
-        }
-        "###);
+        // Untracked files don't make the working copy dirty.
+        git.write_file("untracked", "contents")?;
+        assert!(!repo.quick_status()?.is_dirty);
 
-        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| entry.name().unwrap().to_string()).collect_vec(), @r###"
-        [
-            "initial.txt",
-            "test1.txt",
-        ]
-        "###);
+        git.write_file("test1", "updated contents")?;
+        git.run(&["checkout", "--detach"])?;
+        assert_eq!(
+            repo.quick_status()?,
+            QuickStatus {
+                branch_name: None,
+                head_oid: Some(test1_oid),
+                is_dirty: true,
+            }
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_amend_fast_from_index() -> eyre::Result<()> {
+    fn test_get_range() -> eyre::Result<()> {
         let git = make_git()?;
         git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&["checkout", "-b", "other", "HEAD^"])?;
+        let test3_oid = git.commit_file("test3", 3)?;
+        git.run(&["merge", "--no-ff", "-m", "merge", "master"])?;
+        let merge_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
 
-        git.run(&["checkout", "master"])?;
-        let initial_oid = git.commit_file_with_contents("initial", 2, "initial contents")?;
-        git.write_file("initial", "updated contents")?;
+        let repo = git.get_repo()?;
+        let get_range = |from, to| -> eyre::Result<Vec<NonZeroOid>> {
+            Ok(repo
+                .get_range(from, to)?
+                .iter()
+                .map(|commit| commit.get_oid())
+                .collect())
+        };
+        assert_eq!(get_range(test1_oid, test2_oid)?, vec![test2_oid]);
+        assert_eq!(get_range(test2_oid, test2_oid)?, vec![]);
+        assert_eq!(get_range(test2_oid, test1_oid)?, vec![]);
+        assert_eq!(get_range(test2_oid, test3_oid)?, vec![test3_oid]);
+
+        let range = get_range(test1_oid, merge_oid)?;
+        assert_eq!(range.len(), 3);
+        assert_eq!(range.last(), Some(&merge_oid));
+        assert!(range.contains(&test2_oid));
+        assert!(range.contains(&test3_oid));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_merge_commit() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        git.run(&["checkout", "-b", "other", "HEAD^"])?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        git.run(&["merge", "--no-ff", "-m", "merge", "master"])?;
 
         let repo = git.get_repo()?;
-        let initial_commit = repo.find_commit_or_fail(initial_oid)?;
+        let test1_commit = repo.find_commit_or_fail(test1_oid)?;
+        let test2_commit = repo.find_commit_or_fail(test2_oid)?;
+        let resolved_tree = repo.resolve_tree("HEAD")?.unwrap();
+        let signature = Signature::automated()?;
 
-        let tree = initial_commit.get_tree()?;
-        insta::assert_debug_snapshot!(tree, @r###"
-        Tree {
-            inner: Tree {
-                id: 01deb7745d411223bbf6b9cb1abaeed451bb25a0,
-            },
+        let merge_oid = repo.create_merge_commit(
+            "merge",
+            &[&test2_commit, &test1_commit],
+            &resolved_tree,
+            &signature,
+            &signature,
+        )?;
+        let merge_commit = repo.find_commit_or_fail(merge_oid)?;
+        assert_eq!(merge_commit.get_parent_oids(), vec![test2_oid, test1_oid]);
+        assert_eq!(merge_commit.get_tree()?.get_oid(), resolved_tree.get_oid());
+
+        for parents in [vec![&test1_commit], vec![&test1_commit, &test1_commit]] {
+            let result =
+                repo.create_merge_commit("merge", &parents, &resolved_tree, &signature, &signature);
+            assert!(matches!(result, Err(Error::InvalidMergeParents { .. })));
         }
-        "###);
-        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
-        [
-            (
-                "initial.txt",
-                "5c41c3d7e736911dbbd53d62c10292b9bc78f838",
-            ),
-        ]
-        "###);
 
-        let tree = repo.amend_fast(
-            &initial_commit,
-            &AmendFastOptions::FromIndex {
-                paths: vec!["initial.txt".into()],
-            },
-        )?;
+        Ok(())
+    }
 
-        insta::assert_debug_snapshot!(tree, @r###"
-        Tree {
-            inner: Tree {
-                id: 01deb7745d411223bbf6b9cb1abaeed451bb25a0,
-            },
-        }
-        "###);
-        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
-        [
-            (
-                "initial.txt",
-                "5c41c3d7e736911dbbd53d62c10292b9bc78f838",
-            ),
-        ]
-        "###);
+    #[test]
+    fn test_check_repo_state() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo_with_options(&GitInitOptions {
+            make_initial_commit: false,
+            run_branchless_init: false,
+        })?;
 
-        git.run(&["add", "initial.txt"])?;
-        let tree = repo.amend_fast(
-            &initial_commit,
-            &AmendFastOptions::FromIndex {
-                paths: vec!["initial.txt".into()],
-            },
-        )?;
+        let repo = git.get_repo()?;
+        assert_eq!(
+            repo.check_repo_state()?,
+            RepoStateWarnings {
+                is_head_unborn: true,
+                ..Default::default()
+            }
+        );
 
-        insta::assert_debug_snapshot!(tree, @r###"
-        Tree {
-            inner: Tree {
-                id: 1c15b79a72c3285df172fcfdaceedb7259283eb5,
+        git.commit_file("test1", 1)?;
+        assert!(repo.check_repo_state()?.is_empty());
+
+        git.run(&["checkout", "--detach"])?;
+        assert!(repo.check_repo_state()?.is_empty());
+        git.write_file("test1", "updated contents")?;
+        assert_eq!(
+            repo.check_repo_state()?,
+            RepoStateWarnings {
+                is_detached_and_dirty: true,
+                ..Default::default()
+            }
+        );
+        git.run(&["checkout", "test1.txt"])?;
+
+        git.run(&["checkout", "-b", "ours"])?;
+        git.write_file("test1", "our contents")?;
+        git.run(&["commit", "-a", "-m", "our commit"])?;
+        git.run(&["checkout", "-b", "theirs", "HEAD^"])?;
+        git.write_file("test1", "their contents")?;
+        git.run(&["commit", "-a", "-m", "their commit"])?;
+        git.run_with_options(
+            &["merge", "ours"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
             },
-        }
-        "###);
-        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
-        [
-            (
-                "initial.txt",
-                "53cd9398c8a2d92f18d279c6cad3f5dde67235e7",
-            ),
-        ]
+        )?;
+        let warnings = repo.check_repo_state()?;
+        assert_eq!(
+            warnings,
+            RepoStateWarnings {
+                operation_type: Some("merge".to_string()),
+                unmerged_paths: vec![PathBuf::from("test1.txt")],
+                is_detached_and_dirty: false,
+                is_head_unborn: false,
+            }
+        );
+        insta::assert_snapshot!(warnings.to_string(), @r###"
+        A merge operation is in progress.
+        There are unresolved merge conflicts in 1 path(s): test1.txt
         "###);
 
         Ok(())
     }
 
     #[test]
-    fn test_amend_fast_from_working_tree() -> eyre::Result<()> {
+    fn test_reword_preserving_trailers() -> eyre::Result<()> {
         let git = make_git()?;
         git.init_repo()?;
-
-        git.run(&["checkout", "master"])?;
-        let initial_oid = git.commit_file_with_contents("initial", 2, "initial contents")?;
-        git.write_file("initial", "updated contents")?;
+        git.run(&[
+            "commit",
+            "--allow-empty",
+            "-m",
+            "old subject\n\nold body\n\nSigned-off-by: Testy McTestface <test@example.com>\nChange-Id: I123",
+        ])?;
 
         let repo = git.get_repo()?;
-        let initial_commit = repo.find_commit_or_fail(initial_oid)?;
-        let tree = repo.amend_fast(
-            &initial_commit,
-            &AmendFastOptions::FromWorkingCopy {
-                status_entries: vec![StatusEntry {
-                    index_status: FileStatus::Renamed,
-                    working_copy_status: FileStatus::Unmodified,
-                    working_copy_file_mode: FileMode::Blob,
-                    path: "initial.txt".into(),
-                    orig_path: None,
-                }],
-            },
-        )?;
+        let commit = repo.revparse_single_commit("HEAD")?.unwrap();
 
-        insta::assert_debug_snapshot!(tree, @r###"
-        Tree {
-            inner: Tree {
-                id: 1c15b79a72c3285df172fcfdaceedb7259283eb5,
-            },
-        }
-        "###);
-        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
-        [
-            (
-                "initial.txt",
-                "53cd9398c8a2d92f18d279c6cad3f5dde67235e7",
-            ),
-        ]
-        "###);
+        insta::assert_snapshot!(commit.reword_preserving_trailers("new subject", None)?, @r###"
+        new subject
 
-        git.write_file("file2", "another file")?;
-        git.write_file("initial", "updated contents again")?;
-        let tree = repo.amend_fast(
-            &initial_commit,
-            &AmendFastOptions::FromWorkingCopy {
-                status_entries: vec![StatusEntry {
-                    index_status: FileStatus::Unmodified,
-                    working_copy_status: FileStatus::Added,
-                    working_copy_file_mode: FileMode::Blob,
-                    path: "file2.txt".into(),
-                    orig_path: None,
-                }],
-            },
-        )?;
-        insta::assert_debug_snapshot!(tree, @r###"
-        Tree {
-            inner: Tree {
-                id: 1a9fbbecd825881c3e79f0fb194a1c1e1104fe0f,
-            },
-        }
+        Signed-off-by: Testy McTestface <test@example.com>
+        Change-Id: I123
         "###);
-        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @r###"
-        [
-            (
-                "file2.txt",
-                "cdcb28483da7783a8b505a074c50632a5481a69b",
-            ),
-            (
-                "initial.txt",
-                "5c41c3d7e736911dbbd53d62c10292b9bc78f838",
-            ),
+        let reworded_messages = [
+            ("new subject\n\n\nnew body\n\n\n", None),
+            ("new subject\n\nchange-id: I456\nReviewed-by: Someone", None),
+            ("# only a comment\nnew subject", Some('#')),
         ]
+        .into_iter()
+        .map(|(message, comment_char)| commit.reword_preserving_trailers(message, comment_char))
+        .collect::<Result<Vec<_>>>()?;
+        insta::assert_snapshot!(reworded_messages.join("---\n"), @r###"
+        new subject
+
+        new body
+
+        Signed-off-by: Testy McTestface <test@example.com>
+        Change-Id: I123
+        ---
+        new subject
+
+        change-id: I456
+        Reviewed-by: Someone
+        Signed-off-by: Testy McTestface <test@example.com>
+        ---
+        new subject
+
+        Signed-off-by: Testy McTestface <test@example.com>
+        Change-Id: I123
         "###);
 
-        git.delete_file("initial")?;
-        let tree = repo.amend_fast(
-            &initial_commit,
-            &AmendFastOptions::FromWorkingCopy {
-                status_entries: vec![StatusEntry {
-                    index_status: FileStatus::Unmodified,
-                    working_copy_status: FileStatus::Deleted,
-                    working_copy_file_mode: FileMode::Blob,
-                    path: "initial.txt".into(),
-                    orig_path: None,
-                }],
-            },
-        )?;
-        insta::assert_debug_snapshot!(tree, @r###"
-        Tree {
-            inner: Tree {
-                id: 4b825dc642cb6eb9a060e54bf8d69288fbee4904,
-            },
-        }
-        "###);
-        insta::assert_debug_snapshot!(tree.inner.iter().map(|entry| (entry.name().unwrap().to_string(), entry.id().to_string())).collect_vec(), @"[]");
-
         Ok(())
     }
 
@@ -2422,4 +8293,135 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_find_branches_matching() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["branch", "feat/foo"])?;
+        git.run(&["branch", "feat/bar"])?;
+        git.run(&["branch", "fix/baz"])?;
+        git.run(&["update-ref", "refs/remotes/origin/feat/qux", "HEAD"])?;
+
+        let repo = git.get_repo()?;
+        let branch_names = |glob: &str, branch_type: BranchType| -> eyre::Result<Vec<String>> {
+            repo.find_branches_matching(glob, branch_type)?
+                .iter()
+                .map(|branch| Ok(branch.get_name()?.to_owned()))
+                .collect()
+        };
+        assert_eq!(
+            branch_names("feat/*", BranchType::Local)?,
+            vec!["feat/bar".to_string(), "feat/foo".to_string()]
+        );
+        assert_eq!(
+            branch_names("f*/ba?", BranchType::Local)?,
+            vec!["feat/bar".to_string(), "fix/baz".to_string()]
+        );
+        assert_eq!(
+            branch_names("*/feat/*", BranchType::Remote)?,
+            vec!["origin/feat/qux".to_string()]
+        );
+        assert!(branch_names("nonexistent/*", BranchType::Local)?.is_empty());
+        assert_eq!(
+            branch_names("feat/**o", BranchType::Local)?,
+            vec!["feat/foo".to_string()]
+        );
+        assert_eq!(
+            branch_names("f[!i]at/[]a-z]ar", BranchType::Local)?,
+            vec!["feat/bar".to_string()]
+        );
+
+        assert!(matches!(
+            repo.find_branches_matching("feat/[ab", BranchType::Local),
+            Err(Error::InvalidBranchGlob { .. })
+        ));
+        assert!(matches!(
+            repo.find_branches_matching("feat/[[:alpha:]", BranchType::Local),
+            Err(Error::InvalidBranchGlob { .. })
+        ));
+        assert!(matches!(
+            repo.find_branches_matching("", BranchType::Local),
+            Err(Error::InvalidBranchGlob { .. })
+        ));
+        assert!(matches!(
+            repo.find_branches_matching("refs/heads/*", BranchType::Local),
+            Err(Error::InvalidBranchGlob { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_tree() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["branch", "main"])?;
+        git.commit_file("test2", 2)?;
+
+        let repo = git.get_repo()?;
+        let main_commit = repo.revparse_single_commit("main")?.unwrap();
+        let main_tree_oid = repo.peel_to_tree(&main_commit)?.get_oid();
+        for spec in ["main", "main^{tree}", "main:"] {
+            let tree = repo.resolve_tree(spec)?;
+            assert_eq!(
+                tree.map(|tree| tree.get_oid()),
+                Some(main_tree_oid),
+                "{spec}"
+            );
+        }
+
+        let head_tree_oid = repo.resolve_tree("HEAD")?.unwrap().get_oid();
+        assert_ne!(head_tree_oid, main_tree_oid);
+
+        assert!(repo.resolve_tree("main:test1.txt")?.is_none());
+        assert!(repo.resolve_tree("nonexistent")?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_local_branch_infos() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["remote", "add", "origin", "https://example.com/repo.git"])?;
+        git.run(&["branch", "feature"])?;
+        git.run(&[
+            "config",
+            "branch.feature.description",
+            "Work on the feature",
+        ])?;
+        git.run(&["config", "branch.feature.remote", "origin"])?;
+        git.run(&["config", "branch.feature.merge", "refs/heads/master"])?;
+        git.run(&["config", "branch.feature.pushRemote", "fork"])?;
+        git.run(&["update-ref", "refs/remotes/origin/master", "HEAD"])?;
+
+        let repo = git.get_repo()?;
+        let head_oid = repo.get_head_info()?.oid;
+        let branch_infos = repo.get_local_branch_infos()?;
+        assert_eq!(
+            branch_infos,
+            vec![
+                BranchInfo {
+                    name: "feature".to_string(),
+                    oid: head_oid,
+                    upstream_name: Some("origin/master".to_string()),
+                    push_remote_name: Some("fork".to_string()),
+                    description: Some("Work on the feature".to_string()),
+                },
+                BranchInfo {
+                    name: "master".to_string(),
+                    oid: head_oid,
+                    upstream_name: None,
+                    push_remote_name: None,
+                    description: None,
+                },
+            ]
+        );
+
+        Ok(())
+    }
 }
@@ -16,7 +16,7 @@ use tracing::instrument;
 use crate::core::config::get_core_hooks_path;
 use crate::core::effects::{Effects, OperationType};
 use crate::core::eventlog::{EventTransactionId, BRANCHLESS_TRANSACTION_ID_ENV_VAR};
-use crate::git::repo::Repo;
+use crate::git::repo::{GitVersion, Repo};
 use crate::util::{get_sh, ExitCode};
 
 /// Path to the `git` executable on disk to be executed.
@@ -341,6 +341,22 @@ impl GitRunInfo {
         )
     }
 
+    /// Get the version of the Git executable.
+    #[instrument]
+    pub fn get_version(&self, repo: &Repo) -> eyre::Result<GitVersion> {
+        let version_str = self
+            .run_silent(repo, None, &["version"], Default::default())
+            .wrap_err("Determining Git version")?
+            .stdout;
+        let version_str =
+            String::from_utf8(version_str).wrap_err("Decoding stdout from Git subprocess")?;
+        let version_str = version_str.trim();
+        let version: GitVersion = version_str
+            .parse()
+            .wrap_err_with(|| format!("Parsing Git version string: {}", version_str))?;
+        Ok(version)
+    }
+
     fn run_hook_inner(
         &self,
         effects: &Effects,
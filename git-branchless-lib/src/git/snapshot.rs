@@ -21,8 +21,11 @@
 //!  into multiple could also be used to split the working copy into multiple
 //!  commits.
 
+use cursive::theme::BaseColor;
+use cursive::utils::markup::StyledString;
 use itertools::Itertools;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use tracing::instrument;
@@ -31,9 +34,9 @@ use crate::core::formatting::Pluralize;
 use crate::git::FileStatus;
 
 use super::index::{Index, IndexEntry, Stage};
-use super::repo::Signature;
+use super::repo::{Error as RepoError, Signature};
 use super::status::FileMode;
-use super::tree::{hydrate_tree, make_empty_tree};
+use super::tree::{get_changed_paths_between_trees, hydrate_tree, make_empty_tree, Tree};
 use super::{
     Commit, MaybeZeroOid, NonZeroOid, ReferenceName, Repo, ResolvedReferenceInfo, StatusEntry,
 };
@@ -332,6 +335,11 @@ branchless: automated working copy snapshot
         let num_changes = changed_paths.len();
 
         let head_tree = head_commit.map(|commit| commit.get_tree()).transpose()?;
+        // Status paths are relative to the repository root, which isn't
+        // necessarily the current directory.
+        let working_copy_path = repo
+            .get_working_copy_path()
+            .ok_or(RepoError::NoWorkingCopyPath)?;
         let hydrate_entries = {
             let mut result = HashMap::new();
             for (path, file_mode) in changed_paths {
@@ -342,8 +350,14 @@ branchless: automated working copy snapshot
                     // existence on disk because it's no longer being tracked by
                     // the index.
                     None
+                } else if file_mode == FileMode::Commit {
+                    // Submodules are recorded as the commit they have checked
+                    // out; any changes in their working copies aren't
+                    // captured.
+                    repo.get_submodule_head_oid(&working_copy_path.join(&path))?
+                        .map(|commit_oid| (commit_oid, file_mode))
                 } else {
-                    repo.create_blob_from_path(&path)?
+                    repo.create_blob_from_path(&working_copy_path.join(&path))?
                         .map(|blob_oid| (blob_oid, file_mode))
                 };
                 result.insert(path, entry);
@@ -412,6 +426,7 @@ branchless: automated working copy snapshot
                         | FileStatus::Ignored
                         | FileStatus::Modified
                         | FileStatus::Renamed
+                        | FileStatus::TypeChanged
                         | FileStatus::Unmodified
                         | FileStatus::Untracked,
                     ) => continue,
@@ -489,6 +504,91 @@ branchless: automated working copy snapshot
             Ok(WorkingCopyChangesType::None)
         }
     }
+
+    /// Render the changes captured in this snapshot relative to its `HEAD`
+    /// commit, similarly to `git status`. The changes are grouped into
+    /// unmerged paths, staged changes, and unstaged changes. Groups without
+    /// any changes are omitted, so no lines are returned if the working copy
+    /// was clean.
+    #[instrument]
+    pub fn render_changes(&self, repo: &Repo) -> eyre::Result<Vec<StyledString>> {
+        let base_tree = self.base_commit.get_tree()?;
+        let stage0_tree = self.commit_stage0.get_tree()?;
+        let unstaged_tree = self.commit_unstaged.get_tree()?;
+
+        let mut unmerged_paths = BTreeSet::new();
+        for commit in [
+            &self.commit_stage1,
+            &self.commit_stage2,
+            &self.commit_stage3,
+        ] {
+            let stage_tree = commit.get_tree()?;
+            unmerged_paths.extend(get_changed_paths_between_trees(
+                repo,
+                Some(&base_tree.inner),
+                Some(&stage_tree.inner),
+            )?);
+        }
+
+        let describe_changes = |old_tree: &Tree,
+                                new_tree: &Tree,
+                                color: BaseColor|
+         -> eyre::Result<Vec<StyledString>> {
+            let changed_paths: BTreeSet<PathBuf> = get_changed_paths_between_trees(
+                repo,
+                Some(&old_tree.inner),
+                Some(&new_tree.inner),
+            )?
+            .into_iter()
+            .filter(|path| !unmerged_paths.contains(path))
+            .collect();
+            let mut lines = Vec::new();
+            for path in changed_paths {
+                let change = match (old_tree.get_path(&path)?, new_tree.get_path(&path)?) {
+                    (None, _) => "added",
+                    (_, None) => "deleted",
+                    (Some(_), Some(_)) => "modified",
+                };
+                lines.push(StyledString::styled(
+                    format!("    {}: {}", change, path.display()),
+                    color.light(),
+                ));
+            }
+            Ok(lines)
+        };
+
+        let mut lines = Vec::new();
+        if !unmerged_paths.is_empty() {
+            lines.push(StyledString::plain("Unmerged paths:"));
+            for path in unmerged_paths.iter() {
+                lines.push(StyledString::styled(
+                    format!("    unmerged: {}", path.display()),
+                    BaseColor::Red.light(),
+                ));
+            }
+        }
+        for (header, old_tree, new_tree, color) in [
+            (
+                "Staged changes:",
+                &base_tree,
+                &stage0_tree,
+                BaseColor::Green,
+            ),
+            (
+                "Unstaged changes:",
+                &stage0_tree,
+                &unstaged_tree,
+                BaseColor::Red,
+            ),
+        ] {
+            let changes = describe_changes(old_tree, new_tree, color)?;
+            if !changes.is_empty() {
+                lines.push(StyledString::plain(header));
+                lines.extend(changes);
+            }
+        }
+        Ok(lines)
+    }
 }
 
 #[cfg(test)]
@@ -497,7 +597,7 @@ mod tests {
 
     use crate::core::effects::Effects;
     use crate::core::eventlog::EventLogDb;
-    use crate::core::formatting::Glyphs;
+    use crate::core::formatting::{printable_styled_string, Glyphs};
     use crate::git::WorkingCopyChangesType;
     use crate::testing::{make_git, GitRunOptions};
 
@@ -553,4 +653,51 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_render_changes() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.commit_file("test2", 2)?;
+
+        let glyphs = Glyphs::text();
+        let effects = Effects::new_suppress_for_test(glyphs.clone());
+        let git_run_info = git.get_git_run_info();
+        let repo = git.get_repo()?;
+        let render = || -> eyre::Result<String> {
+            let (snapshot, _status) = repo.get_status(
+                &effects,
+                &git_run_info,
+                &repo.get_index()?,
+                &repo.get_head_info()?,
+                None,
+            )?;
+            let lines = snapshot
+                .render_changes(&repo)?
+                .into_iter()
+                .map(|line| printable_styled_string(&glyphs, line))
+                .collect::<eyre::Result<Vec<_>>>()?;
+            Ok(lines.join("\n"))
+        };
+
+        insta::assert_snapshot!(render()?, @"");
+
+        git.write_file("new_file", "new file contents\n")?;
+        git.run(&["add", "new_file.txt"])?;
+        git.write_file("test1", "staged contents\n")?;
+        git.run(&["add", "test1.txt"])?;
+        git.write_file("test1", "unstaged contents\n")?;
+        git.delete_file("test2")?;
+        insta::assert_snapshot!(render()?, @r###"
+        Staged changes:
+            added: new_file.txt
+            modified: test1.txt
+        Unstaged changes:
+            modified: test1.txt
+            deleted: test2.txt
+        "###);
+
+        Ok(())
+    }
 }
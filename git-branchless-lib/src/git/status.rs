@@ -6,6 +6,8 @@ use lazy_static::lazy_static;
 use regex::bytes::Regex;
 use tracing::{instrument, warn};
 
+use super::NonZeroOid;
+
 /// A Git file status indicator.
 /// See <https://git-scm.com/docs/git-status#_short_format>.
 #[allow(missing_docs)]
@@ -17,6 +19,7 @@ pub enum FileStatus {
     Deleted,
     Renamed,
     Copied,
+    TypeChanged,
     Unmerged,
     Untracked,
     Ignored,
@@ -31,7 +34,8 @@ impl FileStatus {
             | FileStatus::Copied
             | FileStatus::Deleted
             | FileStatus::Modified
-            | FileStatus::Renamed => true,
+            | FileStatus::Renamed
+            | FileStatus::TypeChanged => true,
             FileStatus::Ignored
             | FileStatus::Unmerged
             | FileStatus::Unmodified
@@ -49,6 +53,7 @@ impl From<u8> for FileStatus {
             b'D' => FileStatus::Deleted,
             b'R' => FileStatus::Renamed,
             b'C' => FileStatus::Copied,
+            b'T' => FileStatus::TypeChanged,
             b'U' => FileStatus::Unmerged,
             b'?' => FileStatus::Untracked,
             b'!' => FileStatus::Ignored,
@@ -60,6 +65,27 @@ impl From<u8> for FileStatus {
     }
 }
 
+impl From<git2::Delta> for FileStatus {
+    fn from(delta: git2::Delta) -> Self {
+        match delta {
+            git2::Delta::Unmodified => FileStatus::Unmodified,
+            git2::Delta::Added => FileStatus::Added,
+            git2::Delta::Deleted => FileStatus::Deleted,
+            git2::Delta::Modified => FileStatus::Modified,
+            git2::Delta::Renamed => FileStatus::Renamed,
+            git2::Delta::Copied => FileStatus::Copied,
+            git2::Delta::Typechange => FileStatus::TypeChanged,
+            git2::Delta::Conflicted => FileStatus::Unmerged,
+            git2::Delta::Untracked => FileStatus::Untracked,
+            git2::Delta::Ignored => FileStatus::Ignored,
+            git2::Delta::Unreadable => {
+                warn!(?delta, "unreadable delta");
+                FileStatus::Untracked
+            }
+        }
+    }
+}
+
 /// Wrapper around [git2::FileMode].
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -181,6 +207,91 @@ impl StatusEntry {
     }
 }
 
+/// Options for `Repo::get_status_with_options`.
+#[derive(Clone, Debug)]
+pub struct StatusOptions {
+    /// If set, then renamed files are reported as a single entry with both the
+    /// old and new paths. Otherwise, they're reported as separate deletion and
+    /// addition entries. Either way, this overrides the user's
+    /// `status.renames` setting. Defaults to `true`.
+    pub detect_renames: bool,
+
+    /// If set, then submodules whose working copies have changes, but which
+    /// still point to their recorded commits, aren't reported as changed.
+    /// Otherwise, the user's own submodule configuration applies. Callers
+    /// should usually set this according to `get_ignore_submodule_dirty`.
+    /// Defaults to `false`.
+    pub ignore_submodule_dirty: bool,
+}
+
+impl Default for StatusOptions {
+    fn default() -> Self {
+        Self {
+            detect_renames: true,
+            ignore_submodule_dirty: false,
+        }
+    }
+}
+
+/// The branch information reported in the headers of `git status
+/// --porcelain=v2 --branch`.
+/// See <https://git-scm.com/docs/git-status#_branch_headers>.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatusHeader {
+    /// The commit that `HEAD` points to, or `None` if `HEAD` is unborn.
+    pub head_oid: Option<NonZeroOid>,
+    /// The name of the current branch, or `None` if `HEAD` is detached.
+    pub branch_name: Option<String>,
+    /// The name of the upstream branch, if one is set.
+    pub upstream_name: Option<String>,
+    /// The number of commits that the current branch is ahead of and behind
+    /// its upstream branch, respectively. This is `None` if there is no
+    /// upstream branch or if it doesn't exist.
+    pub ahead_behind: Option<(usize, usize)>,
+}
+
+impl StatusHeader {
+    /// Update this header with the information from a single header line,
+    /// without its trailing NUL terminator. Unrecognized headers are ignored.
+    #[instrument]
+    pub fn parse_line(&mut self, line: &[u8]) -> eyre::Result<()> {
+        let line = std::str::from_utf8(line)
+            .map_err(|err| eyre::eyre!("unable to decode status header line: {:?}", err))?;
+        let (key, value) = line
+            .strip_prefix("# ")
+            .and_then(|line| line.split_once(' '))
+            .ok_or_else(|| eyre::eyre!("unable to parse status header line: {:?}", line))?;
+        match key {
+            "branch.oid" => {
+                self.head_oid = match value {
+                    "(initial)" => None,
+                    value => Some(value.parse()?),
+                };
+            }
+            "branch.head" => {
+                self.branch_name = match value {
+                    "(detached)" => None,
+                    value => Some(value.to_owned()),
+                };
+            }
+            "branch.upstream" => {
+                self.upstream_name = Some(value.to_owned());
+            }
+            "branch.ab" => {
+                let (ahead, behind) = value
+                    .split_once(' ')
+                    .and_then(|(ahead, behind)| {
+                        Some((ahead.strip_prefix('+')?, behind.strip_prefix('-')?))
+                    })
+                    .ok_or_else(|| eyre::eyre!("unable to parse branch.ab: {:?}", value))?;
+                self.ahead_behind = Some((ahead.parse()?, behind.parse()?));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
 impl TryFrom<&[u8]> for StatusEntry {
     type Error = eyre::Error;
 
@@ -315,6 +426,52 @@ mod tests {
                 working_copy_file_mode: FileMode::BlobExecutable,
             }
         );
+
+        assert_eq!(
+            StatusEntry::try_from(
+                "1 .T N... 100644 100644 120000 51fcbe2362663a19d132767b69c2c7829023f3da 51fcbe2362663a19d132767b69c2c7829023f3da repo.rs".as_bytes(),
+            ).unwrap(),
+            StatusEntry {
+                index_status: FileStatus::Unmodified,
+                working_copy_status: FileStatus::TypeChanged,
+                path: "repo.rs".into(),
+                orig_path: None,
+                working_copy_file_mode: FileMode::Link,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_status_header() -> eyre::Result<()> {
+        let mut header = StatusHeader::default();
+        for line in [
+            "# branch.oid 62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+            "# branch.head master",
+            "# branch.upstream origin/master",
+            "# branch.ab +1 -2",
+            "# stash 3",
+        ] {
+            header.parse_line(line.as_bytes())?;
+        }
+        assert_eq!(
+            header,
+            StatusHeader {
+                head_oid: Some("62fc20d2a290daea0d52bdc2ed2ad4be6491010e".parse()?),
+                branch_name: Some("master".to_string()),
+                upstream_name: Some("origin/master".to_string()),
+                ahead_behind: Some((1, 2)),
+            }
+        );
+
+        let mut header = StatusHeader::default();
+        header.parse_line(b"# branch.oid (initial)")?;
+        header.parse_line(b"# branch.head (detached)")?;
+        assert_eq!(header, StatusHeader::default());
+
+        assert!(header.parse_line(b"# branch.ab 1 2").is_err());
+        assert!(header.parse_line(b"branch.head master").is_err());
+
+        Ok(())
     }
 
     #[test]
@@ -441,7 +598,7 @@ mod tests {
         WorkingCopySnapshot {
             base_commit: Commit {
                 inner: Commit {
-                    id: e378f780ff4d810e12d36e89334d8971d7add0d1,
+                    id: 2f8be3a55bd58854a5871d7260abc37ab5d1199c,
                     summary: "branchless: automated working copy snapshot",
                 },
             },
@@ -460,7 +617,7 @@ mod tests {
             ),
             commit_unstaged: Commit {
                 inner: Commit {
-                    id: 8f4827eba90e1ce5bbd0d76fc29d587c8ad9135e,
+                    id: 329d438eaf36089efa9a49a3942a10985c037dce,
                     summary: "branchless: working copy snapshot data: 4 unstaged changes",
                 },
             },
@@ -493,4 +650,175 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_get_status_detect_renames() -> eyre::Result<()> {
+        let git = make_git()?;
+        let git_run_info = git.get_git_run_info();
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["mv", "test1.txt", "renamed.txt"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let get_status = |detect_renames: bool| -> eyre::Result<Vec<StatusEntry>> {
+            let (_snapshot, status) = repo.get_status_with_options(
+                &effects,
+                &git_run_info,
+                &repo.get_index()?,
+                &repo.get_head_info()?,
+                None,
+                &StatusOptions {
+                    detect_renames,
+                    ..Default::default()
+                },
+            )?;
+            Ok(status)
+        };
+
+        let renamed_entry = StatusEntry {
+            index_status: FileStatus::Renamed,
+            working_copy_status: FileStatus::Unmodified,
+            working_copy_file_mode: FileMode::Blob,
+            path: "renamed.txt".into(),
+            orig_path: Some("test1.txt".into()),
+        };
+        let split_entries = vec![
+            StatusEntry {
+                index_status: FileStatus::Added,
+                working_copy_status: FileStatus::Unmodified,
+                working_copy_file_mode: FileMode::Blob,
+                path: "renamed.txt".into(),
+                orig_path: None,
+            },
+            StatusEntry {
+                index_status: FileStatus::Deleted,
+                working_copy_status: FileStatus::Unmodified,
+                working_copy_file_mode: FileMode::Unreadable,
+                path: "test1.txt".into(),
+                orig_path: None,
+            },
+        ];
+
+        // The option takes precedence over the user's configuration in both
+        // directions.
+        for config_value in ["true", "false"] {
+            git.run(&["config", "status.renames", config_value])?;
+            assert_eq!(get_status(true)?, vec![renamed_entry.clone()]);
+            assert_eq!(get_status(false)?, split_entries);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_status_ignore_submodule_dirty() -> eyre::Result<()> {
+        let git = make_git()?;
+        let git_run_info = git.get_git_run_info();
+        git.init_repo()?;
+
+        git.run(&["init", "--quiet", "submodule"])?;
+        git.run(&["-C", "submodule", "config", "user.name", "Testy McTestface"])?;
+        git.run(&[
+            "-C",
+            "submodule",
+            "config",
+            "user.email",
+            "test@example.com",
+        ])?;
+        std::fs::write(git.repo_path.join("submodule").join("foo.txt"), "foo\n")?;
+        git.run(&["-C", "submodule", "add", "foo.txt"])?;
+        git.run(&["-C", "submodule", "commit", "-m", "create foo.txt"])?;
+        git.run(&["add", "submodule"])?;
+        git.run(&["commit", "-m", "add submodule"])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let get_changed_paths = |options: &StatusOptions| -> eyre::Result<Vec<PathBuf>> {
+            let (_snapshot, status) = repo.get_status_with_options(
+                &effects,
+                &git_run_info,
+                &repo.get_index()?,
+                &repo.get_head_info()?,
+                None,
+                options,
+            )?;
+            Ok(status.into_iter().map(|entry| entry.path).collect())
+        };
+        let default_options = StatusOptions::default();
+        let ignore_dirty_options = StatusOptions {
+            ignore_submodule_dirty: true,
+            ..Default::default()
+        };
+
+        // Dirty the submodule's working copy without changing its commit.
+        std::fs::write(git.repo_path.join("submodule").join("foo.txt"), "bar\n")?;
+        assert_eq!(
+            get_changed_paths(&default_options)?,
+            vec![PathBuf::from("submodule")]
+        );
+        assert!(repo.has_changed_files(&effects, &git_run_info, &default_options)?);
+        assert_eq!(
+            get_changed_paths(&ignore_dirty_options)?,
+            Vec::<PathBuf>::new()
+        );
+        assert!(!repo.has_changed_files(&effects, &git_run_info, &ignore_dirty_options)?);
+
+        // Changes to the commit which the submodule points to are still
+        // reported.
+        git.run(&["-C", "submodule", "commit", "-a", "-m", "update foo.txt"])?;
+        assert_eq!(
+            get_changed_paths(&ignore_dirty_options)?,
+            vec![PathBuf::from("submodule")]
+        );
+        assert!(repo.has_changed_files(&effects, &git_run_info, &ignore_dirty_options)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_status_with_header() -> eyre::Result<()> {
+        let git = make_git()?;
+        let git_run_info = git.get_git_run_info();
+        git.init_repo()?;
+        git.run(&["branch", "upstream"])?;
+        git.run(&["branch", "--set-upstream-to", "upstream"])?;
+        let test1_oid = git.commit_file("test1", 1)?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let get_header = || -> eyre::Result<StatusHeader> {
+            let (_snapshot, _status, header) = repo.get_status_with_header(
+                &effects,
+                &git_run_info,
+                &repo.get_index()?,
+                &repo.get_head_info()?,
+                None,
+            )?;
+            Ok(header)
+        };
+
+        assert_eq!(
+            get_header()?,
+            StatusHeader {
+                head_oid: Some(test1_oid),
+                branch_name: Some("master".to_string()),
+                upstream_name: Some("upstream".to_string()),
+                ahead_behind: Some((1, 0)),
+            }
+        );
+
+        git.run(&["checkout", "--detach"])?;
+        assert_eq!(
+            get_header()?,
+            StatusHeader {
+                head_oid: Some(test1_oid),
+                branch_name: None,
+                upstream_name: None,
+                ahead_behind: None,
+            }
+        );
+
+        Ok(())
+    }
 }
@@ -116,6 +116,23 @@ impl Tree<'_> {
         self.get_path(path)
             .map(|maybe_entry| maybe_entry.map(|entry| entry.inner.id().into()))
     }
+
+    /// Iterate over the entries directly contained in this tree, yielding the
+    /// name, OID, and file mode of each entry. This doesn't recurse into
+    /// subtrees; entries with `FileMode::Tree` can be looked up with
+    /// `Repo::find_tree` to walk further.
+    ///
+    /// Submodule entries are yielded with `FileMode::Commit`. Their OID refers
+    /// to a commit in the submodule's repository, which is typically not
+    /// present in this repository.
+    pub fn entries(&self) -> impl Iterator<Item = (PathBuf, NonZeroOid, FileMode)> + '_ {
+        self.inner.iter().map(|entry| {
+            let name = entry.name_bytes().to_vec().into_path_buf_lossy();
+            let oid = make_non_zero_oid(entry.id());
+            let file_mode = FileMode::from(entry.filemode_raw());
+            (name, oid, file_mode)
+        })
+    }
 }
 
 /// This function is a hot code path. Do not annotate with `#[instrument]`, and
@@ -12,7 +12,7 @@ use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 use crate::core::config::env_vars::{get_git_exec_path, get_path_to_git, TEST_GIT};
-use crate::git::{GitRunInfo, GitVersion, NonZeroOid, Repo};
+use crate::git::{GitRunInfo, GitVersion, NonZeroOid, Repo, ResolvedReferenceInfo};
 use crate::util::get_sh;
 
 use eyre::Context;
@@ -79,6 +79,20 @@ pub struct GitRunOptions {
     pub env: HashMap<String, String>,
 }
 
+/// The expected state of `HEAD`, for use with `Git::assert_head`.
+#[derive(Clone, Copy, Debug)]
+pub enum ExpectedHead<'a> {
+    /// `HEAD` points directly to the given commit.
+    Detached(NonZeroOid),
+
+    /// `HEAD` points to the given local branch (without the `refs/heads/`
+    /// prefix), which points to the given commit.
+    Branch(&'a str, NonZeroOid),
+
+    /// `HEAD` points to the given local branch, which doesn't exist yet.
+    Unborn(&'a str),
+}
+
 impl Git {
     /// Constructor.
     pub fn new(path_to_git: PathBuf, repo_path: PathBuf, git_exec_path: PathBuf) -> Self {
@@ -421,6 +435,31 @@ stderr:
         Ok(())
     }
 
+    /// Create `num_refs` branches named `refs/heads/ref-N`, all pointing to
+    /// the current `HEAD` commit, and pack them into `packed-refs`. This is
+    /// useful for testing and benchmarking operations which scale with the
+    /// number of references in the repository.
+    #[instrument]
+    pub fn populate_refs(&self, num_refs: usize) -> eyre::Result<()> {
+        let head_oid = self
+            .get_repo()?
+            .get_head_info()?
+            .oid
+            .ok_or_else(|| eyre::eyre!("Cannot populate references with an unborn HEAD"))?;
+        let input = (0..num_refs)
+            .map(|i| format!("create refs/heads/ref-{i} {head_oid}\n"))
+            .collect::<String>();
+        self.run_with_options(
+            &["update-ref", "--stdin"],
+            &GitRunOptions {
+                input: Some(input),
+                ..Default::default()
+            },
+        )?;
+        self.run(&["pack-refs", "--all"])?;
+        Ok(())
+    }
+
     /// Get a `Repo` object for this repository.
     #[instrument]
     pub fn get_repo(&self) -> eyre::Result<Repo> {
@@ -428,6 +467,29 @@ stderr:
         Ok(repo)
     }
 
+    /// Assert that `HEAD` is in the expected state, as reported by
+    /// `Repo::get_head_info`.
+    #[track_caller]
+    pub fn assert_head(&self, expected: ExpectedHead) -> eyre::Result<()> {
+        let expected = match expected {
+            ExpectedHead::Detached(oid) => ResolvedReferenceInfo {
+                oid: Some(oid),
+                reference_name: None,
+            },
+            ExpectedHead::Branch(branch_name, oid) => ResolvedReferenceInfo {
+                oid: Some(oid),
+                reference_name: Some(format!("refs/heads/{branch_name}").into()),
+            },
+            ExpectedHead::Unborn(branch_name) => ResolvedReferenceInfo {
+                oid: None,
+                reference_name: Some(format!("refs/heads/{branch_name}").into()),
+            },
+        };
+        let head_info = self.get_repo()?.get_head_info()?;
+        assert_eq!(head_info, expected);
+        Ok(())
+    }
+
     /// Get the version of the Git executable.
     #[instrument]
     pub fn get_version(&self) -> eyre::Result<GitVersion> {
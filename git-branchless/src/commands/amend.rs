@@ -15,12 +15,16 @@ use tracing::instrument;
 
 use crate::commands::restack;
 use crate::opts::{MoveOptions, Revset};
-use lib::core::config::get_restack_preserve_timestamps;
+use lib::core::config::{
+    get_commit_signing, get_ignore_submodule_dirty, get_restack_preserve_timestamps,
+};
 use lib::core::effects::Effects;
 use lib::core::eventlog::{Event, EventLogDb};
 use lib::core::formatting::Pluralize;
 use lib::core::gc::mark_commit_reachable;
-use lib::git::{AmendFastOptions, GitRunInfo, MaybeZeroOid, Repo, ResolvedReferenceInfo};
+use lib::git::{
+    AmendFastOptions, GitRunInfo, MaybeZeroOid, Repo, ResolvedReferenceInfo, StatusOptions,
+};
 
 /// Amends the existing HEAD commit.
 #[instrument]
@@ -58,8 +62,17 @@ pub fn amend(
     }
 
     let event_tx_id = event_log_db.make_transaction_id(now, "amend")?;
-    let (snapshot, status) =
-        repo.get_status(effects, git_run_info, &index, &head_info, Some(event_tx_id))?;
+    let (snapshot, status) = repo.get_status_with_options(
+        effects,
+        git_run_info,
+        &index,
+        &head_info,
+        Some(event_tx_id),
+        &StatusOptions {
+            ignore_submodule_dirty: get_ignore_submodule_dirty(&repo)?,
+            ..Default::default()
+        },
+    )?;
     {
         let ResolvedReferenceInfo {
             oid,
@@ -106,24 +119,14 @@ pub fn amend(
         return Ok(ExitCode(0));
     }
 
-    let amended_tree = repo.amend_fast(&head_commit, &opts)?;
-
-    let (author, committer) = (head_commit.get_author(), head_commit.get_committer());
-    let (author, committer) = if get_restack_preserve_timestamps(&repo)? {
-        (author, committer)
-    } else {
-        (
-            author.update_timestamp(now)?,
-            committer.update_timestamp(now)?,
-        )
-    };
-
-    let amended_commit_oid = head_commit.amend_commit(
-        Some("HEAD"),
-        Some(&author),
-        Some(&committer),
+    let amended_commit_oid = repo.amend_fast_commit(
+        &head_commit,
+        &opts,
         None,
-        Some(&amended_tree),
+        Some("HEAD"),
+        get_commit_signing(&repo)?.as_ref(),
+        get_restack_preserve_timestamps(&repo)?,
+        now,
     )?;
     mark_commit_reachable(&repo, amended_commit_oid)
         .wrap_err("Marking commit as reachable for GC purposes.")?;
@@ -146,12 +146,12 @@ fn describe_event_cursor(
         &graph,
         references_snapshot.head_oid,
         &mut [
-            &mut CommitOidDescriptor::new(true)?,
+            &mut CommitOidDescriptor::new(true, None)?,
             &mut RelativeTimeDescriptor::new(repo, now)?,
             &mut ObsolescenceExplanationDescriptor::new(event_replayer, event_cursor)?,
             &mut BranchesDescriptor::new(repo, head_info, references_snapshot, redactor)?,
             &mut DifferentialRevisionDescriptor::new(repo, redactor)?,
-            &mut CommitMessageDescriptor::new(redactor)?,
+            &mut CommitMessageDescriptor::new(redactor, false)?,
         ],
     )?;
     let graph_lines = graph_lines
@@ -0,0 +1,97 @@
+//! Display the events recorded by `git-branchless`, analogous to `git reflog`
+//! but covering all of the operations that `git-branchless` has observed.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::printable_styled_string;
+use lib::core::node_descriptors::RelativeTimeDescriptor;
+use lib::git::{GitRunInfo, Repo};
+use lib::util::ExitCode;
+use tracing::instrument;
+
+use crate::commands::undo::describe_events_numbered;
+
+/// Render the most recent transactions in the event log, from most to least
+/// recent.
+///
+/// If `event_id` is provided, only transactions at or before that event are
+/// rendered. As with `git smartlog --event-id`, a negative value is treated as
+/// an offset from the current event.
+#[instrument]
+pub fn event_log(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    event_id: Option<isize>,
+    max_count: usize,
+) -> eyre::Result<ExitCode> {
+    let now = SystemTime::now();
+    let glyphs = effects.get_glyphs();
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = {
+        let default_cursor = event_replayer.make_default_cursor();
+        match event_id {
+            None => default_cursor,
+            Some(event_id) => match event_id.cmp(&0) {
+                Ordering::Less => event_replayer.advance_cursor(default_cursor, event_id),
+                Ordering::Equal | Ordering::Greater => event_replayer.make_cursor(event_id),
+            },
+        }
+    };
+    let relative_time_provider = RelativeTimeDescriptor::new(&repo, now)?;
+
+    let mut is_first = true;
+    for i in 0..max_count {
+        let event_cursor =
+            event_replayer.advance_cursor_by_transaction(event_cursor, -isize::try_from(i)?);
+        let (event_id, events) = match event_replayer.get_tx_events_before_cursor(event_cursor) {
+            Some(tx_events) => tx_events,
+            None => break,
+        };
+
+        let event_tx_id = events[0].get_event_tx_id();
+        let transaction_message = event_log_db.get_transaction_message(event_tx_id)?;
+        let relative_time = if relative_time_provider.is_enabled() {
+            format!(
+                " ({} ago)",
+                RelativeTimeDescriptor::describe_time_delta(now, events[0].get_timestamp())?
+            )
+        } else {
+            String::new()
+        };
+
+        if !is_first {
+            writeln!(effects.get_output_stream())?;
+        }
+        is_first = false;
+        writeln!(
+            effects.get_output_stream(),
+            "Transaction {} (event {event_id}){relative_time}: {transaction_message}",
+            event_tx_id.to_string(),
+        )?;
+        for line in describe_events_numbered(glyphs, &repo, events)? {
+            // Some event descriptions end with a blank line, which is only
+            // useful when rendered in `git undo -i`.
+            let line = printable_styled_string(glyphs, line)?;
+            if !line.trim().is_empty() {
+                writeln!(effects.get_output_stream(), "{line}")?;
+            }
+        }
+    }
+
+    if is_first {
+        writeln!(
+            effects.get_output_stream(),
+            "There are no previous available events."
+        )?;
+    }
+
+    Ok(ExitCode(0))
+}
@@ -12,6 +12,7 @@ use tracing::{instrument, warn};
 use crate::opts::write_man_pages;
 use lib::core::config::{get_core_hooks_path, get_default_branch_name};
 use lib::core::effects::Effects;
+use lib::core::repo_ext::RepoExt;
 use lib::git::{BranchType, Config, ConfigRead, ConfigWrite, GitRunInfo, GitVersion, Repo};
 
 pub const ALL_HOOKS: &[(&str, &str)] = &[
@@ -560,6 +561,9 @@ pub fn init(
     let default_config = Config::open_default()?;
     let readonly_config = repo.get_readonly_config()?;
     let mut config = create_isolated_config(effects, &repo, readonly_config.into_config())?;
+    for migration in repo.migrate_config()? {
+        writeln!(effects.get_output_stream(), "{migration}")?;
+    }
 
     set_configs(&mut in_, effects, &repo, &mut config, main_branch_name)?;
     install_hooks(effects, &repo)?;
@@ -2,6 +2,7 @@
 
 mod amend;
 mod bug_report;
+mod event_log;
 mod gc;
 mod hide;
 mod hooks;
@@ -23,10 +24,12 @@ mod wrap;
 use std::any::Any;
 use std::convert::TryInto;
 use std::ffi::OsString;
+use std::fmt::Write;
 use std::path::PathBuf;
 use std::time::SystemTime;
 
 use clap::Parser;
+use console::style;
 use eyre::Context;
 use itertools::Itertools;
 use lib::core::rewrite::MergeConflictRemediation;
@@ -39,14 +42,17 @@ use tracing_subscriber::EnvFilter;
 
 use crate::opts::ColorSetting;
 use crate::opts::Command;
+use crate::opts::GlyphsSetting;
 use crate::opts::Opts;
 use crate::opts::SnapshotSubcommand;
 use crate::opts::WrappedCommand;
 use lib::core::config::env_vars::get_path_to_git;
+use lib::core::config::get_glyphs_ascii;
 use lib::core::effects::Effects;
 use lib::core::formatting::Glyphs;
 use lib::git::GitRunInfo;
 use lib::git::NonZeroOid;
+use lib::git::Repo;
 
 use self::reword::InitialCommitMessages;
 use self::smartlog::SmartlogOptions;
@@ -94,6 +100,7 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
         working_directory,
         command,
         color,
+        glyphs,
     } = Opts::parse_from(args);
     if let Some(working_directory) = working_directory {
         std::env::set_current_dir(&working_directory).wrap_err_with(|| {
@@ -117,7 +124,37 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
         Some(ColorSetting::Never) => Glyphs::text(),
         Some(ColorSetting::Auto) | None => Glyphs::detect(),
     };
+    let (use_ascii_glyphs, glyphs_config_error) = match glyphs {
+        Some(GlyphsSetting::Ascii) => (true, None),
+        Some(GlyphsSetting::Auto) => (false, None),
+        None => match Repo::from_dir(&git_run_info.working_directory) {
+            // An invalid value shouldn't prevent every command from running,
+            // so fall back to the default glyphs and warn about it below.
+            Ok(repo) => match get_glyphs_ascii(&repo) {
+                Ok(use_ascii_glyphs) => (use_ascii_glyphs, None),
+                Err(err) => (false, Some(err)),
+            },
+            // Not in a repository; the command will report the error itself.
+            Err(_) => (false, None),
+        },
+    };
+    let color = if use_ascii_glyphs {
+        Glyphs {
+            should_write_ansi_escape_codes: color.should_write_ansi_escape_codes,
+            ..Glyphs::ascii()
+        }
+    } else {
+        color
+    };
     let effects = Effects::new(color);
+    if let Some(err) = glyphs_config_error {
+        writeln!(
+            effects.get_error_stream(),
+            "{}: {}",
+            style("Warning").yellow().bold(),
+            err
+        )?;
+    }
 
     let ExitCode(exit_code) = match command {
         Command::Amend { move_options } => amend::amend(&effects, &git_run_info, &move_options)?,
@@ -128,6 +165,11 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
             navigation::checkout(&effects, &git_run_info, &checkout_options)?
         }
 
+        Command::EventLog {
+            event_id,
+            max_count,
+        } => event_log::event_log(&effects, &git_run_info, event_id, max_count)?,
+
         Command::Gc | Command::HookPreAutoGc => {
             gc::gc(&effects)?;
             ExitCode(0)
@@ -294,6 +336,11 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
             show_hidden_commits,
             event_id,
             revset,
+            show_stats,
+            format,
+            max_commits,
+            author,
+            show_remote,
         } => smartlog::smartlog(
             &effects,
             &git_run_info,
@@ -301,6 +348,11 @@ fn do_main_and_drop_locals() -> eyre::Result<i32> {
                 show_hidden_commits,
                 event_id,
                 revset,
+                show_stats,
+                format,
+                max_commits,
+                author,
+                show_remote,
             },
         )?,
 
@@ -393,7 +445,21 @@ fn install_tracing() -> eyre::Result<impl Drop> {
             Ok(_) | Err(_) => false,
         };
 
-        let filename = match std::env::var("RUST_PROFILE") {
+        // `BRANCHLESS_TRACE` names the exact file to write the trace for
+        // this command to, so that users can easily attach it to bug reports.
+        // Nested invocations write to a separate file with a suffix.
+        let branchless_trace_filename = match std::env::var("BRANCHLESS_TRACE") {
+            Ok(value) if !value.is_empty() => {
+                if nesting_level == 0 {
+                    Some(value)
+                } else {
+                    Some(format!("{}-{}", value, nesting_level))
+                }
+            }
+            Ok(_) | Err(_) => None,
+        };
+
+        let rust_profile_filename = match std::env::var("RUST_PROFILE") {
             Ok(value) if value == "1" || value == "true" => {
                 let filename = format!(
                     "trace-{}.json-{}",
@@ -407,6 +473,7 @@ fn install_tracing() -> eyre::Result<impl Drop> {
             Ok(value) if !value.is_empty() => Some(format!("{}-{}", value, nesting_level)),
             Ok(_) | Err(_) => None,
         };
+        let filename = branchless_trace_filename.or(rust_profile_filename);
 
         match filename {
             Some(filename) => {
@@ -17,9 +17,7 @@ use tracing::instrument;
 
 use crate::opts::{MoveOptions, Revset};
 use crate::revset::resolve_commits;
-use lib::core::config::{
-    get_hint_enabled, get_restack_preserve_timestamps, print_hint_suppression_notice, Hint,
-};
+use lib::core::config::{print_hint_suppression_notice, Hint};
 use lib::core::dag::{commit_set_to_vec_unsorted, sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
 use lib::core::eventlog::{EventLogDb, EventReplayer};
@@ -74,6 +72,7 @@ pub fn r#move(
     let should_sources_default_to_head = !sources_provided && !bases_provided && !exacts_provided;
 
     let repo = Repo::from_current_dir()?;
+    let branchless_config = repo.load_branchless_config()?;
     let head_oid = repo.get_head_info()?.oid;
 
     let dest = match dest {
@@ -202,7 +201,7 @@ pub fn r#move(
     let source_oids = source_oids.union(&base_oids);
 
     if let Some(head_oid) = head_oid {
-        if get_hint_enabled(&repo, Hint::MoveImplicitHeadArgument)? {
+        if branchless_config.is_hint_enabled(Hint::MoveImplicitHeadArgument) {
             let should_warn_base = !sources_provided
                 && bases_provided
                 && base_oids.contains(&head_oid.into())?
@@ -461,7 +460,7 @@ pub fn r#move(
             let options = ExecuteRebasePlanOptions {
                 now,
                 event_tx_id,
-                preserve_timestamps: get_restack_preserve_timestamps(&repo)?,
+                preserve_timestamps: branchless_config.restack_preserve_timestamps,
                 force_in_memory,
                 force_on_disk,
                 resolve_merge_conflicts,
@@ -95,7 +95,7 @@ fn advance(
         }
     };
 
-    let public_commits = dag.query().ancestors(dag.main_branch_commit.clone())?;
+    let public_commits = dag.query_public_commits()?;
 
     let glyphs = effects.get_glyphs();
     let mut current_oid = current_oid;
@@ -380,7 +380,7 @@ pub fn traverse_commits(
         &repo,
         &dag,
         &mut [
-            &mut CommitOidDescriptor::new(true)?,
+            &mut CommitOidDescriptor::new(true, None)?,
             &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
             &mut BranchesDescriptor::new(
                 &repo,
@@ -389,7 +389,7 @@ pub fn traverse_commits(
                 &Redactor::Disabled,
             )?,
             &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
-            &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+            &mut CommitMessageDescriptor::new(&Redactor::Disabled, false)?,
         ],
         head_oid,
         command,
@@ -574,7 +574,7 @@ pub fn checkout(
                 initial_query,
                 graph.get_commits(),
                 &mut [
-                    &mut CommitOidDescriptor::new(true)?,
+                    &mut CommitOidDescriptor::new(true, None)?,
                     &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
                     &mut BranchesDescriptor::new(
                         &repo,
@@ -583,7 +583,7 @@ pub fn checkout(
                         &Redactor::Disabled,
                     )?,
                     &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
-                    &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+                    &mut CommitMessageDescriptor::new(&Redactor::Disabled, false)?,
                 ],
             )? {
                 Some(oid) => Some(CheckoutTarget::Oid(oid)),
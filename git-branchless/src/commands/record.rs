@@ -9,11 +9,14 @@ use cursive_buffered_backend::BufferedBackend;
 use git_record::Recorder;
 use git_record::{RecordError, RecordState};
 use itertools::Itertools;
+use lib::core::config::BranchlessConfig;
 use lib::core::effects::{Effects, OperationType};
 use lib::core::eventlog::{EventLogDb, EventTransactionId};
+use lib::core::repo_ext::RepoExt;
 use lib::git::{
-    process_diff_for_record, update_index, CategorizedReferenceName, FileMode, GitRunInfo, Repo,
-    ResolvedReferenceInfo, Stage, UpdateIndexCommand, WorkingCopyChangesType, WorkingCopySnapshot,
+    make_change_id, process_diff_for_record, update_index, CategorizedReferenceName, DiffOptions,
+    FileMode, GitRunInfo, Repo, ResolvedReferenceInfo, Stage, StatusOptions, UpdateIndexCommand,
+    WorkingCopyChangesType, WorkingCopySnapshot,
 };
 use lib::util::ExitCode;
 
@@ -25,6 +28,7 @@ pub fn record(
     detach: bool,
 ) -> eyre::Result<ExitCode> {
     let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let branchless_config = repo.load_branchless_config()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
     let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "record")?;
@@ -32,8 +36,17 @@ pub fn record(
     let (snapshot, working_copy_changes_type) = {
         let head_info = repo.get_head_info()?;
         let index = repo.get_index()?;
-        let (snapshot, _status) =
-            repo.get_status(effects, git_run_info, &index, &head_info, Some(event_tx_id))?;
+        let (snapshot, _status) = repo.get_status_with_options(
+            effects,
+            git_run_info,
+            &index,
+            &head_info,
+            Some(event_tx_id),
+            &StatusOptions {
+                ignore_submodule_dirty: branchless_config.ignore_submodule_dirty,
+                ..Default::default()
+            },
+        )?;
 
         let working_copy_changes_type = snapshot.get_working_copy_changes_type()?;
         match working_copy_changes_type {
@@ -60,6 +73,8 @@ pub fn record(
         (snapshot, working_copy_changes_type)
     };
 
+    let change_id_trailer =
+        get_change_id_trailer(effects, git_run_info, &repo, &branchless_config, &snapshot)?;
     let commit_exit_code = if interactive {
         if working_copy_changes_type == WorkingCopyChangesType::Staged {
             writeln!(
@@ -76,9 +91,11 @@ pub fn record(
                 effects,
                 git_run_info,
                 &repo,
+                &branchless_config,
                 &snapshot,
                 event_tx_id,
                 message.as_deref(),
+                change_id_trailer.as_deref(),
             )?
         }
     } else {
@@ -90,6 +107,9 @@ pub fn record(
             if working_copy_changes_type == WorkingCopyChangesType::Unstaged {
                 args.push("--all");
             }
+            if let Some(change_id_trailer) = &change_id_trailer {
+                args.extend(["--trailer", change_id_trailer]);
+            }
             args
         };
         git_run_info.run_direct_no_wrapping(Some(event_tx_id), &args)?
@@ -142,13 +162,48 @@ pub fn record(
     Ok(ExitCode(0))
 }
 
+/// If `branchless.commit.stampChangeId` is set, generate a trailer containing
+/// a new change ID to add to the commit being recorded.
+fn get_change_id_trailer(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    branchless_config: &BranchlessConfig,
+    snapshot: &WorkingCopySnapshot,
+) -> eyre::Result<Option<String>> {
+    if !branchless_config.commit_stamp_change_id {
+        return Ok(None);
+    }
+
+    if !git_run_info
+        .get_version(repo)?
+        .supports_commit_trailer_option()
+    {
+        writeln!(
+            effects.get_error_stream(),
+            "Not adding a change ID to the commit, as this requires Git v2.32 or later."
+        )?;
+        return Ok(None);
+    }
+
+    // The snapshot commit is unique to the current state of the working copy
+    // and the time at which it was taken.
+    let change_id = make_change_id(snapshot.base_commit.get_oid().as_bytes())?;
+    Ok(Some(format!(
+        "{}: {}",
+        branchless_config.commit_change_id_trailer, change_id
+    )))
+}
+
 fn record_interactive(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     repo: &Repo,
+    branchless_config: &BranchlessConfig,
     snapshot: &WorkingCopySnapshot,
     event_tx_id: EventTransactionId,
     message: Option<&str>,
+    change_id_trailer: Option<&str>,
 ) -> eyre::Result<ExitCode> {
     let file_states = {
         let (effects, _progress) = effects.start_operation(OperationType::CalculateDiff);
@@ -158,8 +213,12 @@ fn record_interactive(
             &effects,
             Some(&old_tree),
             &new_tree,
-            // We manually add context to the git-record output, so suppress the context lines here.
-            0,
+            &DiffOptions {
+                // We manually add context to the git-record output, so suppress the context lines here.
+                num_context_lines: 0,
+                max_file_size: branchless_config.diff_max_file_size,
+                ..Default::default()
+            },
         )?;
         process_diff_for_record(repo, &diff)?
     };
@@ -213,6 +272,9 @@ fn record_interactive(
         if let Some(message) = message {
             args.extend(["--message", message]);
         }
+        if let Some(change_id_trailer) = change_id_trailer {
+            args.extend(["--trailer", change_id_trailer]);
+        }
         args
     };
     git_run_info.run_direct_no_wrapping(Some(event_tx_id), &args)
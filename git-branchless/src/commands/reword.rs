@@ -19,7 +19,8 @@ use eyre::Context;
 use tracing::{instrument, warn};
 
 use lib::core::config::{
-    get_comment_char, get_commit_template, get_editor, get_restack_preserve_timestamps,
+    get_comment_char, get_commit_signing, get_commit_template, get_editor,
+    get_restack_preserve_timestamps,
 };
 use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
@@ -232,11 +233,19 @@ pub fn reword(
         let repo_pool = RepoResource::new_pool(&repo)?;
         let mut builder = RebasePlanBuilder::new(&dag, permissions);
 
+        let commit_signing = get_commit_signing(&repo)?;
         for commit in commits.iter() {
             let message = messages.get(&commit.get_oid()).unwrap();
             // This looks funny, but just means "leave everything but the message as is"
-            let replacement_oid =
-                commit.amend_commit(None, None, None, Some(message.as_str()), None)?;
+            let replacement_oid = repo.amend_signed_commit(
+                commit_signing.as_ref(),
+                commit,
+                None,
+                None,
+                None,
+                Some(message.as_str()),
+                None,
+            )?;
             builder.move_subtree(commit.get_oid(), commit.get_parent_oids())?;
             builder.replace_commit(commit.get_oid(), replacement_oid)?;
         }
@@ -360,8 +369,6 @@ fn prepare_messages(
     commits: &[Commit],
     edit_message_fn: impl Fn(&str) -> eyre::Result<String>,
 ) -> eyre::Result<PrepareMessagesResult> {
-    let comment_char = get_comment_char(repo)?;
-
     let (message, load_editor, discard_messages) = match messages {
         InitialCommitMessages::Discard => {
             (get_commit_template(repo)?.unwrap_or_default(), true, true)
@@ -391,6 +398,15 @@ fn prepare_messages(
         return Ok(PrepareMessagesResult::Succeeded { messages });
     };
 
+    let comment_char = {
+        let mut existing_messages = message.clone();
+        for commit in commits {
+            existing_messages.push('\n');
+            existing_messages.push_str(&commit.get_message_raw()?.to_str_lossy());
+        }
+        get_comment_char(repo, &existing_messages)?
+    };
+
     let possible_template_message = message.trim();
     let possible_template_message = if possible_template_message.is_empty() {
         String::from("\n")
@@ -664,7 +680,7 @@ fn render_status_report(
                     &NodeObject::Commit {
                         commit: original_commit.clone(),
                     },
-                    &mut [&mut CommitOidDescriptor::new(true)?],
+                    &mut [&mut CommitOidDescriptor::new(true, None)?],
                 )?
             )?,
             printable_styled_string(&glyphs, replacement_commit.friendly_describe(&glyphs)?)?
@@ -785,6 +801,50 @@ This is a template!
         Ok(())
     }
 
+    #[test]
+    fn test_reword_comment_char_auto() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.run(&["config", "core.commentChar", "auto"])?;
+        let repo = git.get_repo()?;
+
+        git.write_file("test1", "contents")?;
+        git.run(&["add", "test1.txt"])?;
+        git.run(&[
+            "commit",
+            "--cleanup=verbatim",
+            "-m",
+            "create test1.txt\n\n#include <test1.txt>",
+        ])?;
+        let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+
+        let result = prepare_messages(
+            &repo,
+            InitialCommitMessages::Messages([].to_vec()),
+            &[head_commit],
+            |message| {
+                insta::assert_snapshot!(message.trim(), @r###"
+                create test1.txt
+
+                #include <test1.txt>
+
+                ; Rewording: Please enter the commit message to apply to this 1 commit. Lines
+                ; starting with ';' will be ignored, and an empty message aborts rewording.
+                "###);
+                Ok(message.replace("create test1.txt", "update test1.txt"))
+            },
+        )?;
+        insta::assert_debug_snapshot!(result, @r###"
+        Succeeded {
+            messages: {
+                NonZeroOid(dfe1b8bf4354d54f58f32ec1723dddbb19ff804a): "update test1.txt\n\n#include <test1.txt>\n",
+            },
+        }
+        "###);
+
+        Ok(())
+    }
+
     #[test]
     fn test_reword_parses_bulk_edit_message() -> eyre::Result<()> {
         let git = make_git()?;
@@ -10,7 +10,7 @@ use std::time::SystemTime;
 
 use console::style;
 use eden_dag::DagAlgorithm;
-use lib::core::config::{get_hint_enabled, print_hint_suppression_notice, Hint};
+use lib::core::config::{get_core_abbrev, print_hint_suppression_notice, Hint};
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::find_rewrite_target;
 use lib::util::ExitCode;
@@ -21,31 +21,39 @@ use lib::core::effects::Effects;
 use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::formatting::{printable_styled_string, Pluralize};
 use lib::core::node_descriptors::{
-    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
-    DifferentialRevisionDescriptor, ObsolescenceExplanationDescriptor, Redactor,
-    RelativeTimeDescriptor,
+    get_unique_abbrev_len, BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
+    CommitStatsDescriptor, DifferentialRevisionDescriptor, ObsolescenceExplanationDescriptor,
+    Redactor, RelativeTimeDescriptor,
 };
 use lib::git::{GitRunInfo, Repo};
+use regex::Regex;
 
-pub use graph::{make_smartlog_graph, SmartlogGraph};
-pub use render::{render_graph, SmartlogOptions};
+pub use graph::{
+    filter_smartlog_graph_by_author, make_smartlog_graph, prune_smartlog_graph, SmartlogGraph,
+};
+pub use render::{render_graph, render_graph_dot, render_graph_json, SmartlogOptions};
 
+use crate::opts::SmartlogFormat;
 use crate::revset::resolve_commits;
 
 mod graph {
-    use std::collections::HashMap;
+    use std::cmp::Reverse;
+    use std::collections::{HashMap, HashSet};
     use std::convert::TryFrom;
 
     use eden_dag::DagAlgorithm;
+    use itertools::Itertools;
+    use lib::core::config::get_smartlog_reachability_index;
     use lib::core::gc::mark_commit_reachable;
     use tracing::instrument;
 
-    use lib::core::dag::{commit_set_to_vec_unsorted, CommitSet, Dag};
+    use lib::core::dag::{commit_set_to_vec_unsorted, CommitSet, Dag, ReachabilityIndex};
     use lib::core::effects::{Effects, OperationType};
     use lib::core::eventlog::{EventCursor, EventReplayer};
     use lib::core::node_descriptors::NodeObject;
     use lib::git::{Commit, Time};
     use lib::git::{NonZeroOid, Repo};
+    use regex::Regex;
 
     /// Node contained in the smartlog commit graph.
     #[derive(Debug)]
@@ -83,11 +91,20 @@ mod graph {
         /// where you commit directly to the main branch and then later rewrite the
         /// commit.
         pub is_obsolete: bool,
+
+        /// The number of descendant commits which were removed from the graph
+        /// by `prune_smartlog_graph`. These are rendered as a single line below
+        /// this node.
+        pub num_omitted_descendants: usize,
     }
 
     /// Graph of commits that the user is working on.
     pub struct SmartlogGraph<'repo> {
         pub nodes: HashMap<NonZeroOid, Node<'repo>>,
+
+        /// An index for answering ancestry queries between the nodes, if
+        /// enabled via `branchless.smartlog.reachabilityIndex`.
+        pub reachability_index: Option<ReachabilityIndex>,
     }
 
     impl<'repo> SmartlogGraph<'repo> {
@@ -127,6 +144,7 @@ mod graph {
         dag: &Dag,
         public_commits: &CommitSet,
         active_heads: &CommitSet,
+        use_reachability_index: bool,
     ) -> eyre::Result<SmartlogGraph<'repo>> {
         let mut graph: HashMap<NonZeroOid, Node> = {
             let mut result = HashMap::new();
@@ -159,6 +177,7 @@ mod graph {
                             children: Vec::new(), // populated below
                             is_main: public_commits.contains(&vertex)?,
                             is_obsolete: dag.obsolete_commits.contains(&vertex)?,
+                            num_omitted_descendants: 0,
                         },
                     );
                 }
@@ -166,6 +185,13 @@ mod graph {
             result
         };
 
+        let reachability_index = if use_reachability_index {
+            let node_oids: CommitSet = graph.keys().copied().collect();
+            Some(dag.make_reachability_index(effects, &node_oids)?)
+        } else {
+            None
+        };
+
         // Find immediate parent-child links.
         let links: Vec<(NonZeroOid, NonZeroOid)> = {
             let non_main_node_oids =
@@ -175,8 +201,16 @@ mod graph {
 
             let mut links = Vec::new();
             for child_oid in non_main_node_oids {
-                let parent_vertexes = dag.query().parents(CommitSet::from(*child_oid))?;
-                let parent_oids = commit_set_to_vec_unsorted(&parent_vertexes)?;
+                let parent_oids = match reachability_index
+                    .as_ref()
+                    .and_then(|reachability_index| reachability_index.get_parent_oids(*child_oid))
+                {
+                    Some(parent_oids) => parent_oids,
+                    None => {
+                        let parent_vertexes = dag.query().parents(CommitSet::from(*child_oid))?;
+                        commit_set_to_vec_unsorted(&parent_vertexes)?
+                    }
+                };
                 for parent_oid in parent_oids {
                     if graph.contains_key(&parent_oid) {
                         links.push((*child_oid, parent_oid))
@@ -191,7 +225,10 @@ mod graph {
             graph.get_mut(parent_oid).unwrap().children.push(*child_oid);
         }
 
-        Ok(SmartlogGraph { nodes: graph })
+        Ok(SmartlogGraph {
+            nodes: graph,
+            reachability_index,
+        })
     }
 
     /// Sort children nodes of the commit graph in a standard order, for determinism
@@ -216,6 +253,165 @@ mod graph {
         }
     }
 
+    /// Remove the non-main commits from the graph whose author doesn't match
+    /// `author_pattern`. The pattern is matched against the author's name and
+    /// email in the form `Name <email>`, as with `git log --author`.
+    ///
+    /// Main branch commits are always kept. The children of a removed commit
+    /// are attached to its nearest remaining ancestor, so that the graph stays
+    /// connected.
+    #[instrument]
+    pub fn filter_smartlog_graph_by_author(graph: &mut SmartlogGraph, author_pattern: &Regex) {
+        let keep_oids: HashSet<NonZeroOid> = graph
+            .nodes
+            .iter()
+            .filter(|(_oid, node)| {
+                node.is_main
+                    || match &node.object {
+                        NodeObject::Commit { commit } => {
+                            let author = commit.get_author();
+                            let author = format!(
+                                "{} <{}>",
+                                author.get_name().unwrap_or_default(),
+                                author.get_email().unwrap_or_default()
+                            );
+                            author_pattern.is_match(&author)
+                        }
+                        NodeObject::GarbageCollected { oid: _ } => false,
+                    }
+            })
+            .map(|(oid, _node)| *oid)
+            .collect();
+
+        // For each remaining node, find its nearest remaining descendants
+        // along each path, skipping over removed nodes.
+        let mut new_children: HashMap<NonZeroOid, Vec<NonZeroOid>> = HashMap::new();
+        for oid in keep_oids.iter() {
+            let mut children = Vec::new();
+            let mut seen_oids = HashSet::new();
+            let mut stack: Vec<NonZeroOid> =
+                graph.nodes[oid].children.iter().rev().copied().collect();
+            while let Some(child_oid) = stack.pop() {
+                if !seen_oids.insert(child_oid) {
+                    continue;
+                }
+                if keep_oids.contains(&child_oid) {
+                    children.push(child_oid);
+                } else {
+                    stack.extend(graph.nodes[&child_oid].children.iter().rev().copied());
+                }
+            }
+            new_children.insert(*oid, children);
+        }
+        let new_parents: HashMap<NonZeroOid, Option<NonZeroOid>> = keep_oids
+            .iter()
+            .map(|oid| {
+                let mut parent_oid = graph.nodes[oid].parent;
+                while let Some(oid) = parent_oid {
+                    if keep_oids.contains(&oid) {
+                        break;
+                    }
+                    parent_oid = graph.nodes[&oid].parent;
+                }
+                (*oid, parent_oid)
+            })
+            .collect();
+
+        graph.nodes.retain(|oid, _node| keep_oids.contains(oid));
+        for (oid, node) in graph.nodes.iter_mut() {
+            node.parent = new_parents[oid];
+            node.children = new_children.remove(oid).unwrap_or_default();
+        }
+        sort_children(graph);
+    }
+
+    /// Limit the number of non-main commits shown under each root of the graph
+    /// to the `max_commits` most recent ones (by committer time), and record
+    /// how many were omitted on the root node.
+    ///
+    /// The ancestors of every remaining commit are also kept, so that no
+    /// remaining commit becomes disconnected from its root. `head_oid` is
+    /// always kept, even if it's not among the most recent commits.
+    #[instrument]
+    pub fn prune_smartlog_graph(
+        graph: &mut SmartlogGraph,
+        max_commits: usize,
+        head_oid: Option<NonZeroOid>,
+    ) {
+        let root_oids: Vec<NonZeroOid> = graph
+            .nodes
+            .iter()
+            .filter(|(_oid, node)| node.parent.is_none())
+            .map(|(oid, _node)| *oid)
+            .sorted()
+            .collect();
+
+        let get_descendants = |root_oid: NonZeroOid| -> Vec<NonZeroOid> {
+            let mut descendants = Vec::new();
+            let mut seen_oids = HashSet::new();
+            let mut stack = graph.nodes[&root_oid].children.clone();
+            while let Some(oid) = stack.pop() {
+                if !seen_oids.insert(oid) {
+                    continue;
+                }
+                descendants.push(oid);
+                stack.extend(graph.nodes[&oid].children.iter().copied());
+            }
+            descendants
+        };
+
+        let mut keep_oids: HashSet<NonZeroOid> = root_oids.iter().copied().collect();
+        let mut root_descendants = Vec::new();
+        for root_oid in root_oids {
+            let mut descendants = get_descendants(root_oid);
+            // Most recent first. Garbage-collected commits have no timestamp,
+            // so they're sorted last.
+            descendants.sort_by_key(|oid| {
+                let time = match &graph.nodes[oid].object {
+                    NodeObject::Commit { commit } => Some(commit.get_committer().get_time()),
+                    NodeObject::GarbageCollected { oid: _ } => None,
+                };
+                (Reverse(time), *oid)
+            });
+
+            let newest_oids = descendants
+                .iter()
+                .copied()
+                .take(max_commits)
+                .chain(head_oid.filter(|head_oid| descendants.contains(head_oid)));
+            for oid in newest_oids {
+                // Keep the path back to the root as well.
+                let mut current_oid = Some(oid);
+                while let Some(oid) = current_oid {
+                    if !keep_oids.insert(oid) {
+                        break;
+                    }
+                    current_oid = graph.nodes[&oid].parent;
+                }
+            }
+            root_descendants.push((root_oid, descendants));
+        }
+
+        let mut omitted_oids = HashSet::new();
+        for (root_oid, descendants) in root_descendants {
+            let num_omitted = descendants
+                .into_iter()
+                .filter(|oid| !keep_oids.contains(oid) && omitted_oids.insert(*oid))
+                .count();
+            graph
+                .nodes
+                .get_mut(&root_oid)
+                .unwrap()
+                .num_omitted_descendants = num_omitted;
+        }
+
+        graph.nodes.retain(|oid, _node| !omitted_oids.contains(oid));
+        for node in graph.nodes.values_mut() {
+            node.children
+                .retain(|child_oid| !omitted_oids.contains(child_oid));
+        }
+    }
+
     /// Construct the smartlog graph for the repo.
     #[instrument]
     pub fn make_smartlog_graph<'repo>(
@@ -245,7 +441,14 @@ mod graph {
                 mark_commit_reachable(repo, oid)?;
             }
 
-            walk_from_active_heads(&effects, repo, dag, &public_commits, &active_heads)?
+            walk_from_active_heads(
+                &effects,
+                repo,
+                dag,
+                &public_commits,
+                &active_heads,
+                get_smartlog_reachability_index(repo)?,
+            )?
         };
         sort_children(&mut graph);
         Ok(graph)
@@ -254,20 +457,25 @@ mod graph {
 
 mod render {
     use std::cmp::Ordering;
+    use std::collections::HashSet;
 
-    use cursive::theme::Effect;
+    use bstr::ByteSlice;
+    use cursive::theme::{BaseColor, Effect};
     use cursive::utils::markup::StyledString;
     use eden_dag::DagAlgorithm;
+    use itertools::Itertools;
+    use serde_json::json;
     use tracing::instrument;
 
     use lib::core::dag::{CommitSet, CommitVertex, Dag};
     use lib::core::effects::Effects;
     use lib::core::formatting::set_effect;
     use lib::core::formatting::{Glyphs, StyledStringBuilder};
-    use lib::core::node_descriptors::{render_node_descriptors, NodeDescriptor};
-    use lib::git::{NonZeroOid, Repo};
+    use lib::core::node_descriptors::{render_node_descriptors, NodeDescriptor, NodeObject};
+    use lib::core::repo_ext::RepoReferencesSnapshot;
+    use lib::git::{CategorizedReferenceName, NonZeroOid, Repo};
 
-    use crate::opts::Revset;
+    use crate::opts::{Revset, SmartlogFormat};
 
     use super::graph::SmartlogGraph;
 
@@ -301,6 +509,15 @@ mod render {
                 _ => return lhs_oid.cmp(rhs_oid),
             };
 
+            if let Some(reachability_index) = &graph.reachability_index {
+                if reachability_index.is_ancestor(*lhs_oid, *rhs_oid) == Some(true) {
+                    return Ordering::Less;
+                }
+                if reachability_index.is_ancestor(*rhs_oid, *lhs_oid) == Some(true) {
+                    return Ordering::Greater;
+                }
+            }
+
             let merge_base_oid = dag.get_one_merge_base_oid(effects, repo, *lhs_oid, *rhs_oid);
             let merge_base_oid = match merge_base_oid {
                 Err(_) => return lhs_oid.cmp(rhs_oid),
@@ -351,25 +568,62 @@ mod render {
             (true, true, true) => glyphs.commit_main_obsolete_head,
         };
 
+        let children: Vec<_> = current_node
+            .children
+            .iter()
+            .filter(|child_oid| graph.nodes.contains_key(child_oid))
+            .copied()
+            .collect();
+
+        // The description may span multiple lines (such as when showing the
+        // full commit message). Subsequent lines are aligned with the first
+        // line, and are prefixed with the line leading to this node's
+        // children or to the next root, if any.
+        let mut text_lines = StyledStringBuilder::split_lines(text).into_iter();
+        let omitted_line = match current_node.num_omitted_descendants {
+            0 => None,
+            num_omitted => Some(StyledString::styled(
+                format!("{} ({} more)", glyphs.vertical_ellipsis, num_omitted),
+                BaseColor::Black.light(),
+            )),
+        };
         let first_line = {
             let mut first_line = StyledString::new();
             first_line.append_plain(cursor);
             first_line.append_plain(" ");
-            first_line.append(text);
+            first_line.append(text_lines.next().unwrap_or_default());
             if is_head {
                 set_effect(first_line, Effect::Bold)
             } else {
                 first_line
             }
         };
+        let gutter = if children
+            .iter()
+            .any(|child_oid| !root_oids.contains(child_oid))
+        {
+            Some(glyphs.line)
+        } else {
+            last_child_line_char
+        };
 
         let mut lines = vec![first_line];
-        let children: Vec<_> = current_node
-            .children
-            .iter()
-            .filter(|child_oid| graph.nodes.contains_key(child_oid))
-            .copied()
-            .collect();
+        for text_line in text_lines.chain(omitted_line) {
+            let line = match (gutter, text_line.is_empty()) {
+                (Some(gutter), true) => StyledString::plain(gutter),
+                (Some(gutter), false) => StyledStringBuilder::new()
+                    .append_plain(format!("{} ", gutter))
+                    .append(text_line)
+                    .build(),
+                (None, true) => StyledString::new(),
+                (None, false) => StyledStringBuilder::new()
+                    .append_plain("  ")
+                    .append(text_line)
+                    .build(),
+            };
+            lines.push(line);
+        }
+
         for (child_idx, child_oid) in children.iter().enumerate() {
             if root_oids.contains(child_oid) {
                 // Will be rendered by the parent.
@@ -514,6 +768,210 @@ mod render {
         Ok(lines)
     }
 
+    /// Get the nodes of the graph in the same order that they'd be rendered by
+    /// `render_graph`, but with each node appearing only once.
+    fn get_nodes_in_render_order(
+        graph: &SmartlogGraph,
+        root_oids: &[NonZeroOid],
+    ) -> Vec<NonZeroOid> {
+        let mut ordered_oids = Vec::new();
+        let mut seen_oids = HashSet::new();
+        // Visit the roots in reverse so that the first root is at the top of
+        // the stack.
+        let mut stack: Vec<NonZeroOid> = root_oids.iter().rev().copied().collect();
+        while let Some(oid) = stack.pop() {
+            if !seen_oids.insert(oid) {
+                continue;
+            }
+            ordered_oids.push(oid);
+            stack.extend(
+                graph.nodes[&oid]
+                    .children
+                    .iter()
+                    .rev()
+                    .filter(|child_oid| graph.nodes.contains_key(child_oid)),
+            );
+        }
+        ordered_oids
+    }
+
+    /// Get the links between consecutive roots of the smartlog graph, as
+    /// `(previous_root_oid, root_oid, is_real_parent)`. These are the main
+    /// branch commits, which don't have a parent in the smartlog graph itself.
+    /// If `is_real_parent` is false, then there are omitted commits between
+    /// the two roots, as with the vertical ellipsis in `render_graph`.
+    fn get_root_links(
+        dag: &Dag,
+        root_oids: &[NonZeroOid],
+    ) -> eyre::Result<Vec<(NonZeroOid, NonZeroOid, bool)>> {
+        let mut links = Vec::new();
+        for (previous_root_oid, root_oid) in root_oids.iter().tuple_windows() {
+            let parents = dag.query().parents(CommitSet::from(*root_oid))?;
+            if parents.is_empty()? {
+                continue;
+            }
+            let is_real_parent = parents.contains(&CommitVertex::from(*previous_root_oid))?;
+            links.push((*previous_root_oid, *root_oid, is_real_parent));
+        }
+        Ok(links)
+    }
+
+    /// Serialize the nodes of the smartlog graph as a JSON array, for
+    /// consumption by other tools. Nodes are listed in the same order that
+    /// they'd be rendered by `render_graph`, but each node appears only once.
+    ///
+    /// Consecutive main branch commits are linked through `parent` and
+    /// `children`, as in `render_graph_dot`. `parent_is_direct` is false if
+    /// there are omitted commits between a node and its parent.
+    #[instrument(skip(graph, references_snapshot))]
+    pub fn render_graph_json(
+        effects: &Effects,
+        repo: &Repo,
+        dag: &Dag,
+        graph: &SmartlogGraph,
+        references_snapshot: &RepoReferencesSnapshot,
+    ) -> eyre::Result<serde_json::Value> {
+        let root_oids = split_commit_graph_by_roots(effects, repo, dag, graph);
+        let root_links = get_root_links(dag, &root_oids)?;
+        let mut nodes = Vec::new();
+        for oid in get_nodes_in_render_order(graph, &root_oids) {
+            let node = &graph.nodes[&oid];
+            let (parent, parent_is_direct) = match root_links
+                .iter()
+                .find(|(_previous_root_oid, root_oid, _is_real_parent)| *root_oid == oid)
+            {
+                Some((previous_root_oid, _root_oid, is_real_parent)) => {
+                    (Some(*previous_root_oid), Some(*is_real_parent))
+                }
+                None => (node.parent, node.parent.map(|_| true)),
+            };
+            let children: Vec<String> = root_links
+                .iter()
+                .filter(|(previous_root_oid, _root_oid, _is_real_parent)| *previous_root_oid == oid)
+                .map(|(_previous_root_oid, root_oid, _is_real_parent)| *root_oid)
+                .chain(
+                    node.children
+                        .iter()
+                        .filter(|child_oid| graph.nodes.contains_key(child_oid))
+                        .copied(),
+                )
+                .map(|child_oid| child_oid.to_string())
+                .collect();
+            let summary = match &node.object {
+                NodeObject::Commit { commit } => {
+                    Some(commit.get_summary()?.to_str_lossy().into_owned())
+                }
+                NodeObject::GarbageCollected { oid: _ } => None,
+            };
+            let branches: Vec<String> = match references_snapshot.branch_oid_to_names.get(&oid) {
+                Some(branch_names) => branch_names
+                    .iter()
+                    .map(|branch_name| CategorizedReferenceName::new(branch_name).render_suffix())
+                    .sorted()
+                    .collect(),
+                None => Vec::new(),
+            };
+            nodes.push(json!({
+                "oid": oid.to_string(),
+                "parent": parent.map(|parent_oid| parent_oid.to_string()),
+                "parent_is_direct": parent_is_direct,
+                "children": children,
+                "is_main": node.is_main,
+                "is_obsolete": node.is_obsolete,
+                "is_head": references_snapshot.head_oid == Some(oid),
+                "summary": summary,
+                "branches": branches,
+                "num_omitted_descendants": node.num_omitted_descendants,
+            }));
+        }
+        Ok(serde_json::Value::Array(nodes))
+    }
+
+    /// Render the smartlog graph as a Graphviz `digraph`, which can be piped
+    /// into `dot -Tpng` and similar. Node IDs are full OIDs, and node labels
+    /// contain the abbreviated OID and the commit summary.
+    ///
+    /// Main branch commits are drawn as boxes, obsolete commits are dashed
+    /// and grayed out, and the `HEAD` commit is bold. Consecutive main branch
+    /// commits are connected with a dashed edge if there are omitted commits
+    /// between them, as with the vertical ellipsis in `render_graph`.
+    #[instrument(skip(graph))]
+    pub fn render_graph_dot(
+        effects: &Effects,
+        repo: &Repo,
+        dag: &Dag,
+        graph: &SmartlogGraph,
+        head_oid: Option<NonZeroOid>,
+        abbrev_len: usize,
+    ) -> eyre::Result<String> {
+        fn quote(value: &str) -> String {
+            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+
+        let root_oids = split_commit_graph_by_roots(effects, repo, dag, graph);
+        let mut lines = vec!["digraph smartlog {".to_string()];
+        let ordered_oids = get_nodes_in_render_order(graph, &root_oids);
+        for oid in ordered_oids.iter() {
+            let node = &graph.nodes[oid];
+            let short_oid = &oid.to_string()[..abbrev_len];
+            let summary = match &node.object {
+                NodeObject::Commit { commit } => commit.get_summary()?.to_str_lossy().into_owned(),
+                NodeObject::GarbageCollected { oid: _ } => "<garbage collected>".to_string(),
+            };
+
+            let mut attributes = vec![format!(
+                "label={}",
+                quote(&format!("{short_oid} {summary}"))
+            )];
+            if node.is_main {
+                attributes.push("shape=box".to_string());
+            }
+            let mut styles = Vec::new();
+            if node.is_obsolete {
+                styles.push("dashed");
+                attributes.push("color=gray".to_string());
+                attributes.push("fontcolor=gray".to_string());
+            }
+            if head_oid == Some(*oid) {
+                styles.push("bold");
+            }
+            if !styles.is_empty() {
+                attributes.push(format!("style={}", quote(&styles.join(","))));
+            }
+            lines.push(format!(
+                "  {} [{}];",
+                quote(&oid.to_string()),
+                attributes.join(", ")
+            ));
+        }
+
+        for (previous_root_oid, root_oid, is_real_parent) in get_root_links(dag, &root_oids)? {
+            lines.push(format!(
+                "  {} -> {}{};",
+                quote(&previous_root_oid.to_string()),
+                quote(&root_oid.to_string()),
+                if is_real_parent {
+                    ""
+                } else {
+                    " [style=dashed]"
+                }
+            ));
+        }
+        for oid in ordered_oids.iter() {
+            for child_oid in graph.nodes[oid].children.iter() {
+                if graph.nodes.contains_key(child_oid) {
+                    lines.push(format!(
+                        "  {} -> {};",
+                        quote(&oid.to_string()),
+                        quote(&child_oid.to_string())
+                    ));
+                }
+            }
+        }
+        lines.push("}".to_string());
+        Ok(lines.join("\n"))
+    }
+
     /// Options for rendering the smartlog.
     #[derive(Debug)]
     pub struct SmartlogOptions {
@@ -529,6 +987,25 @@ mod render {
         /// The commits to render. These commits and their ancestors up to the
         /// main branch will be rendered.
         pub revset: Revset,
+
+        /// Whether to show the number of lines added and removed by each
+        /// commit.
+        pub show_stats: bool,
+
+        /// The output format.
+        pub format: SmartlogFormat,
+
+        /// If set, the maximum number of non-main commits to show under each
+        /// main branch commit. See `prune_smartlog_graph`.
+        pub max_commits: Option<usize>,
+
+        /// If set, only show non-main commits whose author matches this
+        /// regular expression. See `filter_smartlog_graph_by_author`.
+        pub author: Option<String>,
+
+        /// Whether to show how far each local branch is ahead of or behind
+        /// its upstream branch, in addition to `branchless.smartlog.showRemote`.
+        pub show_remote: bool,
     }
 
     impl Default for SmartlogOptions {
@@ -537,6 +1014,11 @@ mod render {
                 show_hidden_commits: Default::default(),
                 event_id: Default::default(),
                 revset: Revset("draft()".to_string()),
+                show_stats: Default::default(),
+                format: SmartlogFormat::Text,
+                max_commits: Default::default(),
+                author: Default::default(),
+                show_remote: Default::default(),
             }
         }
     }
@@ -553,9 +1035,15 @@ pub fn smartlog(
         show_hidden_commits,
         event_id,
         revset,
+        show_stats,
+        format,
+        max_commits,
+        author,
+        show_remote,
     } = options;
 
     let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let branchless_config = repo.load_branchless_config()?;
     let head_info = repo.get_head_info()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
@@ -613,7 +1101,7 @@ pub fn smartlog(
         observed_commits
     };
 
-    let graph = make_smartlog_graph(
+    let mut graph = make_smartlog_graph(
         effects,
         &repo,
         &dag,
@@ -622,6 +1110,51 @@ pub fn smartlog(
         &observed_commits,
         !show_hidden_commits,
     )?;
+    if let Some(author) = author {
+        let author_pattern = match Regex::new(author) {
+            Ok(author_pattern) => author_pattern,
+            Err(err) => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Invalid author pattern {author:?}: {err}"
+                )?;
+                return Ok(ExitCode(1));
+            }
+        };
+        filter_smartlog_graph_by_author(&mut graph, &author_pattern);
+    }
+    if let Some(max_commits) = max_commits {
+        prune_smartlog_graph(&mut graph, *max_commits, references_snapshot.head_oid);
+    }
+
+    // Abbreviate the commit hashes enough that the displayed commits can be
+    // distinguished from each other.
+    let abbrev_len = get_unique_abbrev_len(graph.nodes.keys().copied(), get_core_abbrev(&repo)?);
+
+    match format {
+        SmartlogFormat::Text => {}
+        SmartlogFormat::Dot => {
+            let dot = render_graph_dot(
+                effects,
+                &repo,
+                &dag,
+                &graph,
+                references_snapshot.head_oid,
+                abbrev_len,
+            )?;
+            writeln!(effects.get_output_stream(), "{dot}")?;
+            return Ok(ExitCode(0));
+        }
+        SmartlogFormat::Json => {
+            let nodes = render_graph_json(effects, &repo, &dag, &graph, &references_snapshot)?;
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                serde_json::to_string_pretty(&nodes)?
+            )?;
+            return Ok(ExitCode(0));
+        }
+    }
 
     let lines = render_graph(
         effects,
@@ -630,7 +1163,7 @@ pub fn smartlog(
         &graph,
         references_snapshot.head_oid,
         &mut [
-            &mut CommitOidDescriptor::new(true)?,
+            &mut CommitOidDescriptor::new(true, Some(abbrev_len))?,
             &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
             &mut ObsolescenceExplanationDescriptor::new(
                 &event_replayer,
@@ -641,9 +1174,11 @@ pub fn smartlog(
                 &head_info,
                 &references_snapshot,
                 &Redactor::Disabled,
-            )?,
+            )?
+            .with_show_remote(*show_remote || branchless_config.smartlog_show_remote),
             &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
-            &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+            &mut CommitMessageDescriptor::new(&Redactor::Disabled, false)?,
+            &mut CommitStatsDescriptor::new(effects, &repo, *show_stats)?,
         ],
     )?;
     for line in lines {
@@ -654,7 +1189,7 @@ pub fn smartlog(
         )?;
     }
 
-    if !show_hidden_commits && get_hint_enabled(&repo, Hint::SmartlogFixAbandoned)? {
+    if !show_hidden_commits && branchless_config.is_hint_enabled(Hint::SmartlogFixAbandoned) {
         let commits_with_abandoned_children: CommitSet = graph
             .nodes
             .iter()
@@ -692,3 +1227,88 @@ pub fn smartlog(
 
     Ok(ExitCode(0))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib::core::formatting::Glyphs;
+    use lib::testing::make_git;
+
+    #[test]
+    fn test_render_graph_show_body() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        git.commit_file("test1", 1)?;
+        git.run(&["checkout", "--detach"])?;
+        git.write_file("test2", "test2 contents\n")?;
+        git.run(&["add", "."])?;
+        git.run(&[
+            "commit",
+            "-m",
+            "create test2.txt",
+            "-m",
+            "This is the body.\nIt spans multiple lines.",
+            "-m",
+            "And has multiple paragraphs.",
+        ])?;
+        git.write_file("test3", "test3 contents\n")?;
+        git.run(&["add", "."])?;
+        git.run(&["commit", "-m", "create test3.txt", "-m", "Another body."])?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        let event_cursor = event_replayer.make_default_cursor();
+        let references_snapshot = repo.get_references_snapshot()?;
+        let dag = Dag::open_and_sync(
+            &effects,
+            &repo,
+            &event_replayer,
+            event_cursor,
+            &references_snapshot,
+        )?;
+        let head_oid = repo.get_head_info()?.oid;
+        let graph = make_smartlog_graph(
+            &effects,
+            &repo,
+            &dag,
+            &event_replayer,
+            event_cursor,
+            &head_oid.into_iter().collect(),
+            true,
+        )?;
+
+        let lines = render_graph(
+            &effects,
+            &repo,
+            &dag,
+            &graph,
+            head_oid,
+            &mut [
+                &mut CommitOidDescriptor::new(false, None)?,
+                &mut CommitMessageDescriptor::new(&Redactor::Disabled, true)?,
+            ],
+        )?;
+        let lines = lines
+            .into_iter()
+            .map(|line| printable_styled_string(effects.get_glyphs(), line))
+            .collect::<eyre::Result<Vec<_>>>()?;
+        insta::assert_snapshot!(lines.join("\n"), @r###"
+        :
+        O 62fc20d create test1.txt
+        |
+        o 59cb7cd create test2.txt
+        | This is the body.
+        | It spans multiple lines.
+        |
+        | And has multiple paragraphs.
+        |
+        @ cf992f9 create test3.txt
+          Another body.
+        "###);
+
+        Ok(())
+    }
+}
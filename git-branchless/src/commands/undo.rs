@@ -67,7 +67,7 @@ fn render_cursor_smartlog(
         &graph,
         references_snapshot.head_oid,
         &mut [
-            &mut CommitOidDescriptor::new(true)?,
+            &mut CommitOidDescriptor::new(true, None)?,
             &mut RelativeTimeDescriptor::new(repo, SystemTime::now())?,
             &mut ObsolescenceExplanationDescriptor::new(event_replayer, event_cursor)?,
             &mut BranchesDescriptor::new(
@@ -77,7 +77,7 @@ fn render_cursor_smartlog(
                 &Redactor::Disabled,
             )?,
             &mut DifferentialRevisionDescriptor::new(repo, &Redactor::Disabled)?,
-            &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+            &mut CommitMessageDescriptor::new(&Redactor::Disabled, false)?,
         ],
     )?;
     Ok(result)
@@ -375,7 +375,7 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
     Ok(result)
 }
 
-fn describe_events_numbered(
+pub fn describe_events_numbered(
     glyphs: &Glyphs,
     repo: &Repo,
     events: &[Event],
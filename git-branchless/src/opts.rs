@@ -176,6 +176,20 @@ pub enum Command {
         checkout_options: CheckoutOptions,
     },
 
+    /// Show the operations recorded in the event log, from most to least
+    /// recent.
+    EventLog {
+        /// The most recent event to show. If not provided, starts from the
+        /// current event. If negative, is treated as an offset from the
+        /// current event.
+        #[clap(value_parser, long = "event-id")]
+        event_id: Option<isize>,
+
+        /// The maximum number of transactions to show.
+        #[clap(value_parser, short = 'n', long = "max-count", default_value = "10")]
+        max_count: usize,
+    },
+
     /// Run internal garbage collection.
     Gc,
 
@@ -448,6 +462,33 @@ pub enum Command {
         /// main branch will be rendered.
         #[clap(value_parser, default_value = "draft()")]
         revset: Revset,
+
+        /// Show the number of lines added and removed by each commit.
+        #[clap(action, long = "stats")]
+        show_stats: bool,
+
+        /// The output format. `json` prints the nodes of the smartlog graph
+        /// as a JSON array, for consumption by other tools. `dot` prints the
+        /// graph in the Graphviz DOT language, e.g. for `dot -Tpng`.
+        #[clap(value_parser, long = "format", arg_enum, default_value = "text")]
+        format: SmartlogFormat,
+
+        /// Show at most this many commits on top of each main branch commit,
+        /// preferring the most recent ones. The current commit and the
+        /// ancestors of each shown commit are always shown.
+        #[clap(value_parser, long = "max-commits")]
+        max_commits: Option<usize>,
+
+        /// Only show commits whose author matches this regular expression.
+        /// The pattern is matched against `Name <email>`. Main branch commits
+        /// are always shown.
+        #[clap(value_parser, long = "author")]
+        author: Option<String>,
+
+        /// Show how far each local branch is ahead of or behind its upstream
+        /// branch. Can also be enabled with `branchless.smartlog.showRemote`.
+        #[clap(action, long = "show-remote")]
+        show_remote: bool,
     },
 
     #[clap(hide = true)]
@@ -545,6 +586,27 @@ pub enum ColorSetting {
     Never,
 }
 
+/// Which characters to use for rendering.
+#[derive(ArgEnum, Clone)]
+pub enum GlyphsSetting {
+    /// Use Unicode box-drawing characters when writing to a terminal, and
+    /// ASCII characters otherwise. This is the default behavior.
+    Auto,
+    /// Always use ASCII characters.
+    Ascii,
+}
+
+/// The output format for `git smartlog`.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmartlogFormat {
+    /// Render the commit graph for display in the terminal.
+    Text,
+    /// Emit the nodes of the commit graph as a JSON array.
+    Json,
+    /// Emit the commit graph in the Graphviz DOT language.
+    Dot,
+}
+
 /// Branchless workflow for Git.
 ///
 /// See the documentation at <https://github.com/arxanas/git-branchless/wiki>.
@@ -560,6 +622,11 @@ pub struct Opts {
     #[clap(value_parser, long = "color", arg_enum, global = true)]
     pub color: Option<ColorSetting>,
 
+    /// Which characters to use when rendering the smartlog and other output.
+    /// Defaults to the `branchless.glyphs` config setting.
+    #[clap(value_parser, long = "glyphs", arg_enum, global = true)]
+    pub glyphs: Option<GlyphsSetting>,
+
     /// The `git-branchless` subcommand to run.
     #[clap(subcommand)]
     pub command: Command,
@@ -49,6 +49,7 @@ lazy_static! {
             ("committer.name", &fn_committer_name),
             ("committer.email", &fn_committer_email),
             ("committer.date", &fn_committer_date),
+            ("rewritten_by_other", &fn_rewritten_by_other),
             ("exactly", &fn_exactly),
         ];
         functions.iter().cloned().collect()
@@ -266,7 +267,7 @@ fn fn_path_changed(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
         args,
         Box::new(move |repo: &Repo, commit: &Commit| {
             let touched_paths = match repo
-                .get_paths_touched_by_commit(commit)
+                .get_paths_touched_by_commit_cached(commit)
                 .map_err(PatternError::Repo)?
             {
                 Some(touched_paths) => touched_paths,
@@ -381,6 +382,16 @@ fn fn_committer_date(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult
     )
 }
 
+fn fn_rewritten_by_other(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    eval0(ctx, name, args)?;
+    make_pattern_matcher(
+        ctx,
+        name,
+        args,
+        Box::new(|_repo: &Repo, commit: &Commit| Ok(!commit.author_committer_match())),
+    )
+}
+
 fn fn_exactly(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     let (lhs, expected_len) = eval_number_rhs(ctx, name, args)?;
     let actual_len: usize = lhs
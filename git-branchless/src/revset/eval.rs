@@ -1066,6 +1066,96 @@ mod tests {
             "###);
         }
 
+        {
+            let expr = Expr::FunctionCall(Cow::Borrowed("rewritten_by_other"), vec![]);
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: 9ee1994c0737c221efc07acd8d73590d336ee46d,
+                            summary: "test1",
+                        },
+                    },
+                    Commit {
+                        inner: Commit {
+                            id: 05ff2fc6b3e7917ac6800b18077c211e173e8fb4,
+                            summary: "test2",
+                        },
+                    },
+                ],
+            )
+            "###);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_eval_rewritten_by_other() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+
+        git.detach_head()?;
+        git.commit_file("test1", 1)?;
+        git.run_with_options(
+            &["commit", "--amend", "-m", "test1 amended by its author"],
+            &GitRunOptions {
+                time: 2,
+                ..Default::default()
+            },
+        )?;
+        git.commit_file("test3", 3)?;
+        git.run_with_options(
+            &["commit", "--amend", "-m", "test3 amended by someone else"],
+            &GitRunOptions {
+                time: 4,
+                env: {
+                    [
+                        ("GIT_COMMITTER_NAME", "Foo"),
+                        ("GIT_COMMITTER_EMAIL", "foo@example.com"),
+                    ]
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect()
+                },
+                ..Default::default()
+            },
+        )?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        let event_cursor = event_replayer.make_default_cursor();
+        let references_snapshot = repo.get_references_snapshot()?;
+        let mut dag = Dag::open_and_sync(
+            &effects,
+            &repo,
+            &event_replayer,
+            event_cursor,
+            &references_snapshot,
+        )?;
+
+        // Only the commit rewritten by someone other than its author is
+        // included, not the one which the current user amended.
+        {
+            let expr = Expr::FunctionCall(Cow::Borrowed("rewritten_by_other"), vec![]);
+            insta::assert_debug_snapshot!(eval_and_sort(&effects, &repo, &mut dag, &expr), @r###"
+            Ok(
+                [
+                    Commit {
+                        inner: Commit {
+                            id: 7fee13c4759ed7d015d4bd9ba7bed73925544e3c,
+                            summary: "test3 amended by someone else",
+                        },
+                    },
+                ],
+            )
+            "###);
+        }
+
         Ok(())
     }
 
@@ -2,6 +2,8 @@ use std::fmt::Write;
 
 use lib::core::dag::{CommitSet, Dag};
 use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::repo_ext::RepoExt;
 use lib::git::Repo;
 use tracing::instrument;
 
@@ -15,10 +17,21 @@ use super::{eval, parse};
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum ResolveError {
-    ParseError { expr: String, source: ParseError },
-    EvalError { expr: String, source: EvalError },
-    DagError { source: eden_dag::Error },
-    OtherError { source: eyre::Error },
+    ParseError {
+        expr: String,
+        source: ParseError,
+    },
+    EvalError {
+        expr: String,
+        source: EvalError,
+        hint: Option<String>,
+    },
+    DagError {
+        source: eden_dag::Error,
+    },
+    OtherError {
+        source: eyre::Error,
+    },
 }
 
 impl ResolveError {
@@ -33,13 +46,16 @@ impl ResolveError {
                 )?;
                 Ok(())
             }
-            ResolveError::EvalError { expr, source } => {
+            ResolveError::EvalError { expr, source, hint } => {
                 writeln!(
                     effects.get_error_stream(),
                     "Evaluation error for expression '{}': {}",
                     expr,
                     source
                 )?;
+                if let Some(hint) = hint {
+                    writeln!(effects.get_error_stream(), "{}", hint)?;
+                }
                 Ok(())
             }
             ResolveError::DagError { source } => Err(source.into()),
@@ -76,10 +92,18 @@ pub fn resolve_commits(
             expr: revset.clone(),
             source: err,
         })?;
-        let commits = eval(effects, repo, dag, &expr).map_err(|err| ResolveError::EvalError {
-            expr: revset.clone(),
-            source: err,
-        })?;
+        let commits = match eval(effects, repo, dag, &expr) {
+            Ok(commits) => commits,
+            Err(err) => {
+                let hint = describe_unbound_name(effects, repo, &err)
+                    .map_err(|err| ResolveError::OtherError { source: err })?;
+                return Err(ResolveError::EvalError {
+                    expr: revset,
+                    source: err,
+                    hint,
+                });
+            }
+        };
 
         // Note that commits which are identified by `revparse_single_commit`
         // above will still be returned in the result. This ends up resulting in
@@ -93,3 +117,25 @@ pub fn resolve_commits(
     }
     Ok(commit_sets)
 }
+
+/// If `err` is due to a name which looks like a commit hash, explain what
+/// git-branchless knows about the commit, such as whether it was
+/// garbage-collected.
+fn describe_unbound_name(
+    effects: &Effects,
+    repo: &Repo,
+    err: &EvalError,
+) -> eyre::Result<Option<String>> {
+    let name = match err {
+        EvalError::UnboundName { name } => name,
+        _ => return Ok(None),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, &event_log_db)?;
+    Ok(Some(repo.describe_missing_commit(&event_replayer, name)))
+}
@@ -513,3 +513,37 @@ fn test_amend_undo_detached_head() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_amend_ignore_submodule_dirty() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.run(&["init", "--quiet", "submodule"])?;
+    git.run(&["-C", "submodule", "config", "user.name", "Testy McTestface"])?;
+    git.run(&[
+        "-C",
+        "submodule",
+        "config",
+        "user.email",
+        "test@example.com",
+    ])?;
+    git.write_file("submodule/foo", "foo\n")?;
+    git.run(&["-C", "submodule", "add", "foo.txt"])?;
+    git.run(&["-C", "submodule", "commit", "-m", "create foo.txt"])?;
+    git.run(&["add", "submodule"])?;
+    git.run(&["commit", "-m", "add submodule"])?;
+
+    // Dirty the submodule's working copy without changing its commit.
+    git.write_file("submodule/foo", "bar\n")?;
+    git.run(&["config", "branchless.status.ignoreSubmoduleDirty", "true"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["amend"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        There are no uncommitted or staged changes. Nothing to amend.
+        "###);
+    }
+
+    Ok(())
+}
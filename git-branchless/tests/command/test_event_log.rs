@@ -0,0 +1,68 @@
+use lib::testing::make_git;
+
+#[test]
+fn test_event_log() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["hide", "HEAD"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "event-log"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Transaction 7 (event 9): hide
+        1. Hide commit 96d1c37 create test2.txt
+
+        Transaction 6 (event 8): post-commit
+        1. Commit 96d1c37 create test2.txt
+
+        Transaction 5 (event 6): reference-transaction
+        1. Check out from 62fc20d create test1.txt
+                       to 96d1c37 create test2.txt
+        2. Move branch foo from 62fc20d create test1.txt
+                             to 96d1c37 create test2.txt
+
+        Transaction 4 (event 5): hook-post-checkout
+        1. Check out from 62fc20d create test1.txt
+                       to 62fc20d create test1.txt
+
+        Transaction 3 (event 4): reference-transaction
+        1. Create branch foo at 62fc20d create test1.txt
+
+        Transaction 2 (event 3): post-commit
+        1. Commit 62fc20d create test1.txt
+
+        Transaction 1 (event 1): reference-transaction
+        1. Check out from f777ecc create initial.txt
+                       to 62fc20d create test1.txt
+        2. Move branch master from f777ecc create initial.txt
+                                to 62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.run(&["branchless", "event-log", "--event-id=-2", "-n", "1"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Transaction 5 (event 6): reference-transaction
+        1. Check out from 62fc20d create test1.txt
+                       to 96d1c37 create test2.txt
+        2. Move branch foo from 62fc20d create test1.txt
+                             to 96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["branchless", "event-log", "--event-id", "0"])?;
+        insta::assert_snapshot!(stdout, @"There are no previous available events.");
+    }
+
+    Ok(())
+}
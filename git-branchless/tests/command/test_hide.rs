@@ -56,8 +56,46 @@ fn test_hide_bad_commit() -> eyre::Result<()> {
                 ..Default::default()
             },
         )?;
-        insta::assert_snapshot!(stderr, @"Evaluation error for expression 'abc123': no commit, branch, or reference with the name 'abc123' could be found
-");
+        insta::assert_snapshot!(stderr, @r###"
+        Evaluation error for expression 'abc123': no commit, branch, or reference with the name 'abc123' could be found
+        Commit abc123 could not be found, and git-branchless has no record of it. Check that the hash was typed correctly.
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_hide_garbage_collected_commit() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["reset", "--hard", "HEAD^"])?;
+
+    // Simulate garbage collection by deleting the loose object for the commit.
+    let test1_hash = test1_oid.to_string();
+    std::fs::remove_file(
+        git.repo_path
+            .join(".git")
+            .join("objects")
+            .join(&test1_hash[..2])
+            .join(&test1_hash[2..]),
+    )?;
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["hide", &test1_hash[..7]],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Evaluation error for expression '62fc20d': no commit, branch, or reference with the name '62fc20d' could be found
+        Commit 62fc20d could not be found, but git-branchless previously observed it as 62fc20d2a290daea0d52bdc2ed2ad4be6491010e. It was probably garbage-collected.
+        "###);
         insta::assert_snapshot!(stdout, @"");
     }
 
@@ -242,6 +242,85 @@ fn test_init_basic() -> eyre::Result<()> {
     Ok(())
 }
 
+#[cfg(unix)]
+#[test]
+fn test_init_migrate_deprecated_config() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo_with_options(&GitInitOptions {
+        run_branchless_init: false,
+        ..Default::default()
+    })?;
+
+    {
+        std::fs::create_dir_all(git.repo_path.join(".git").join("branchless"))?;
+        git.run(&[
+            "config",
+            "--file",
+            ".git/branchless/config",
+            "branchless.mainBranch",
+            "master",
+        ])?;
+
+        let (stdout, _stderr) = git.run(&["branchless", "init"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Created config file at <repo-path>/.git/branchless/config
+        Renamed deprecated config key branchless.mainBranch to branchless.core.mainBranch
+        Auto-detected your main branch as: master
+        If this is incorrect, run: git config branchless.core.mainBranch <branch>
+        Installing hook: post-commit
+        Installing hook: post-merge
+        Installing hook: post-rewrite
+        Installing hook: post-checkout
+        Installing hook: pre-auto-gc
+        Installing hook: reference-transaction
+        Successfully installed git-branchless.
+        To uninstall, run: git branchless init --uninstall
+        "###);
+
+        let (stdout, _stderr) = git.run(&[
+            "config",
+            "--file",
+            ".git/branchless/config",
+            "--get-regexp",
+            "^branchless\\.",
+        ])?;
+        insta::assert_snapshot!(stdout, @"branchless.core.mainbranch master");
+    }
+
+    {
+        git.run(&[
+            "config",
+            "--file",
+            ".git/branchless/config",
+            "branchless.mainBranch",
+            "foo",
+        ])?;
+
+        let (stdout, _stderr) = git.run(&["branchless", "init"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Created config file at <repo-path>/.git/branchless/config
+        Removed deprecated config key branchless.mainBranch (overridden by branchless.core.mainBranch)
+        Auto-detected your main branch as: master
+        If this is incorrect, run: git config branchless.core.mainBranch <branch>
+        Installing hook: post-commit
+        Installing hook: post-merge
+        Installing hook: post-rewrite
+        Installing hook: post-checkout
+        Installing hook: pre-auto-gc
+        Installing hook: reference-transaction
+        Successfully installed git-branchless.
+        To uninstall, run: git branchless init --uninstall
+        "###);
+    }
+
+    Ok(())
+}
+
 #[cfg(unix)]
 #[test]
 fn test_init_prompt_for_main_branch() -> eyre::Result<()> {
@@ -325,7 +404,7 @@ fn test_main_branch_not_found_error_message() -> eyre::Result<()> {
 
       ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━ SPANTRACE ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
 
-       0: git_branchless::commands::smartlog::smartlog with effects=<Output fancy=false> git_run_info=<GitRunInfo path_to_git="<git-executable>" working_directory="<repo-path>" env=not shown> options=SmartlogOptions { show_hidden_commits: false, event_id: None, revset: Revset("draft()") }
+       0: git_branchless::commands::smartlog::smartlog with effects=<Output fancy=false> git_run_info=<GitRunInfo path_to_git="<git-executable>" working_directory="<repo-path>" env=not shown> options=SmartlogOptions { show_hidden_commits: false, event_id: None, revset: Revset("draft()"), show_stats: false, format: Text, max_commits: None, author: None, show_remote: false }
           at some/file/path.rs:123
 
     Suggestion:
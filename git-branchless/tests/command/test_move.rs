@@ -117,6 +117,52 @@ fn test_move_stick() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_move_on_disk_update_refs() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["checkout", "-b", "foo"])?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.run(&["checkout", "-b", "bar"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "-b", "baz"])?;
+    git.commit_file("test5", 5)?;
+    git.run(&["checkout", "bar"])?;
+
+    git.run(&[
+        "move",
+        "--on-disk",
+        "-s",
+        &test3_oid.to_string(),
+        "-d",
+        &test1_oid.to_string(),
+    ])?;
+
+    let (stdout, _stderr) = git.run(&["smartlog"])?;
+    insta::assert_snapshot!(stdout, @r###"
+    :
+    O 62fc20d create test1.txt
+    |\
+    | o 4838e49 (foo) create test3.txt
+    | |
+    | @ a248207 (> bar) create test4.txt
+    | |
+    | o 566e434 (baz) create test5.txt
+    |
+    O 96d1c37 (master) create test2.txt
+    "###);
+
+    Ok(())
+}
+
 #[test]
 fn test_move_insert_stick() -> eyre::Result<()> {
     let git = make_git()?;
@@ -3131,6 +3177,62 @@ fn test_move_branches_after_move() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_move_reapply_commits_with_empty_patches() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.run(&["config", "core.autocrlf", "false"])?;
+    git.run(&["config", "branchless.diff.normalizeLineEndings", "true"])?;
+    git.commit_file_with_contents("test1", 1, "foo\r\n1\n2\n3\n4\n5\nbar\r\n")?;
+
+    // Both of these commits have empty patches when line endings are
+    // normalized, but they make different changes, so the first should not be
+    // skipped as a duplicate of the second.
+    git.detach_head()?;
+    git.write_file("test1", "foo\n1\n2\n3\n4\n5\nbar\r\n")?;
+    git.run(&["commit", "-a", "-m", "clean up foo"])?;
+    let clean_up_foo_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+    git.run(&["checkout", "master"])?;
+    git.write_file("test1", "foo\r\n1\n2\n3\n4\n5\nbar\n")?;
+    git.run(&["commit", "-a", "-m", "clean up bar"])?;
+    git.run(&["checkout", &clean_up_foo_oid.to_string()])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["move", "--in-memory", "-d", "master"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Committed as: e748020 clean up foo
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout e748020de42180b0792a366493bedf0fcf0b0b8a
+        :
+        O 2a0fd1a (master) clean up bar
+        |
+        @ e748020 clean up foo
+        In-memory rebase succeeded.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--format=%s", "HEAD:test1.txt"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        foo
+        1
+        2
+        3
+        4
+        5
+        bar
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_move_no_reapply_upstream_commits() -> eyre::Result<()> {
     let git = make_git()?;
@@ -3351,8 +3453,8 @@ fn test_move_no_reapply_squashed_commits() -> eyre::Result<()> {
             hint: you can omit the --dest flag in this case, as it defaults to HEAD
             hint: disable this hint by running: git config --global branchless.hint.moveImplicitHeadArgument false
             Attempting rebase in-memory...
-            [1/2] Skipped now-empty commit: e7bcdd6 create test1.txt
-            [2/2] Skipped now-empty commit: 12d361a create test2.txt
+            [1/2] Skipped now-empty commit: 62fc20d create test1.txt
+            [2/2] Skipped now-empty commit: 96d1c37 create test2.txt
             branchless: processing 2 rewritten commits
             branchless: running command: <git-executable> checkout master
             :
@@ -3981,7 +4083,7 @@ fn test_move_orphaned_root() -> eyre::Result<()> {
             "###);
             insta::assert_snapshot!(stdout, @r###"
             Attempting rebase in-memory...
-            [1/2] Skipped now-empty commit: 270b681 new root
+            [1/2] Skipped now-empty commit: da90168 new root
             [2/2] Committed as: 70deb1e create test3.txt
             branchless: processing 1 update: branch new-root
             branchless: processing 2 rewritten commits
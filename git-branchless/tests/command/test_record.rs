@@ -1,4 +1,5 @@
 use crate::util::{run_in_pty, PtyAction};
+use lib::git::GitVersion;
 use lib::testing::{make_git, GitInitOptions, GitRunOptions};
 
 #[test]
@@ -42,6 +43,32 @@ fn test_record_unstaged_changes() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_record_stamp_change_id() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? || git.get_version()? < GitVersion(2, 32, 0) {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&["config", "branchless.commit.stampChangeId", "true"])?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file("test1", "contents1\n")?;
+    git.run(&["record", "-m", "foo"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%B"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        foo
+
+        Change-Id: I025c488d2113d61cd3ce92835c5ba25c2749c094
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_record_unstaged_changes_interactive() -> eyre::Result<()> {
     let git = make_git()?;
@@ -18,6 +18,27 @@ fn test_init_smartlog() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_smartlog_core_abbrev() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["config", "core.abbrev", "10"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc9b0 (master) create initial.txt
+        |
+        @ 62fc20d2a2 create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_show_reachable_commit() -> eyre::Result<()> {
     let git = make_git()?;
@@ -633,3 +654,469 @@ fn test_smartlog_hint_abandoned_except_current_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_smartlog_stats() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("test1", "updated contents\nanother line\n")?;
+    git.run(&["commit", "-a", "-m", "update test1"])?;
+    git.run(&["checkout", "-b", "foo", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.run_with_options(
+        &["merge", "--no-ff", "-m", "merge", "HEAD@{2}"],
+        &GitRunOptions {
+            time: 3,
+            ..Default::default()
+        },
+    )?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--stats"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt +1/-0
+        |\
+        | o 62fc20d create test1.txt +1/-0
+        | |
+        | o 5a8fe3a update test1 +2/-1
+        | |
+        | @ 6dc9712 (> foo) merge
+        |
+        o fe65c1f create test2.txt +1/-0
+        |
+        @ 6dc9712 (> foo) merge
+        "###);
+    }
+
+    {
+        // Stats aren't shown by default.
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |
+        | o 5a8fe3a update test1
+        | |
+        | @ 6dc9712 (> foo) merge
+        |
+        o fe65c1f create test2.txt
+        |
+        @ 6dc9712 (> foo) merge
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_json() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--format", "json"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        [
+          {
+            "branches": [],
+            "children": [
+              "98b9119d16974f372e76cb64a3b77c528fc0b18b",
+              "62fc20d2a290daea0d52bdc2ed2ad4be6491010e"
+            ],
+            "is_head": false,
+            "is_main": true,
+            "is_obsolete": false,
+            "num_omitted_descendants": 0,
+            "oid": "f777ecc9b0db5ed372b2615695191a8a17f79f24",
+            "parent": null,
+            "parent_is_direct": null,
+            "summary": "create initial.txt"
+          },
+          {
+            "branches": [
+              "foo"
+            ],
+            "children": [
+              "96d1c37a3d4363611c49f7e52186e189a04c531f"
+            ],
+            "is_head": false,
+            "is_main": false,
+            "is_obsolete": false,
+            "num_omitted_descendants": 0,
+            "oid": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+            "parent": "f777ecc9b0db5ed372b2615695191a8a17f79f24",
+            "parent_is_direct": true,
+            "summary": "create test1.txt"
+          },
+          {
+            "branches": [],
+            "children": [],
+            "is_head": false,
+            "is_main": false,
+            "is_obsolete": false,
+            "num_omitted_descendants": 0,
+            "oid": "96d1c37a3d4363611c49f7e52186e189a04c531f",
+            "parent": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+            "parent_is_direct": true,
+            "summary": "create test2.txt"
+          },
+          {
+            "branches": [
+              "master"
+            ],
+            "children": [],
+            "is_head": true,
+            "is_main": true,
+            "is_obsolete": false,
+            "num_omitted_descendants": 0,
+            "oid": "98b9119d16974f372e76cb64a3b77c528fc0b18b",
+            "parent": "f777ecc9b0db5ed372b2615695191a8a17f79f24",
+            "parent_is_direct": true,
+            "summary": "create test3.txt"
+          }
+        ]
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_dot() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["commit", "--amend", "-m", "amended \"test3\""])?;
+    git.run(&["checkout", &test2_oid.to_string()])?;
+    git.run(&["commit", "--amend", "-m", "amended test2"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--format", "dot", "--hidden"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        digraph smartlog {
+          "f777ecc9b0db5ed372b2615695191a8a17f79f24" [label="f777ecc create initial.txt", shape=box];
+          "62fc20d2a290daea0d52bdc2ed2ad4be6491010e" [label="62fc20d create test1.txt"];
+          "cb8137adb1d2a166d27eeaf6bfc39a374748852c" [label="cb8137a amended test2", style="bold"];
+          "96d1c37a3d4363611c49f7e52186e189a04c531f" [label="96d1c37 create test2.txt", color=gray, fontcolor=gray, style="dashed"];
+          "98b9119d16974f372e76cb64a3b77c528fc0b18b" [label="98b9119 create test3.txt", color=gray, fontcolor=gray, style="dashed"];
+          "dc150e1d92b8a15fa971c86743c129e4f5f36fcd" [label="dc150e1 amended \"test3\"", shape=box];
+          "f777ecc9b0db5ed372b2615695191a8a17f79f24" -> "dc150e1d92b8a15fa971c86743c129e4f5f36fcd";
+          "f777ecc9b0db5ed372b2615695191a8a17f79f24" -> "62fc20d2a290daea0d52bdc2ed2ad4be6491010e";
+          "f777ecc9b0db5ed372b2615695191a8a17f79f24" -> "98b9119d16974f372e76cb64a3b77c528fc0b18b";
+          "62fc20d2a290daea0d52bdc2ed2ad4be6491010e" -> "cb8137adb1d2a166d27eeaf6bfc39a374748852c";
+          "62fc20d2a290daea0d52bdc2ed2ad4be6491010e" -> "96d1c37a3d4363611c49f7e52186e189a04c531f";
+        }
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_ascii_glyphs() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--color", "always"])?;
+        assert!(!stdout.is_ascii());
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--color", "always", "--glyphs", "ascii"])?;
+        assert!(stdout.is_ascii());
+        // Colors are still rendered.
+        assert!(stdout.contains('\x1b'));
+    }
+
+    git.run(&["config", "branchless.glyphs", "ascii"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--color", "always"])?;
+        assert!(stdout.is_ascii());
+        assert!(stdout.contains('\x1b'));
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--color", "always", "--glyphs", "auto"])?;
+        assert!(!stdout.is_ascii());
+    }
+
+    // Unknown values are reported, but don't prevent the command from running.
+    git.run(&["config", "branchless.glyphs", "foo"])?;
+    {
+        let (stdout, stderr) = git.run(&["smartlog", "--color", "always"])?;
+        assert!(!stdout.is_ascii());
+        insta::assert_snapshot!(stderr, @r###"
+        Warning: Unsupported value for branchless.glyphs: "foo" (expected "ascii" or "auto")
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_max_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test4", 4)?;
+    git.commit_file("test5", 5)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |
+        | o 96d1c37 create test2.txt
+        | |
+        | o 70deb1e create test3.txt
+        |
+        @ 8f7aef5 create test4.txt
+        |
+        o 47d30fa create test5.txt
+        "###);
+    }
+
+    {
+        // `test5` is the most recent commit, and its ancestor `test4` (which
+        // is also `HEAD`) is kept.
+        let (stdout, _stderr) = git.run(&["smartlog", "--max-commits", "1"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        | : (3 more)
+        |
+        @ 8f7aef5 create test4.txt
+        |
+        o 47d30fa create test5.txt
+        "###);
+    }
+
+    {
+        // `HEAD` is always kept, along with its ancestors.
+        git.run(&["checkout", &test2_oid.to_string()])?;
+        let (stdout, _stderr) = git.run(&["smartlog", "--max-commits", "0"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        | : (3 more)
+        |
+        o 62fc20d create test1.txt
+        |
+        @ 96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_author() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("test2", "test2 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run_with_options(
+        &[
+            "commit",
+            "-m",
+            "create test2.txt",
+            "--author",
+            "Other Author <other@example.com>",
+        ],
+        &GitRunOptions {
+            time: 2,
+            ..Default::default()
+        },
+    )?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--author", "Testy"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ af22836 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog", "--author", "other@example"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 7b4f92d create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.run_with_options(
+            &["smartlog", "--author", "("],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Invalid author pattern "(": regex parse error:
+            (
+            ^
+        error: unclosed group
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_show_remote() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    {
+        original_repo.init_repo()?;
+        original_repo.commit_file("test1", 1)?;
+        original_repo.run(&["branch", "feature"])?;
+        original_repo.run(&[
+            "clone",
+            original_repo.repo_path.to_str().unwrap(),
+            cloned_repo.repo_path.to_str().unwrap(),
+        ])?;
+        original_repo.commit_file("test2", 2)?;
+        original_repo.run(&["branch", "-D", "feature"])?;
+    }
+
+    {
+        cloned_repo.init_repo_with_options(&GitInitOptions {
+            make_initial_commit: false,
+            ..Default::default()
+        })?;
+        cloned_repo.run(&["branch", "--track", "feature", "origin/feature"])?;
+        cloned_repo.run(&["branch", "untracked"])?;
+        cloned_repo.commit_file("test3", 3)?;
+        cloned_repo.run(&["fetch", "--prune"])?;
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d (feature, untracked) create test1.txt
+        |\
+        | @ 4838e49 (> master) create test3.txt
+        |
+        O 96d1c37 (remote origin/master) create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog", "--show-remote"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d (feature [gone], untracked) create test1.txt
+        |\
+        | @ 4838e49 (> master [ahead 1, behind 1]) create test3.txt
+        |
+        O 96d1c37 (remote origin/master) create test2.txt
+        "###);
+    }
+
+    {
+        cloned_repo.run(&["config", "branchless.smartlog.showRemote", "true"])?;
+        let (stdout, _stderr) = cloned_repo.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d (feature [gone], untracked) create test1.txt
+        |\
+        | @ 4838e49 (> master [ahead 1, behind 1]) create test3.txt
+        |
+        O 96d1c37 (remote origin/master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_reachability_index() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", "-b", "test3", &test1_oid.to_string()])?;
+    git.commit_file("test3", 3)?;
+    git.run_with_options(
+        &["merge", &test2_oid.to_string()],
+        &GitRunOptions {
+            time: 4,
+            ..Default::default()
+        },
+    )?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test5", 5)?;
+    git.detach_head()?;
+    git.commit_file("test6", 6)?;
+
+    let (expected_stdout, _stderr) = git.run(&["smartlog"])?;
+    git.run(&["config", "branchless.smartlog.reachabilityIndex", "true"])?;
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        assert_eq!(stdout, expected_stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |\
+        | | o 96d1c37 create test2.txt
+        | | |
+        | | o 4b290b4 (test3) Merge commit '96d1c37a3d4363611c49f7e52186e189a04c531f' into test3
+        | |
+        | o 4838e49 create test3.txt
+        | |
+        | o 4b290b4 (test3) Merge commit '96d1c37a3d4363611c49f7e52186e189a04c531f' into test3
+        |
+        O aff9c67 (master) create test5.txt
+        |
+        @ ed2ab67 create test6.txt
+        "###);
+    }
+
+    Ok(())
+}
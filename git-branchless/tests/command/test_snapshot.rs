@@ -532,3 +532,102 @@ fn test_snapshot_restore_unborn_head() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_snapshot_from_subdirectory() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file("subdir/test2", "test2 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create subdir/test2.txt"])?;
+    git.write_file("test1", "test1 new contents\n")?;
+
+    // The paths reported by `git status` are relative to the repository root,
+    // not to the current directory.
+    let snapshot_oid = {
+        let (snapshot_oid, _stderr) =
+            git.run(&["-C", "subdir", "branchless", "snapshot", "create"])?;
+        NonZeroOid::from_str(snapshot_oid.trim())?
+    };
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain=2"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    git.run(&[
+        "branchless",
+        "snapshot",
+        "restore",
+        &snapshot_oid.to_string(),
+    ])?;
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain=2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        1 .M N... 100644 100644 100644 7432a8fff25da8f35a9960893ad6155d1d150d39 7432a8fff25da8f35a9960893ad6155d1d150d39 test1.txt
+        "###);
+    }
+    assert_eq!(
+        std::fs::read_to_string(git.repo_path.join("test1.txt"))?,
+        "test1 new contents\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_submodule() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.run(&["init", "--quiet", "submodule"])?;
+    git.run(&["-C", "submodule", "config", "user.name", "Testy McTestface"])?;
+    git.run(&[
+        "-C",
+        "submodule",
+        "config",
+        "user.email",
+        "test@example.com",
+    ])?;
+    git.write_file("submodule/foo", "foo\n")?;
+    git.run(&["-C", "submodule", "add", "foo.txt"])?;
+    git.run(&["-C", "submodule", "commit", "-m", "create foo.txt"])?;
+    git.run(&["add", "submodule"])?;
+    git.run(&["commit", "-m", "add submodule"])?;
+    git.write_file("submodule/foo", "bar\n")?;
+    git.run(&["-C", "submodule", "commit", "-a", "-m", "update foo.txt"])?;
+    let (submodule_oid, _stderr) = git.run(&["-C", "submodule", "rev-parse", "HEAD"])?;
+
+    // The submodule is recorded as the commit it has checked out, rather than
+    // as the contents of its directory.
+    let snapshot_oid = {
+        let (snapshot_oid, _stderr) = git.run(&["branchless", "snapshot", "create"])?;
+        NonZeroOid::from_str(snapshot_oid.trim())?
+    };
+    git.run(&[
+        "branchless",
+        "snapshot",
+        "restore",
+        &snapshot_oid.to_string(),
+    ])?;
+    {
+        let (stdout, _stderr) = git.run(&["diff", "--submodule=short"])?;
+        assert!(
+            stdout.contains(&format!("+Subproject commit {}", submodule_oid.trim())),
+            "{}",
+            stdout
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use lib::core::check_out::{
+    check_out_commit_with_autostash, with_working_copy_snapshot, CheckOutCommitOptions,
+    CheckoutTarget,
+};
+use lib::core::effects::Effects;
+use lib::core::eventlog::EventLogDb;
+use lib::core::formatting::Glyphs;
+use lib::testing::make_git;
+
+#[test]
+fn test_with_working_copy_snapshot_restores_on_error() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.write_file("test1", "uncommitted contents\n")?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let git_run_info = git.get_git_run_info();
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test")?;
+
+    let result: eyre::Result<()> = with_working_copy_snapshot(
+        &effects,
+        &git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        |_repo| {
+            git.write_file("test1", "modified by operation\n")?;
+            git.run(&["commit", "-a", "-m", "operation commit"])?;
+            eyre::bail!("operation failed")
+        },
+    );
+    insta::assert_snapshot!(result.unwrap_err(), @"operation failed");
+
+    {
+        let (stdout, _stderr) = git.run(&["smartlog"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d (> master) create test1.txt
+        |
+        o 66fc9f7 operation commit
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @" M test1.txt");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_with_working_copy_snapshot_success() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.write_file("initial", "uncommitted contents\n")?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let git_run_info = git.get_git_run_info();
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test")?;
+
+    let value = with_working_copy_snapshot(
+        &effects,
+        &git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        |_repo| {
+            git.write_file("test1", "new file\n")?;
+            Ok(42)
+        },
+    )?;
+    assert_eq!(value, 42);
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @r###"
+         M initial.txt
+        ?? test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_check_out_commit_with_autostash() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    let test1_oid = git.commit_file_with_contents("test1", 1, "a\nb\nc\n")?;
+    git.commit_file_with_contents("test1", 2, "A\nb\nc\n")?;
+    git.write_file("test1", "A\nb\nC\n")?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let git_run_info = git.get_git_run_info();
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test")?;
+
+    let exit_code = check_out_commit_with_autostash(
+        &effects,
+        &git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        Some(CheckoutTarget::Oid(test1_oid)),
+        &CheckOutCommitOptions {
+            additional_args: Default::default(),
+            render_smartlog: false,
+        },
+    )?;
+    assert!(exit_code.is_success());
+    assert_eq!(repo.get_head_info()?.oid, Some(test1_oid));
+    assert!(repo.get_stashes()?.is_empty());
+
+    {
+        let (stdout, _stderr) = git.run(&["diff"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/test1.txt b/test1.txt
+        index de98044..6dcce7d 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1,3 +1,3 @@
+         a
+         b
+        -c
+        +C
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_check_out_commit_with_autostash_no_overwrite() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file("test1", "updated contents\n")?;
+
+    let stdout: Arc<Mutex<Vec<u8>>> = Default::default();
+    let stderr: Arc<Mutex<Vec<u8>>> = Default::default();
+    let effects = Effects::new_from_buffer_for_test(Glyphs::text(), &stdout, &stderr);
+    let git_run_info = git.get_git_run_info();
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test")?;
+
+    let exit_code = check_out_commit_with_autostash(
+        &effects,
+        &git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        Some(CheckoutTarget::Oid(test1_oid)),
+        &CheckOutCommitOptions {
+            additional_args: Default::default(),
+            render_smartlog: false,
+        },
+    )?;
+    assert!(exit_code.is_success());
+    assert_eq!(repo.get_head_info()?.oid, Some(test1_oid));
+
+    // The checkout doesn't touch `test1.txt`, so the changes are carried over
+    // without stashing them.
+    let stderr = String::from_utf8(stderr.lock().unwrap().clone())?;
+    let stderr = git.preprocess_output(stderr)?;
+    insta::assert_snapshot!(stderr, @r###"
+    branchless: creating working copy snapshot
+    branchless: processing 1 update: ref HEAD
+    HEAD is now at 62fc20d create test1.txt
+    branchless: processing checkout
+    "###);
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @" M test1.txt");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_check_out_commit_with_autostash_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    let test1_oid = git.commit_file_with_contents("test1", 1, "a\nb\nc\n")?;
+    git.commit_file_with_contents("test1", 2, "A\nb\nc\n")?;
+    git.write_file("test1", "conflicting\nb\nc\n")?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let git_run_info = git.get_git_run_info();
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test")?;
+
+    let exit_code = check_out_commit_with_autostash(
+        &effects,
+        &git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        Some(CheckoutTarget::Oid(test1_oid)),
+        &CheckOutCommitOptions {
+            additional_args: Default::default(),
+            render_smartlog: false,
+        },
+    )?;
+    assert!(!exit_code.is_success());
+    assert_eq!(repo.get_head_info()?.oid, Some(test1_oid));
+
+    // The changes are still available in the stash.
+    let stashes = repo.get_stashes()?;
+    insta::assert_debug_snapshot!(
+        stashes.iter().map(|stash| &stash.message).collect::<Vec<_>>(),
+        @r###"
+    [
+        "On master: branchless: automatic stash for checkout",
+    ]
+    "###
+    );
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"UU test1.txt");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use lib::core::config::Hint;
+use lib::core::dag::CommitSet;
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::Glyphs;
+use lib::core::repo_ext::RepoExt;
+use lib::git::DEFAULT_CHANGE_ID_TRAILER;
+use lib::testing::make_git;
+
+#[test]
+fn test_get_commit_count_by_author() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.write_file("test2", "test2 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&[
+        "commit",
+        "-m",
+        "create test2.txt",
+        "--author",
+        "Other Author <other@example.com>",
+    ])?;
+    git.write_file("test3", "test3 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&[
+        "commit",
+        "-m",
+        "create test3.txt",
+        "--author",
+        "Other Alias <alias@example.com>",
+    ])?;
+
+    let repo = git.get_repo()?;
+    let test2_oid = repo.revparse_single_commit("HEAD^")?.unwrap().get_oid();
+    let test3_oid = repo.get_head_info()?.oid.unwrap();
+    let commits: CommitSet = [test1_oid, test2_oid, test3_oid].iter().copied().collect();
+
+    {
+        let counts: BTreeMap<_, _> = repo
+            .get_commit_count_by_author(&commits)?
+            .into_iter()
+            .collect();
+        insta::assert_debug_snapshot!(counts, @r###"
+        {
+            "Other Alias <alias@example.com>": 1,
+            "Other Author <other@example.com>": 1,
+            "Testy McTestface <test@example.com>": 1,
+        }
+        "###);
+    }
+
+    std::fs::write(
+        git.repo_path.join(".mailmap"),
+        "Other Author <other@example.com> Other Alias <alias@example.com>\n",
+    )?;
+    {
+        let counts: BTreeMap<_, _> = repo
+            .get_commit_count_by_author(&commits)?
+            .into_iter()
+            .collect();
+        insta::assert_debug_snapshot!(counts, @r###"
+        {
+            "Other Author <other@example.com>": 2,
+            "Testy McTestface <test@example.com>": 1,
+        }
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_load_branchless_config() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let repo = git.get_repo()?;
+    let config = repo.load_branchless_config()?;
+    assert_eq!(config.main_branch_name, "master");
+    assert!(config.undo_create_snapshots);
+    assert!(!config.next_interactive);
+    assert_eq!(config.commit_change_id_trailer, DEFAULT_CHANGE_ID_TRAILER);
+    assert_eq!(config.commit_descriptors_pull_request_trailer, None);
+    assert!(config.is_hint_enabled(Hint::MoveImplicitHeadArgument));
+
+    git.run(&["config", "branchless.core.mainBranch", "main"])?;
+    git.run(&["config", "branchless.next.interactive", "true"])?;
+    git.run(&[
+        "config",
+        "branchless.commitDescriptors.pullRequestTrailer",
+        "Pull-Request",
+    ])?;
+    git.run(&[
+        "config",
+        "branchless.hint.moveImplicitHeadArgument",
+        "false",
+    ])?;
+    let config = repo.load_branchless_config()?;
+    assert_eq!(config.main_branch_name, "main");
+    assert!(config.next_interactive);
+    assert_eq!(
+        config.commit_descriptors_pull_request_trailer,
+        Some("Pull-Request".to_string())
+    );
+    assert!(!config.is_hint_enabled(Hint::MoveImplicitHeadArgument));
+    assert!(config.is_hint_enabled(Hint::SmartlogFixAbandoned));
+
+    Ok(())
+}
+
+#[test]
+fn test_describe_missing_commit() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["reset", "--hard", "HEAD^"])?;
+
+    // Simulate garbage collection by deleting the loose object for the commit.
+    let test2_hash = test2_oid.to_string();
+    std::fs::remove_file(
+        git.repo_path
+            .join(".git")
+            .join("objects")
+            .join(&test2_hash[..2])
+            .join(&test2_hash[2..]),
+    )?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+
+    let descriptions = [&test2_hash[..7], "abcdef0", "HEAD~2"]
+        .iter()
+        .map(|commit_hash| repo.describe_missing_commit(&event_replayer, commit_hash))
+        .collect::<Vec<_>>()
+        .join("\n");
+    insta::assert_snapshot!(descriptions, @r###"
+    Commit 96d1c37 could not be found, but git-branchless previously observed it as 96d1c37a3d4363611c49f7e52186e189a04c531f. It was probably garbage-collected.
+    Commit abcdef0 could not be found, and git-branchless has no record of it. Check that the hash was typed correctly.
+    "HEAD~2" is not a valid commit hash.
+    "###);
+
+    Ok(())
+}
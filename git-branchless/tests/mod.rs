@@ -1,14 +1,17 @@
 mod util;
 
 mod core {
+    mod test_check_out;
     mod test_eventlog;
     mod test_gc;
     mod test_hooks;
+    mod test_repo_ext;
 }
 
 mod command {
     mod test_amend;
     mod test_bug_report;
+    mod test_event_log;
     mod test_hide;
     mod test_init;
     mod test_move;
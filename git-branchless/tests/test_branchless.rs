@@ -83,3 +83,31 @@ fn test_profiling() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_branchless_trace() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let trace_path = git.repo_path.join("branchless-trace.json");
+    git.run_with_options(
+        &["smartlog"],
+        &GitRunOptions {
+            env: {
+                let mut env: HashMap<String, String> = HashMap::new();
+                env.insert(
+                    "BRANCHLESS_TRACE".to_string(),
+                    trace_path.to_str().unwrap().to_string(),
+                );
+                env
+            },
+            ..Default::default()
+        },
+    )?;
+
+    let trace = std::fs::read_to_string(&trace_path)?;
+    assert!(trace.trim_start().starts_with('['), "{}", trace);
+    assert!(trace.contains("smartlog"), "{}", trace);
+
+    Ok(())
+}